@@ -0,0 +1,13 @@
+-- Your SQL goes here
+
+CREATE TABLE "webhook_events"(
+	"id" VARCHAR NOT NULL PRIMARY KEY,
+	"event_type" VARCHAR NOT NULL,
+	"payload" JSONB NOT NULL,
+	"received_at" TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	"processed_at" TIMESTAMPTZ,
+	"attempts" INT4 NOT NULL DEFAULT 0,
+	"last_error" VARCHAR
+);
+
+CREATE INDEX "webhook_events_event_type_index" ON "webhook_events"("event_type");
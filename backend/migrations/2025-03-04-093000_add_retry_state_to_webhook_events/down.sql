@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX IF EXISTS "webhook_events_status_next_attempt_at_index";
+
+ALTER TABLE "webhook_events"
+	DROP COLUMN IF EXISTS "status",
+	DROP COLUMN IF EXISTS "next_attempt_at";
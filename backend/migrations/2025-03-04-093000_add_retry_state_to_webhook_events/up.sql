@@ -0,0 +1,9 @@
+-- Your SQL goes here
+
+ALTER TABLE "webhook_events"
+	ADD COLUMN "status" VARCHAR NOT NULL DEFAULT 'pending',
+	ADD COLUMN "next_attempt_at" TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+UPDATE "webhook_events" SET "status" = 'processed' WHERE "processed_at" IS NOT NULL;
+
+CREATE INDEX "webhook_events_status_next_attempt_at_index" ON "webhook_events"("status", "next_attempt_at");
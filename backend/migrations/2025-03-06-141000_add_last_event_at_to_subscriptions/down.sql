@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE "subscriptions" DROP COLUMN IF EXISTS "last_event_at";
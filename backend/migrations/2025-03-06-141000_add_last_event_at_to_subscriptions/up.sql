@@ -0,0 +1,3 @@
+-- Your SQL goes here
+
+ALTER TABLE "subscriptions" ADD COLUMN "last_event_at" TIMESTAMPTZ;
@@ -0,0 +1,26 @@
+-- Your SQL goes here
+
+CREATE TABLE "products"(
+	"id" VARCHAR NOT NULL PRIMARY KEY,
+	"name" VARCHAR NOT NULL,
+	"description" VARCHAR,
+	"active" BOOL NOT NULL,
+	"created_at" TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	"updated_at" TIMESTAMPTZ
+);
+
+CREATE TABLE "prices"(
+	"id" VARCHAR NOT NULL PRIMARY KEY,
+	"product_id" VARCHAR NOT NULL,
+	"active" BOOL NOT NULL,
+	"currency" VARCHAR NOT NULL,
+	"unit_amount" INT8,
+	"nickname" VARCHAR,
+	"recurring_interval" VARCHAR,
+	"recurring_interval_count" INT4,
+	"created_at" TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	"updated_at" TIMESTAMPTZ,
+	FOREIGN KEY ("product_id") REFERENCES "products"("id") ON DELETE CASCADE
+);
+
+CREATE INDEX "prices_product_id_index" ON "prices"("product_id");
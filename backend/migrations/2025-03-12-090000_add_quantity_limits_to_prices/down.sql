@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE "prices" DROP COLUMN IF EXISTS "max_quantity";
+ALTER TABLE "prices" DROP COLUMN IF EXISTS "min_quantity";
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+ALTER TABLE "prices" ADD COLUMN "min_quantity" INT4 NOT NULL DEFAULT 1;
+ALTER TABLE "prices" ADD COLUMN "max_quantity" INT4;
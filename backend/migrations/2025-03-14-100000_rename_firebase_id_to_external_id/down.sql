@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+ALTER INDEX "users_external_id_index" RENAME TO "users_firebase_id_index";
+ALTER TABLE "users" RENAME COLUMN "external_id" TO "firebase_id";
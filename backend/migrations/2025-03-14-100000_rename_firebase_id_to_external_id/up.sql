@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+ALTER TABLE "users" RENAME COLUMN "firebase_id" TO "external_id";
+ALTER INDEX "users_firebase_id_index" RENAME TO "users_external_id_index";
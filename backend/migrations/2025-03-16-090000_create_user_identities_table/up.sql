@@ -0,0 +1,20 @@
+-- Your SQL goes here
+
+CREATE TABLE "user_identities"(
+	"id" INT4 NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+	"user_id" UUID NOT NULL,
+	"provider" VARCHAR NOT NULL,
+	"subject" VARCHAR NOT NULL,
+	"email" VARCHAR NOT NULL,
+	"email_verified" BOOL NOT NULL DEFAULT FALSE,
+	"created_at" TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	"updated_at" TIMESTAMPTZ,
+	FOREIGN KEY ("user_id") REFERENCES "users"("id") ON DELETE CASCADE,
+	UNIQUE ("provider", "subject")
+);
+CREATE INDEX "user_identities_user_id_index" ON "user_identities"("user_id");
+
+-- Every existing user signed in through Firebase. Verification is unknown
+-- until the next login refreshes it.
+INSERT INTO "user_identities"("user_id", "provider", "subject", "email")
+SELECT "id", 'firebase', "external_id", "email" FROM "users";
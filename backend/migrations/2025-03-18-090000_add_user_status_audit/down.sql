@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+DROP TABLE IF EXISTS "user_status_changes";
+ALTER TABLE "users" DROP COLUMN "status_reason";
@@ -0,0 +1,16 @@
+-- Your SQL goes here
+
+ALTER TABLE "users" ADD COLUMN "status_reason" VARCHAR;
+
+CREATE TABLE "user_status_changes"(
+	"id" INT4 NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+	"user_id" UUID NOT NULL,
+	"actor_id" UUID,
+	"old_status" VARCHAR NOT NULL,
+	"new_status" VARCHAR NOT NULL,
+	"reason" VARCHAR,
+	"created_at" TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	FOREIGN KEY ("user_id") REFERENCES "users"("id") ON DELETE CASCADE,
+	FOREIGN KEY ("actor_id") REFERENCES "users"("id") ON DELETE SET NULL
+);
+CREATE INDEX "user_status_changes_user_id_index" ON "user_status_changes"("user_id");
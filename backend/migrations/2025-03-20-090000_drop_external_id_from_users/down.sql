@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE "users" ADD COLUMN "external_id" VARCHAR;
+UPDATE "users" SET "external_id" = (
+	SELECT "subject" FROM "user_identities"
+	WHERE "user_identities"."user_id" = "users"."id"
+	ORDER BY "id" LIMIT 1
+);
+CREATE UNIQUE INDEX "users_external_id_index" ON "users"("external_id");
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+
+-- Provider subjects live in "user_identities", one per linked provider.
+DROP INDEX IF EXISTS "users_external_id_index";
+ALTER TABLE "users" DROP COLUMN "external_id";
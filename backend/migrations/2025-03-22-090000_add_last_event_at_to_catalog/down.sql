@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE "prices" DROP COLUMN IF EXISTS "last_event_at";
+ALTER TABLE "products" DROP COLUMN IF EXISTS "last_event_at";
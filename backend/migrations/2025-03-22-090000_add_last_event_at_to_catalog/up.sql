@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+ALTER TABLE "products" ADD COLUMN "last_event_at" TIMESTAMPTZ;
+ALTER TABLE "prices" ADD COLUMN "last_event_at" TIMESTAMPTZ;
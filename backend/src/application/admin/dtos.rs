@@ -0,0 +1,228 @@
+use crate::application::user::dtos::UserDto;
+use crate::domain::pagination::{PageRequest, SortDirection};
+use crate::domain::subscription::entities::Subscription;
+use crate::domain::subscription::repository::{
+    SubscriptionCursor, SubscriptionFilter, SubscriptionSort, SubscriptionSortField,
+};
+use crate::domain::subscription::value_objects::subscription_status::SubscriptionStatus;
+use crate::domain::user::entities::UserIdentity;
+use crate::domain::user::repositories::{UserCursor, UserFilter, UserSort, UserSortField};
+use crate::domain::user::value_objects::role::Role;
+use crate::domain::user::value_objects::user_status::UserStatus;
+use crate::prelude::*;
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+const MAX_LIMIT: i64 = 100;
+
+fn default_limit() -> i64 {
+    20
+}
+
+/// Cursors are opaque to clients: the JSON of the domain cursor, hex encoded
+/// so it travels safely in a query string.
+fn encode_cursor<C: Serialize>(cursor: &C) -> String {
+    hex::encode(serde_json::to_vec(cursor).unwrap_or_default())
+}
+
+fn decode_cursor<C: DeserializeOwned>(cursor: &str) -> Option<C> {
+    let bytes = hex::decode(cursor).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+/// Checks the paging parameters shared by every listing query.
+fn page_request<C: DeserializeOwned>(cursor: Option<&str>, limit: i64) -> Result<PageRequest<C>> {
+    let mut errors = vec![];
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        errors.push(ValidationError::new(
+            "limit",
+            format!("must be between 1 and {}", MAX_LIMIT),
+        ));
+    }
+    let after = match cursor.filter(|cursor| !cursor.is_empty()) {
+        Some(cursor) => {
+            let after = decode_cursor(cursor);
+            if after.is_none() {
+                errors.push(ValidationError::new("cursor", "is invalid"));
+            }
+            after
+        }
+        None => None,
+    };
+    if errors.is_empty() {
+        Ok(PageRequest { after, limit })
+    } else {
+        Err(Error::Validation(errors))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserListQuery {
+    pub email: Option<String>,
+    pub status: Option<UserStatus>,
+    pub role: Option<Role>,
+    pub subscription_status: Option<SubscriptionStatus>,
+    #[serde(default)]
+    pub sort: UserSortField,
+    #[serde(default)]
+    pub direction: SortDirection,
+    pub cursor: Option<String>,
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+}
+impl UserListQuery {
+    pub fn filter(&self) -> UserFilter {
+        UserFilter {
+            email: self.email.clone().filter(|email| !email.trim().is_empty()),
+            status: self.status,
+            role: self.role,
+            subscription_status: self.subscription_status.clone(),
+        }
+    }
+
+    pub fn sort(&self) -> UserSort {
+        UserSort {
+            field: self.sort,
+            direction: self.direction,
+        }
+    }
+
+    pub fn page(&self) -> Result<PageRequest<UserCursor>> {
+        page_request(self.cursor.as_deref(), self.limit)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SubscriptionListQuery {
+    pub status: Option<SubscriptionStatus>,
+    pub price_id: Option<String>,
+    pub product_id: Option<String>,
+    pub cancel_at_period_end: Option<bool>,
+    pub period_ends_before: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub sort: SubscriptionSortField,
+    #[serde(default)]
+    pub direction: SortDirection,
+    pub cursor: Option<String>,
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+}
+impl SubscriptionListQuery {
+    pub fn filter(&self) -> SubscriptionFilter {
+        SubscriptionFilter {
+            status: self.status.clone(),
+            price_id: self.price_id.clone(),
+            product_id: self.product_id.clone(),
+            cancel_at_period_end: self.cancel_at_period_end,
+            period_ends_before: self.period_ends_before,
+        }
+    }
+
+    pub fn sort(&self) -> SubscriptionSort {
+        SubscriptionSort {
+            field: self.sort,
+            direction: self.direction,
+        }
+    }
+
+    pub fn page(&self) -> Result<PageRequest<SubscriptionCursor>> {
+        page_request(self.cursor.as_deref(), self.limit)
+    }
+}
+
+/// A page of a listing. `next_cursor` is passed back as `cursor` to fetch the
+/// following page, and is absent on the last one.
+#[derive(Debug, Serialize)]
+pub struct PageDto<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+    pub total: i64,
+}
+impl<T> PageDto<T> {
+    pub fn new<C: Serialize>(items: Vec<T>, next: Option<&C>, total: i64) -> Self {
+        Self {
+            items,
+            next_cursor: next.map(encode_cursor),
+            total,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangeUserRoleDto {
+    pub role: Role,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UserIdentityDto {
+    pub provider: String,
+    pub subject: String,
+    pub email: String,
+    pub email_verified: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+impl From<&UserIdentity> for UserIdentityDto {
+    fn from(identity: &UserIdentity) -> Self {
+        Self {
+            provider: identity.provider().to_string(),
+            subject: identity.subject().to_string(),
+            email: identity.email().to_string(),
+            email_verified: identity.email_verified(),
+            created_at: identity.created_at(),
+            updated_at: identity.updated_at(),
+        }
+    }
+}
+
+/// Everything an admin sees about a single user.
+#[derive(Debug, Serialize)]
+pub struct AdminUserDto {
+    #[serde(flatten)]
+    pub user: UserDto,
+    pub subscription: Option<Subscription>,
+    pub identities: Vec<UserIdentityDto>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_list_query() {
+        let query: UserListQuery = serde_urlencoded::from_str(
+            "email=%20&status=banned&subscription_status=past_due&sort=email&direction=desc",
+        )
+        .unwrap();
+        let filter = query.filter();
+        assert_eq!(filter.email, None);
+        assert_eq!(filter.status, Some(UserStatus::Banned));
+        assert_eq!(
+            filter.subscription_status,
+            Some(SubscriptionStatus::PastDue)
+        );
+        assert_eq!(query.sort().field, UserSortField::Email);
+        assert_eq!(query.sort().direction, SortDirection::Desc);
+        let page = query.page().unwrap();
+        assert_eq!(page.after, None);
+        assert_eq!(page.limit, 20);
+
+        // A next cursor handed out by a page is accepted back as is.
+        let cursor = UserCursor {
+            id: uuid::Uuid::new_v4(),
+            created_at: Utc::now(),
+            email: "john@example.com".to_string(),
+        };
+        let next = PageDto::<UserDto>::new(vec![], Some(&cursor), 1).next_cursor;
+        let query: UserListQuery =
+            serde_urlencoded::from_str(&format!("cursor={}", next.unwrap())).unwrap();
+        assert_eq!(query.page().unwrap().after, Some(cursor));
+
+        let query: UserListQuery = serde_urlencoded::from_str("cursor=zz&limit=500").unwrap();
+        match query.page() {
+            Err(Error::Validation(errors)) => assert_eq!(errors.len(), 2),
+            other => panic!("Expected validation errors, got {:?}", other),
+        }
+    }
+}
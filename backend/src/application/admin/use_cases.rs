@@ -0,0 +1,199 @@
+use crate::application::admin::dtos::{
+    AdminUserDto, PageDto, SubscriptionListQuery, UserIdentityDto, UserListQuery,
+};
+use crate::application::payment::service::PaymentService;
+use crate::application::subscription::service::{sync_subscription, SubscriptionService};
+use crate::application::user::dtos::{UpdateUserDto, UserDto};
+use crate::application::user::service::UserService;
+use crate::domain::payment::client::PaymentClient;
+use crate::domain::subscription::entities::Subscription;
+use crate::domain::subscription::repository::SubscriptionRepository;
+use crate::domain::unit_of_work::{Transaction, UnitOfWork};
+use crate::domain::user::repositories::UserRepository;
+use crate::domain::user::value_objects::role::Role;
+use crate::prelude::*;
+use chrono::Utc;
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct ListUsersUseCase<U: UserRepository> {
+    user_service: UserService<U>,
+}
+impl<U: UserRepository> ListUsersUseCase<U> {
+    pub fn new(user_service: UserService<U>) -> Self {
+        Self { user_service }
+    }
+
+    pub async fn execute(&self, query: UserListQuery) -> Result<PageDto<UserDto>> {
+        let page = query.page()?;
+        let filter = query.filter();
+        let users = self.user_service.list(&filter, query.sort(), &page).await?;
+        let total = self.user_service.count(&filter).await?;
+        let items = users
+            .items
+            .iter()
+            .map(UserDto::try_from)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(PageDto::new(items, users.next.as_ref(), total))
+    }
+}
+
+#[derive(Clone)]
+pub struct ListSubscriptionsUseCase<S: SubscriptionRepository> {
+    subscription_service: SubscriptionService<S>,
+}
+impl<S: SubscriptionRepository> ListSubscriptionsUseCase<S> {
+    pub fn new(subscription_service: SubscriptionService<S>) -> Self {
+        Self {
+            subscription_service,
+        }
+    }
+
+    pub async fn execute(&self, query: SubscriptionListQuery) -> Result<PageDto<Subscription>> {
+        let page = query.page()?;
+        let filter = query.filter();
+        let subscriptions = self
+            .subscription_service
+            .list(&filter, query.sort(), &page)
+            .await?;
+        let total = self.subscription_service.count(&filter).await?;
+        Ok(PageDto::new(
+            subscriptions.items,
+            subscriptions.next.as_ref(),
+            total,
+        ))
+    }
+}
+
+#[derive(Clone)]
+pub struct GetAdminUserUseCase<U: UserRepository, S: SubscriptionRepository> {
+    user_service: UserService<U>,
+    subscription_service: SubscriptionService<S>,
+}
+impl<U: UserRepository, S: SubscriptionRepository> GetAdminUserUseCase<U, S> {
+    pub fn new(user_service: UserService<U>, subscription_service: SubscriptionService<S>) -> Self {
+        Self {
+            user_service,
+            subscription_service,
+        }
+    }
+
+    pub async fn execute(&self, user_id: &Uuid) -> Result<AdminUserDto> {
+        let user = self.user_service.get_by_id(user_id).await?;
+        let subscription = match self.subscription_service.find_by_user_id(user_id).await {
+            Ok(subscription) => Some(subscription),
+            Err(Error::NotFound(_)) => None,
+            Err(e) => return Err(e),
+        };
+        let identities = self.user_service.identities(user_id).await?;
+        Ok(AdminUserDto {
+            user: UserDto::try_from(&user)?,
+            subscription,
+            identities: identities.iter().map(UserIdentityDto::from).collect(),
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct ChangeUserRoleUseCase<U: UserRepository> {
+    user_service: UserService<U>,
+}
+impl<U: UserRepository> ChangeUserRoleUseCase<U> {
+    pub fn new(user_service: UserService<U>) -> Self {
+        Self { user_service }
+    }
+
+    pub async fn execute(&self, actor: &UserDto, user_id: &Uuid, role: Role) -> Result<UserDto> {
+        if actor.id == *user_id {
+            return Err(Error::BadRequest(
+                "Admins cannot change their own role".to_string(),
+            ));
+        }
+        let user = self.user_service.get_by_id(user_id).await?;
+        let user = self.user_service.change_role(&user, role).await?;
+        tracing::info!(
+            "User {} changed role of user {} to {}",
+            actor.id,
+            user_id,
+            role
+        );
+        UserDto::try_from(&user)
+    }
+}
+
+#[derive(Clone)]
+pub struct ResyncUserUseCase<W, U: UserRepository, S: SubscriptionRepository, C: PaymentClient> {
+    unit_of_work: Arc<W>,
+    user_service: UserService<U>,
+    subscription_service: SubscriptionService<S>,
+    payment_service: PaymentService<C>,
+}
+impl<
+        W: UnitOfWork,
+        U: UserRepository + Clone,
+        S: SubscriptionRepository + Clone,
+        C: PaymentClient,
+    > ResyncUserUseCase<W, U, S, C>
+{
+    pub fn new(
+        unit_of_work: Arc<W>,
+        user_service: UserService<U>,
+        subscription_service: SubscriptionService<S>,
+        payment_service: PaymentService<C>,
+    ) -> Self {
+        Self {
+            unit_of_work,
+            user_service,
+            subscription_service,
+            payment_service,
+        }
+    }
+
+    /// Pulls the customer and subscription of the user from Stripe, for when
+    /// webhooks were missed or rejected.
+    pub async fn execute(&self, user_id: &Uuid) -> Result<AdminUserDto> {
+        let user = self.user_service.get_by_id(user_id).await?;
+        let customer_id = match user.stripe_customer_id() {
+            Some(customer_id) => customer_id.to_string(),
+            None => {
+                let customer = self.payment_service.get_customer(user.email()).await?;
+                tracing::info!("Linking customer {} to user {}", customer.id(), user.id());
+                let updates = UpdateUserDto::new(
+                    Some(customer.id()),
+                    Some(user.status()),
+                    user.profile().first_name().map(|s| s.to_string()),
+                    user.profile().last_name().map(|s| s.to_string()),
+                    user.profile().phone().map(|s| s.to_string()),
+                    user.profile().photo_url().map(|s| s.to_string()),
+                );
+                self.user_service.update(updates, &user).await?;
+                customer.id()
+            }
+        };
+
+        let subscriptions = self
+            .payment_service
+            .list_subscriptions(&customer_id)
+            .await?;
+        // Prefer a subscription granting access, otherwise the newest one.
+        let current = subscriptions
+            .iter()
+            .find(|subscription| subscription.status().grants_access())
+            .or(subscriptions.first());
+        match current {
+            Some(details) => {
+                // Locks the row like the webhook use cases do, so a webhook
+                // processed meanwhile cannot interleave with this write.
+                let tx = self.unit_of_work.begin().await?;
+                sync_subscription(tx.subscriptions(), user_id, details, Utc::now()).await?;
+                tx.commit().await?;
+            }
+            None => tracing::info!("Customer {} has no subscriptions", customer_id),
+        }
+
+        GetAdminUserUseCase::new(self.user_service.clone(), self.subscription_service.clone())
+            .execute(user_id)
+            .await
+    }
+}
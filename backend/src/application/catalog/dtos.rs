@@ -0,0 +1,50 @@
+use crate::domain::catalog::entities::{Price, Product};
+use serde::Serialize;
+
+//*******************************************//
+//***************** PlanDto *****************//
+//*******************************************//
+/// Active product with its active prices, as rendered by the pricing page.
+#[derive(Debug, Serialize)]
+pub struct PlanDto {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub prices: Vec<PlanPriceDto>,
+}
+impl PlanDto {
+    pub fn new(product: &Product, prices: Vec<PlanPriceDto>) -> Self {
+        Self {
+            id: product.id().to_string(),
+            name: product.name().to_string(),
+            description: product.description().map(|s| s.to_string()),
+            prices,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlanPriceDto {
+    pub id: String,
+    pub currency: String,
+    pub unit_amount: Option<i64>,
+    pub nickname: Option<String>,
+    pub interval: Option<String>,
+    pub interval_count: Option<i32>,
+    pub min_quantity: i32,
+    pub max_quantity: Option<i32>,
+}
+impl From<&Price> for PlanPriceDto {
+    fn from(price: &Price) -> Self {
+        Self {
+            id: price.id().to_string(),
+            currency: price.currency().to_string(),
+            unit_amount: price.unit_amount(),
+            nickname: price.nickname().map(|s| s.to_string()),
+            interval: price.recurring_interval().map(|s| s.to_string()),
+            interval_count: price.recurring_interval_count(),
+            min_quantity: price.min_quantity(),
+            max_quantity: price.max_quantity(),
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use crate::application::catalog::dtos::{PlanDto, PlanPriceDto};
+use crate::domain::catalog::entities::{Price, Product};
+use crate::domain::catalog::repository::CatalogRepository;
+use crate::domain::payment::entities::checkout::LineItem;
+use crate::prelude::*;
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct CatalogService<R> {
+    repo: Arc<R>,
+}
+impl<R: CatalogRepository> CatalogService<R> {
+    pub fn new(repo: Arc<R>) -> Self {
+        Self { repo }
+    }
+
+    pub async fn upsert_product(
+        &self,
+        product: &Product,
+        event_at: DateTime<Utc>,
+    ) -> Result<Product> {
+        self.repo.upsert_product(product, event_at).await
+    }
+    pub async fn delete_product(&self, id: &str, event_at: DateTime<Utc>) -> Result<()> {
+        self.repo.delete_product(id, event_at).await
+    }
+    pub async fn upsert_price(&self, price: &Price, event_at: DateTime<Utc>) -> Result<Price> {
+        self.repo.upsert_price(price, event_at).await
+    }
+    pub async fn delete_price(&self, id: &str, event_at: DateTime<Utc>) -> Result<()> {
+        self.repo.delete_price(id, event_at).await
+    }
+
+    /// Active products that have at least one active price.
+    pub async fn list_plans(&self) -> Result<Vec<PlanDto>> {
+        let products = self.repo.find_active_products().await?;
+        let prices = self.repo.find_active_prices().await?;
+
+        let plans = products
+            .iter()
+            .filter_map(|product| {
+                let product_prices: Vec<PlanPriceDto> = prices
+                    .iter()
+                    .filter(|price| price.product_id() == product.id())
+                    .map(PlanPriceDto::from)
+                    .collect();
+                if product_prices.is_empty() {
+                    None
+                } else {
+                    Some(PlanDto::new(product, product_prices))
+                }
+            })
+            .collect();
+        Ok(plans)
+    }
+
+    /// Checks checkout line items against the local catalog. `allowed_products`
+    /// restricts purchasable products further; empty means no restriction.
+    pub async fn validate_line_items(
+        &self,
+        line_items: &[LineItem],
+        allowed_products: &[String],
+    ) -> Result<()> {
+        let ids: Vec<String> = line_items.iter().map(|item| item.price.clone()).collect();
+        let prices = self.repo.find_prices(&ids).await?;
+        let products = self.repo.find_active_products().await?;
+
+        let errors = line_item_errors(line_items, &prices, &products, allowed_products);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Validation(errors))
+        }
+    }
+}
+
+fn line_item_errors(
+    line_items: &[LineItem],
+    prices: &[Price],
+    active_products: &[Product],
+    allowed_products: &[String],
+) -> Vec<ValidationError> {
+    if line_items.is_empty() {
+        return vec![ValidationError::new(
+            "line_items",
+            "At least one line item is required",
+        )];
+    }
+
+    let mut errors = vec![];
+    for (index, item) in line_items.iter().enumerate() {
+        let price_field = format!("line_items[{}].price", index);
+        let quantity_field = format!("line_items[{}].quantity", index);
+
+        let price = match prices.iter().find(|price| price.id() == item.price) {
+            Some(price) => price,
+            None => {
+                errors.push(ValidationError::new(
+                    price_field,
+                    format!("Unknown price {}", item.price),
+                ));
+                continue;
+            }
+        };
+        if !price.is_active() {
+            errors.push(ValidationError::new(
+                price_field,
+                format!("Price {} is no longer available", item.price),
+            ));
+            continue;
+        }
+        let product_active = active_products
+            .iter()
+            .any(|product| product.id() == price.product_id());
+        let product_allowed = allowed_products.is_empty()
+            || allowed_products
+                .iter()
+                .any(|product| product == price.product_id());
+        if !product_active || !product_allowed {
+            errors.push(ValidationError::new(
+                price_field,
+                format!("Price {} cannot be purchased", item.price),
+            ));
+            continue;
+        }
+        if !price.allows_quantity(item.quantity) {
+            let message = match price.max_quantity() {
+                Some(max) => format!(
+                    "Quantity must be between {} and {}",
+                    price.min_quantity().max(1),
+                    max
+                ),
+                None => format!("Quantity must be at least {}", price.min_quantity().max(1)),
+            };
+            errors.push(ValidationError::new(quantity_field, message));
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price(id: &str, product_id: &str, active: bool) -> Price {
+        Price::new(
+            id.to_string(),
+            product_id.to_string(),
+            active,
+            "eur".to_string(),
+            Some(990),
+            None,
+            Some("month".to_string()),
+            Some(1),
+            1,
+            Some(3),
+        )
+    }
+
+    fn item(price: &str, quantity: i32) -> LineItem {
+        LineItem {
+            price: price.to_string(),
+            quantity,
+        }
+    }
+
+    #[test]
+    fn test_line_item_errors() {
+        let products = vec![
+            Product::new("prod_basic".to_string(), "Basic".to_string(), None, true),
+            Product::new("prod_pro".to_string(), "Pro".to_string(), None, true),
+        ];
+        let prices = vec![
+            price("price_basic", "prod_basic", true),
+            price("price_old", "prod_basic", false),
+            price("price_pro", "prod_pro", true),
+            price("price_gone", "prod_archived", true),
+        ];
+        let allowed = vec!["prod_basic".to_string()];
+
+        assert!(
+            line_item_errors(&[item("price_basic", 2)], &prices, &products, &allowed).is_empty()
+        );
+
+        let errors = line_item_errors(
+            &[
+                item("price_basic", 0),
+                item("price_missing", 1),
+                item("price_old", 1),
+                item("price_pro", 1),
+                item("price_gone", 1),
+                item("price_basic", 4),
+            ],
+            &prices,
+            &products,
+            &allowed,
+        );
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            vec![
+                "line_items[0].quantity",
+                "line_items[1].price",
+                "line_items[2].price",
+                "line_items[3].price",
+                "line_items[4].price",
+                "line_items[5].quantity",
+            ]
+        );
+
+        let errors = line_item_errors(&[], &prices, &products, &allowed);
+        assert_eq!(errors[0].field, "line_items");
+    }
+}
@@ -0,0 +1,166 @@
+use crate::application::catalog::service::CatalogService;
+use crate::application::payment::service::PaymentService;
+use crate::domain::catalog::repository::CatalogRepository;
+use crate::domain::payment::client::PaymentClient;
+use crate::prelude::*;
+use chrono::Utc;
+
+pub struct SyncCatalogUseCase<R, C> {
+    pub catalog_service: CatalogService<R>,
+    pub payment_service: PaymentService<C>,
+}
+impl<R: CatalogRepository, C: PaymentClient> SyncCatalogUseCase<R, C> {
+    pub fn new(catalog_service: CatalogService<R>, payment_service: PaymentService<C>) -> Self {
+        Self {
+            catalog_service,
+            payment_service,
+        }
+    }
+
+    /// Upserts every product, then every price, from the payment provider.
+    /// Products deleted upstream are removed through `product.deleted` events.
+    /// Objects changed by an event received while listing keep that state.
+    pub async fn execute(&self) -> Result<()> {
+        let listed_at = Utc::now();
+        let products = self.payment_service.list_products().await?;
+        for product in &products {
+            skip_stale(
+                self.catalog_service
+                    .upsert_product(product, listed_at)
+                    .await,
+            )?;
+        }
+
+        let prices = self.payment_service.list_prices().await?;
+        let mut synced_prices = 0;
+        for price in &prices {
+            if !products
+                .iter()
+                .any(|product| product.id() == price.product_id())
+            {
+                tracing::warn!(
+                    "Skipping price {} of unknown product {}",
+                    price.id(),
+                    price.product_id()
+                );
+                continue;
+            }
+            skip_stale(self.catalog_service.upsert_price(price, listed_at).await)?;
+            synced_prices += 1;
+        }
+
+        tracing::info!(
+            "Catalog synchronized: {} products, {} prices",
+            products.len(),
+            synced_prices
+        );
+        Ok(())
+    }
+}
+
+fn skip_stale<T>(result: Result<T>) -> Result<()> {
+    match result {
+        Ok(_) => Ok(()),
+        Err(Error::StaleEvent(reason)) => {
+            tracing::info!("Keeping newer catalog state: {}", reason);
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::catalog::entities::{Price, Product};
+    use crate::infra::memory::payment::InMemoryPaymentClient;
+    use crate::infra::memory::InMemoryDatabase;
+    use std::sync::Arc;
+
+    fn price(id: &str, product_id: &str) -> Price {
+        Price::new(
+            id.to_string(),
+            product_id.to_string(),
+            true,
+            "eur".to_string(),
+            Some(990),
+            None,
+            Some("month".to_string()),
+            Some(1),
+            1,
+            None,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_sync_catalog() {
+        let db = InMemoryDatabase::new();
+        let client = InMemoryPaymentClient::new();
+        client.add_product(Product::new(
+            "prod_pro".to_string(),
+            "Pro".to_string(),
+            None,
+            true,
+        ));
+        client.add_price(price("price_pro", "prod_pro"));
+        client.add_price(price("price_orphan", "prod_missing"));
+
+        let catalog_service = CatalogService::new(Arc::new(db.catalog()));
+        let use_case = SyncCatalogUseCase::new(
+            catalog_service,
+            PaymentService::new(Arc::new(client.clone())),
+        );
+        use_case.execute().await.unwrap();
+
+        let catalog = db.catalog();
+        let products = catalog.find_active_products().await.unwrap();
+        assert_eq!(products.len(), 1);
+        assert_eq!(products[0].id(), "prod_pro");
+        let prices = catalog.find_active_prices().await.unwrap();
+        assert_eq!(prices.len(), 1);
+        assert_eq!(prices[0].id(), "price_pro");
+
+        client.failures().fail_always("payment.list_prices");
+        assert!(use_case.execute().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sync_catalog_keeps_newer_events() {
+        let db = InMemoryDatabase::new();
+        let client = InMemoryPaymentClient::new();
+        client.add_product(Product::new(
+            "prod_pro".to_string(),
+            "Pro".to_string(),
+            None,
+            true,
+        ));
+        let catalog = db.catalog();
+        let renamed_at = Utc::now() + chrono::TimeDelta::hours(1);
+        let renamed = Product::new("prod_pro".to_string(), "Pro+".to_string(), None, true);
+        catalog.upsert_product(&renamed, renamed_at).await.unwrap();
+
+        SyncCatalogUseCase::new(
+            CatalogService::new(Arc::new(db.catalog())),
+            PaymentService::new(Arc::new(client)),
+        )
+        .execute()
+        .await
+        .unwrap();
+        let products = catalog.find_active_products().await.unwrap();
+        assert_eq!(products[0].name(), "Pro+");
+
+        // A late event neither overwrites nor deletes the newer state.
+        let stale = catalog.upsert_product(&renamed, Utc::now()).await;
+        assert!(matches!(stale, Err(Error::StaleEvent(_))));
+        catalog
+            .delete_product("prod_pro", Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(catalog.find_active_products().await.unwrap().len(), 1);
+        catalog
+            .delete_product("prod_pro", renamed_at)
+            .await
+            .unwrap();
+        assert!(catalog.find_active_products().await.unwrap().is_empty());
+    }
+}
@@ -1,5 +1,10 @@
-use crate::domain::payment::entities::checkout::LineItem;
+use crate::domain::payment::entities::checkout::{
+    CheckoutSessionDetails, CheckoutSessionResult, LineItem,
+};
 use crate::domain::payment::entities::customer::Customer;
+use crate::domain::payment::value_objects::checkout_mode::CheckoutMode;
+use crate::domain::payment::value_objects::checkout_status::CheckoutStatus;
+use crate::domain::payment::value_objects::ui_mode::UiMode;
 use crate::prelude::*;
 use serde::{Deserialize, Serialize};
 
@@ -28,20 +33,72 @@ impl TryFrom<NewCustomerDto> for Customer {
 //***************************************************//
 #[derive(Debug, Deserialize)]
 pub struct NewCheckoutSessionDto {
+    #[serde(default)]
     pub line_items: Vec<LineItem>,
     pub success_url: Option<String>,
     pub cancel_url: Option<String>,
+    /// Required for embedded checkouts, ignored by hosted ones.
+    pub return_url: Option<String>,
+    /// Falls back to the configured default when omitted.
+    pub ui_mode: Option<UiMode>,
+    /// Falls back to the configured default when omitted.
+    pub mode: Option<CheckoutMode>,
 }
 impl NewCheckoutSessionDto {
     pub fn new(
         line_items: Vec<LineItem>,
         success_url: Option<String>,
         cancel_url: Option<String>,
+        return_url: Option<String>,
+        ui_mode: Option<UiMode>,
+        mode: Option<CheckoutMode>,
     ) -> Self {
         Self {
             line_items,
             success_url,
             cancel_url,
+            return_url,
+            ui_mode,
+            mode,
+        }
+    }
+}
+
+//************************************************//
+//************** CheckoutSessionDto **************//
+//************************************************//
+#[derive(Debug, Serialize)]
+pub struct CheckoutSessionDto {
+    pub id: String,
+    pub url: Option<String>,
+    pub client_secret: Option<String>,
+    pub ui_mode: UiMode,
+}
+impl From<CheckoutSessionResult> for CheckoutSessionDto {
+    fn from(result: CheckoutSessionResult) -> Self {
+        Self {
+            id: result.id,
+            url: result.url,
+            client_secret: result.client_secret,
+            ui_mode: result.ui_mode,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CheckoutSessionStatusDto {
+    pub id: String,
+    pub status: Option<CheckoutStatus>,
+    pub payment_status: String,
+    pub customer_email: Option<String>,
+}
+impl From<&CheckoutSessionDetails> for CheckoutSessionStatusDto {
+    fn from(details: &CheckoutSessionDetails) -> Self {
+        Self {
+            id: details.id().to_string(),
+            status: details.status(),
+            payment_status: details.payment_status().to_string(),
+            customer_email: details.customer_email().map(|s| s.to_string()),
         }
     }
 }
@@ -1,10 +1,10 @@
-use crate::application::payment::dto::{
-    NewCustomerDto, SessionDto,
-};
+use crate::application::payment::dto::{CheckoutSessionDto, NewCustomerDto, SessionDto};
+use crate::domain::catalog::entities::{Price, Product};
 use crate::domain::payment::client::PaymentClient;
-use crate::domain::payment::entities::checkout::CheckoutSession;
+use crate::domain::payment::entities::checkout::{CheckoutSession, CheckoutSessionDetails};
 use crate::domain::payment::entities::customer::Customer;
 use crate::domain::payment::entities::portal::CustomerPortalSession;
+use crate::domain::payment::entities::subscription::SubscriptionDetails;
 use crate::prelude::*;
 use std::sync::Arc;
 
@@ -28,9 +28,16 @@ impl<C: PaymentClient> PaymentService<C> {
         Ok(result)
     }
 
-    pub async fn create_checkout_session(&self, checkout: CheckoutSession) -> Result<SessionDto> {
+    pub async fn create_checkout_session(
+        &self,
+        checkout: CheckoutSession,
+    ) -> Result<CheckoutSessionDto> {
         let result = self.client.create_checkout_session(&checkout).await?;
-        Ok(SessionDto::new(result))
+        Ok(CheckoutSessionDto::from(result))
+    }
+
+    pub async fn get_checkout_session(&self, session_id: &str) -> Result<CheckoutSessionDetails> {
+        self.client.get_checkout_session(session_id).await
     }
 
     pub async fn create_portal_session(&self, portal: CustomerPortalSession) -> Result<SessionDto> {
@@ -38,4 +45,20 @@ impl<C: PaymentClient> PaymentService<C> {
         let result = self.client.create_portal_session(&portal).await?;
         Ok(SessionDto::new(result))
     }
+
+    pub async fn get_subscription(&self, subscription_id: &str) -> Result<SubscriptionDetails> {
+        self.client.get_subscription(subscription_id).await
+    }
+
+    pub async fn list_subscriptions(&self, customer_id: &str) -> Result<Vec<SubscriptionDetails>> {
+        self.client.list_subscriptions(customer_id).await
+    }
+
+    pub async fn list_products(&self) -> Result<Vec<Product>> {
+        self.client.list_products().await
+    }
+
+    pub async fn list_prices(&self) -> Result<Vec<Price>> {
+        self.client.list_prices().await
+    }
 }
@@ -1,11 +1,16 @@
+use crate::application::catalog::service::CatalogService;
 use crate::application::payment::dto::{
-    NewCheckoutSessionDto, NewCustomerDto, NewPortalDto, SessionDto,
+    CheckoutSessionDto, CheckoutSessionStatusDto, NewCheckoutSessionDto, NewCustomerDto,
+    NewPortalDto, SessionDto,
 };
 use crate::application::payment::service::PaymentService;
 use crate::application::user::dtos::UserDto;
+use crate::domain::catalog::repository::CatalogRepository;
 use crate::domain::payment::client::PaymentClient;
 use crate::domain::payment::entities::checkout::CheckoutSession;
 use crate::domain::payment::entities::portal::CustomerPortalSession;
+use crate::domain::payment::value_objects::checkout_mode::CheckoutMode;
+use crate::domain::payment::value_objects::ui_mode::UiMode;
 use crate::domain::user::entities::User;
 use crate::prelude::*;
 
@@ -48,22 +53,81 @@ use crate::prelude::*;
 //*******************************************************//
 //              Create Checkout Use Cases                //
 //*******************************************************//
+/// Checkout options taken from configuration.
+#[derive(Debug, Clone)]
+pub struct CheckoutSettings {
+    pub ui_mode: UiMode,
+    pub mode: CheckoutMode,
+    pub allowed_ui_modes: Vec<UiMode>,
+    pub allowed_modes: Vec<CheckoutMode>,
+    pub trial_period_days: u32,
+    pub allowed_products: Vec<String>,
+}
+
 #[derive(Clone)]
-pub struct CreateCheckoutSessionUseCase<C> {
+pub struct CreateCheckoutSessionUseCase<C, K> {
     service: PaymentService<C>,
+    catalog_service: CatalogService<K>,
+    settings: CheckoutSettings,
 }
-impl<C: PaymentClient> CreateCheckoutSessionUseCase<C> {
-    pub fn new(service: PaymentService<C>) -> Self {
-        Self { service }
+impl<C: PaymentClient, K: CatalogRepository> CreateCheckoutSessionUseCase<C, K> {
+    pub fn new(
+        service: PaymentService<C>,
+        catalog_service: CatalogService<K>,
+        settings: CheckoutSettings,
+    ) -> Self {
+        Self {
+            service,
+            catalog_service,
+            settings,
+        }
     }
 
     pub async fn execute(
         &self,
         user: UserDto,
         new_checkout: NewCheckoutSessionDto,
-    ) -> Result<SessionDto> {
+    ) -> Result<CheckoutSessionDto> {
+        let ui_mode = new_checkout.ui_mode.unwrap_or(self.settings.ui_mode);
+        let mode = new_checkout.mode.unwrap_or(self.settings.mode);
+
+        // Reject bad requests before anything is created on Stripe's side.
+        let mut errors = vec![];
+        if !self.settings.allowed_ui_modes.contains(&ui_mode) {
+            errors.push(ValidationError::new(
+                "ui_mode",
+                format!("UI mode {} is not allowed", ui_mode),
+            ));
+        }
+        if !self.settings.allowed_modes.contains(&mode) {
+            errors.push(ValidationError::new(
+                "mode",
+                format!("Mode {} is not allowed", mode),
+            ));
+        }
+        if ui_mode == UiMode::Embedded && new_checkout.return_url.is_none() {
+            errors.push(ValidationError::new(
+                "return_url",
+                "Embedded checkouts require a return url",
+            ));
+        }
+        if mode == CheckoutMode::Setup && !new_checkout.line_items.is_empty() {
+            errors.push(ValidationError::new(
+                "line_items",
+                "Setup checkouts do not take line items",
+            ));
+        }
+        if !errors.is_empty() {
+            return Err(Error::Validation(errors));
+        }
+        if mode != CheckoutMode::Setup {
+            self.catalog_service
+                .validate_line_items(&new_checkout.line_items, &self.settings.allowed_products)
+                .await?;
+        }
+
         let user = User::try_from(&user)?;
-        match user.stripe_customer_id() {
+        let customer_id = match user.stripe_customer_id() {
             None => {
                 tracing::debug!("Creating new customer for user: {}", &user.id());
                 let customer = match self.service.get_customer(user.email()).await {
@@ -81,35 +145,67 @@ impl<C: PaymentClient> CreateCheckoutSessionUseCase<C> {
                 };
 
                 tracing::debug!("Customer created: {:?}", &customer);
-                let checkout_session = CheckoutSession::new(
-                    customer.id().to_string(),
-                    new_checkout.line_items,
-                    new_checkout.success_url,
-                    new_checkout.cancel_url,
-                );
-                tracing::info!("Creating checkout session for user: {:?}", &user.id());
-                let session = self
-                    .service
-                    .create_checkout_session(checkout_session)
-                    .await?;
-                Ok(session)
+                customer.id()
             }
             Some(id) => {
                 tracing::debug!("Customer already exists for user: {}", &user.id());
-                let checkout_session = CheckoutSession::new(
-                    id.to_string(),
-                    new_checkout.line_items,
-                    new_checkout.success_url,
-                    new_checkout.cancel_url,
-                );
-                tracing::info!("Creating checkout session for user: {:?}", &user.id());
-                let session = self
-                    .service
-                    .create_checkout_session(checkout_session)
-                    .await?;
-                Ok(session)
+                id.to_string()
             }
+        };
+
+        let checkout_session = CheckoutSession::new(
+            customer_id,
+            Some(user.id().to_string()),
+            mode,
+            ui_mode,
+            new_checkout.line_items,
+            new_checkout.success_url,
+            new_checkout.cancel_url,
+            new_checkout.return_url,
+            Some(self.settings.trial_period_days),
+        );
+        tracing::info!("Creating checkout session for user: {:?}", &user.id());
+        let session = self
+            .service
+            .create_checkout_session(checkout_session)
+            .await?;
+        Ok(session)
+    }
+}
+
+#[derive(Clone)]
+pub struct GetCheckoutSessionUseCase<C> {
+    service: PaymentService<C>,
+}
+impl<C: PaymentClient> GetCheckoutSessionUseCase<C> {
+    pub fn new(service: PaymentService<C>) -> Self {
+        Self { service }
+    }
+
+    /// Sessions that belong to another user are reported as not found.
+    pub async fn execute(
+        &self,
+        user: UserDto,
+        session_id: &str,
+    ) -> Result<CheckoutSessionStatusDto> {
+        let session = self.service.get_checkout_session(session_id).await?;
+
+        let user_id = user.id.to_string();
+        let owned_by_reference = session.client_reference_id() == Some(user_id.as_str());
+        let owned_by_customer = session.customer().is_some()
+            && session.customer() == user.stripe_customer_id.as_deref();
+        if !owned_by_reference && !owned_by_customer {
+            tracing::warn!(
+                "User {} requested checkout session {} they do not own",
+                user.id,
+                session_id
+            );
+            return Err(Error::NotFound(format!(
+                "Checkout session {} not found",
+                session_id
+            )));
         }
+        Ok(CheckoutSessionStatusDto::from(&session))
     }
 }
 
@@ -0,0 +1,136 @@
+use crate::application::catalog::service::CatalogService;
+use crate::application::payment::event_use_cases::UpdateUserEvent;
+use crate::application::payment::service::PaymentService;
+use crate::application::subscription::use_cases::{
+    CheckoutCompletedUseCase, InvoicePaidUseCase, InvoicePaymentFailedUseCase,
+    SubscriptionCanceledUseCase, SubscriptionUpdatedUseCase,
+};
+use crate::application::user::service::UserService;
+use crate::domain::catalog::entities::{Price, Product};
+use crate::domain::catalog::repository::CatalogRepository;
+use crate::domain::payment::client::PaymentClient;
+use crate::domain::payment::entities::customer::Customer;
+use crate::domain::subscription::entities::WebhookEvent;
+use crate::domain::unit_of_work::UnitOfWork;
+use crate::domain::user::repositories::UserRepository;
+use crate::infra::stripe::models::StripeEvent;
+use crate::prelude::*;
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct WebhookDispatcher<W, U, C, K> {
+    unit_of_work: Arc<W>,
+    user_service: UserService<U>,
+    payment_service: PaymentService<C>,
+    catalog_service: CatalogService<K>,
+}
+impl<W, U, C, K> WebhookDispatcher<W, U, C, K>
+where
+    W: UnitOfWork,
+    U: UserRepository + Clone,
+    C: PaymentClient + Clone,
+    K: CatalogRepository,
+{
+    pub fn new(
+        unit_of_work: Arc<W>,
+        user_service: UserService<U>,
+        payment_service: PaymentService<C>,
+        catalog_service: CatalogService<K>,
+    ) -> Self {
+        Self {
+            unit_of_work,
+            user_service,
+            payment_service,
+            catalog_service,
+        }
+    }
+
+    /// Runs the use case for the event. Events older than the state already
+    /// applied are acknowledged without changes.
+    pub async fn dispatch(&self, event: &WebhookEvent) -> Result<()> {
+        match self.handle(event).await {
+            Err(Error::StaleEvent(reason)) => {
+                tracing::info!("Skipping stale webhook event {}: {}", event.id(), reason);
+                Ok(())
+            }
+            other => other,
+        }
+    }
+
+    async fn handle(&self, event: &WebhookEvent) -> Result<()> {
+        let stripe_event: StripeEvent = serde_json::from_value(event.payload().clone())
+            .map_err(|e| Error::DeserializationError(e.to_string()))?;
+        let event_at = stripe_event.created();
+        let stripe_event_type = stripe_event.event_type().to_owned();
+        match stripe_event {
+            StripeEvent::CustomerCreated(event) => {
+                tracing::info!("customer.created event received");
+                let customer = Customer::try_from(event.data.object)?;
+                let use_case = UpdateUserEvent::new(self.user_service.clone());
+                use_case.execute(customer).await?;
+            }
+            StripeEvent::InvoicePaid(event) => {
+                tracing::info!("invoice.paid event received");
+                let use_case = InvoicePaidUseCase::new(self.unit_of_work.clone());
+                use_case.execute(event.data.object, event_at).await?;
+            }
+            StripeEvent::InvoicePaymentFailed(event) => {
+                tracing::info!("invoice.payment_failed event received");
+                let use_case = InvoicePaymentFailedUseCase::new(self.unit_of_work.clone());
+                use_case.execute(event.data.object, event_at).await?;
+            }
+            StripeEvent::SubscriptionUpdated(event)
+            | StripeEvent::SubscriptionPaused(event)
+            | StripeEvent::SubscriptionResumed(event) => {
+                tracing::info!("{} event received", stripe_event_type);
+                let use_case = SubscriptionUpdatedUseCase::new(self.unit_of_work.clone());
+                use_case.execute(event.data.object, event_at).await?;
+            }
+            StripeEvent::SubscriptionDeleted(event) => {
+                tracing::info!("customer.subscription.deleted event received");
+                let use_case = SubscriptionCanceledUseCase::new(self.unit_of_work.clone());
+                use_case.execute(event.data.object, event_at).await?;
+            }
+            StripeEvent::CheckoutSessionCompleted(event) => {
+                tracing::info!("checkout.session.completed event received");
+                let use_case = CheckoutCompletedUseCase::new(
+                    self.unit_of_work.clone(),
+                    self.user_service.clone(),
+                    self.payment_service.clone(),
+                );
+                use_case.execute(event.data.object, event_at).await?;
+            }
+            StripeEvent::ProductCreated(event) | StripeEvent::ProductUpdated(event) => {
+                tracing::info!("{} event received", stripe_event_type);
+                let product = Product::from(event.data.object);
+                self.catalog_service
+                    .upsert_product(&product, event_at)
+                    .await?;
+            }
+            StripeEvent::ProductDeleted(event) => {
+                tracing::info!("product.deleted event received");
+                self.catalog_service
+                    .delete_product(&event.data.object.id, event_at)
+                    .await?;
+            }
+            StripeEvent::PriceCreated(event) | StripeEvent::PriceUpdated(event) => {
+                tracing::info!("{} event received", stripe_event_type);
+                let price = Price::from(event.data.object);
+                self.catalog_service.upsert_price(&price, event_at).await?;
+            }
+            StripeEvent::PriceDeleted(event) => {
+                tracing::info!("price.deleted event received");
+                self.catalog_service
+                    .delete_price(&event.data.object.id, event_at)
+                    .await?;
+            }
+            other => {
+                tracing::debug!(
+                    "Ignoring unhandled webhook event type {}",
+                    other.event_type()
+                );
+            }
+        }
+        Ok(())
+    }
+}
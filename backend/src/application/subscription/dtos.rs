@@ -21,8 +21,10 @@ pub struct NewSubscriptionDto {
     pub customer_id: String, // Customer id
     pub plan: PlanObject,
     pub status: SubscriptionStatus,
-    pub current_period_end: i64,
+    pub current_period_end: Option<i64>,
     pub cancel_at_period_end: Option<bool>,
+    #[serde(skip)]
+    pub event_at: Option<DateTime<Utc>>,
 }
 impl NewSubscriptionDto {
     pub fn into_domain(self) -> Result<Subscription> {
@@ -38,8 +40,10 @@ impl NewSubscriptionDto {
             self.subscription_id,
             self.status,
             has_used_trial,
-            DateTime::<Utc>::from_timestamp(self.current_period_end, 0),
+            self.current_period_end
+                .and_then(|timestamp| DateTime::<Utc>::from_timestamp(timestamp, 0)),
             self.cancel_at_period_end.unwrap_or(false),
+            self.event_at,
         ))
     }
 }
@@ -1,57 +1,185 @@
-use crate::infra::dependencies::AppState;
+use crate::application::subscription::receiver::WebhookReceiver;
+use crate::domain::subscription::repository::WebhookEventRepository;
+use crate::domain::subscription::service::SignatureVerificationService;
 use crate::prelude::*;
 use actix_web::dev::Payload;
-use actix_web::web::Data;
-use actix_web::{web, FromRequest, HttpMessage, HttpRequest};
+use actix_web::http::header::CONTENT_LENGTH;
+use actix_web::web::{BytesMut, Data};
+use actix_web::{FromRequest, HttpRequest};
 use futures_util::future::LocalBoxFuture;
 use futures_util::StreamExt;
 use serde::de::DeserializeOwned;
+use std::marker::PhantomData;
 
+/// A sender of webhooks signed over the raw request body.
+pub trait SignedProvider: 'static {
+    /// Header carrying the signature.
+    const SIGNATURE_HEADER: &'static str;
+
+    /// App data holding the verification settings.
+    type State: 'static;
+
+    /// Largest body accepted, in bytes.
+    fn max_payload_size(state: &Self::State) -> usize;
+
+    fn verify(state: &Self::State, payload: &[u8], signature: &str) -> Result<()>;
+}
+
+/// Webhooks from Stripe, checked by the `WebhookReceiver` in app data.
+pub struct Stripe<R, S>(PhantomData<(R, S)>);
+impl<R, S> SignedProvider for Stripe<R, S>
+where
+    R: WebhookEventRepository + 'static,
+    S: SignatureVerificationService + 'static,
+{
+    const SIGNATURE_HEADER: &'static str = "Stripe-Signature";
+
+    type State = WebhookReceiver<R, S>;
+
+    fn max_payload_size(receiver: &Self::State) -> usize {
+        receiver.max_payload_size()
+    }
+
+    fn verify(receiver: &Self::State, payload: &[u8], signature: &str) -> Result<()> {
+        receiver.verify(payload, signature)
+    }
+}
+
+/// The body of a webhook from `P`, deserialized once its signature is
+/// verified. Rejects with 401 for a missing or wrong signature, 413 for a
+/// body over the limit and 400 for a body that cannot be read or parsed.
 #[derive(Debug)]
-pub struct SignatureVerifier<T>(pub T);
+pub struct SignatureVerifier<T, P>(pub T, PhantomData<P>);
 
-impl<T: DeserializeOwned + 'static> FromRequest for SignatureVerifier<T> {
+impl<T: DeserializeOwned + 'static, P: SignedProvider> FromRequest for SignatureVerifier<T, P> {
     type Error = Error;
     type Future = LocalBoxFuture<'static, Result<Self>>;
 
     fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
         let signature = req
             .headers()
-            .get("Stripe-Signature")
+            .get(P::SIGNATURE_HEADER)
             .and_then(|value| value.to_str().ok())
             .map(String::from);
+        let content_length = req
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<usize>().ok());
 
-        let state = req.app_data::<Data<AppState>>().cloned();
-
-        // let mut payload = payload.take();
-        let payload_future = web::Bytes::from_request(req, payload);
+        let state = req.app_data::<Data<P::State>>().cloned();
+        let mut payload = payload.take();
 
         Box::pin(async move {
             let signature = signature.ok_or_else(|| {
-                tracing::error!("Missing Stripe-Signature header");
+                tracing::error!("Missing {} header", P::SIGNATURE_HEADER);
                 Error::Unauthorized
             })?;
             let state = state.ok_or_else(|| {
                 tracing::error!("App state not found");
                 Error::InternalError
             })?;
-            let raw_body = payload_future.await.map_err(|e| {
-                tracing::error!("Failed to read request body: {}", e);
-                Error::InternalError
-            })?;
-            // Convert raw body to string for verification
-            let payload_str = String::from_utf8(raw_body.to_vec()).map_err(|e| {
-                tracing::error!("Invalid UTF-8 in request body: {}", e);
-                Error::InternalError
-            })?;
-            // Verify signature
-            state.signature_service.verify(&payload_str, &signature)?;
+
+            let limit = P::max_payload_size(&state);
+            if content_length.is_some_and(|length| length > limit) {
+                return Err(Error::PayloadTooLarge(limit));
+            }
+            // The length header is optional, so the limit is also enforced
+            // while reading.
+            let mut raw_body = BytesMut::new();
+            while let Some(chunk) = payload.next().await {
+                let chunk = chunk.map_err(|e| {
+                    tracing::error!("Failed to read request body: {}", e);
+                    Error::BadRequest("Failed to read request body".to_string())
+                })?;
+                if raw_body.len() + chunk.len() > limit {
+                    return Err(Error::PayloadTooLarge(limit));
+                }
+                raw_body.extend_from_slice(&chunk);
+            }
+
+            P::verify(&state, &raw_body, &signature)?;
 
             let json_payload: T = serde_json::from_slice(&raw_body).map_err(|e| {
                 tracing::error!("Failed to parse request body: {}", e);
-                Error::InternalError
+                Error::BadRequest(format!("Invalid webhook payload: {}", e))
             })?;
-            Ok(SignatureVerifier(json_payload))
+            Ok(SignatureVerifier(json_payload, PhantomData))
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infra::stripe::service::{signature_header, StripeSignatureVerificationService};
+    use actix_web::http::StatusCode;
+    use actix_web::test::TestRequest;
+    use actix_web::ResponseError;
+    use serde_json::Value;
+    use std::time::Duration;
+
+    struct TestWebhook;
+    impl SignedProvider for TestWebhook {
+        const SIGNATURE_HEADER: &'static str = "Test-Signature";
+
+        type State = StripeSignatureVerificationService;
+
+        fn max_payload_size(_: &Self::State) -> usize {
+            64
+        }
+
+        fn verify(state: &Self::State, payload: &[u8], signature: &str) -> Result<()> {
+            state.verify(payload, signature)
+        }
+    }
+
+    async fn extract(body: &'static [u8], signature: Option<&str>) -> Result<Value> {
+        let verifier =
+            StripeSignatureVerificationService::new(&["whsec_test"], Duration::from_secs(300));
+        let mut request = TestRequest::post()
+            .app_data(Data::new(verifier))
+            .set_payload(body);
+        if let Some(signature) = signature {
+            request = request.insert_header((TestWebhook::SIGNATURE_HEADER, signature));
+        }
+        let (req, mut payload) = request.to_http_parts();
+        SignatureVerifier::<Value, TestWebhook>::from_request(&req, &mut payload)
+            .await
+            .map(|verified| verified.0)
+    }
+
+    fn sign(body: &str) -> String {
+        signature_header("whsec_test", chrono::Utc::now().timestamp(), body).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_signature_verifier() {
+        let body = r#"{"id":"evt_1"}"#;
+        let event = extract(body.as_bytes(), Some(&sign(body))).await.unwrap();
+        assert_eq!(event["id"], "evt_1");
+
+        let status = |result: Result<Value>| result.unwrap_err().status_code();
+        assert_eq!(
+            status(extract(body.as_bytes(), None).await),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(extract(body.as_bytes(), Some(&sign("{}"))).await),
+            StatusCode::UNAUTHORIZED
+        );
+
+        let large = r#"{"id":"evt_1","padding":"0000000000000000000000000000000000000000"}"#;
+        assert_eq!(
+            status(extract(large.as_bytes(), Some(&sign(large))).await),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+
+        // Signed, but not an event
+        let invalid = "not json";
+        assert_eq!(
+            status(extract(invalid.as_bytes(), Some(&sign(invalid))).await),
+            StatusCode::BAD_REQUEST
+        );
+    }
+}
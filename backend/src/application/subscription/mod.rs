@@ -1,4 +1,7 @@
+pub mod dispatcher;
 pub mod dtos;
 pub mod extractors;
+pub mod receiver;
 pub mod service;
 pub mod use_cases;
+pub mod worker;
@@ -0,0 +1,63 @@
+use crate::application::subscription::service::{SignatureService, WebhookEventService};
+use crate::domain::subscription::entities::WebhookEvent;
+use crate::domain::subscription::repository::WebhookEventRepository;
+use crate::domain::subscription::service::SignatureVerificationService;
+use crate::prelude::*;
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+/// Takes in webhook deliveries: checks their signature and durably stores the
+/// event before it is acknowledged. Processing happens in the worker, woken
+/// up through `notifier`.
+#[derive(Clone)]
+pub struct WebhookReceiver<R, S> {
+    events: WebhookEventService<R>,
+    signatures: SignatureService<S>,
+    notifier: Arc<Notify>,
+    max_payload_size: usize,
+}
+impl<R: WebhookEventRepository, S: SignatureVerificationService> WebhookReceiver<R, S> {
+    pub fn new(
+        events: WebhookEventService<R>,
+        signatures: SignatureService<S>,
+        notifier: Arc<Notify>,
+        max_payload_size: usize,
+    ) -> Self {
+        Self {
+            events,
+            signatures,
+            notifier,
+            max_payload_size,
+        }
+    }
+
+    /// Largest body accepted, in bytes.
+    pub fn max_payload_size(&self) -> usize {
+        self.max_payload_size
+    }
+
+    pub fn verify(&self, payload: &[u8], signature: &str) -> Result<()> {
+        self.signatures.verify(payload, signature)
+    }
+
+    /// Stores a verified event exactly as received, or returns the stored
+    /// copy of a redelivered one.
+    pub async fn record(&self, payload: Value) -> Result<WebhookEvent> {
+        let event = self.events.record(WebhookEvent::try_from(payload)?).await?;
+        if event.is_processed() {
+            tracing::info!("Webhook event {} already processed, skipping", event.id());
+        } else {
+            self.notifier.notify_one();
+        }
+        Ok(event)
+    }
+
+    /// Verifies a raw delivery, then records it.
+    pub async fn receive(&self, payload: &[u8], signature: &str) -> Result<WebhookEvent> {
+        self.verify(payload, signature)?;
+        let payload: Value = serde_json::from_slice(payload)
+            .map_err(|e| Error::BadRequest(format!("Invalid webhook payload: {}", e)))?;
+        self.record(payload).await
+    }
+}
@@ -1,8 +1,14 @@
-use crate::application::subscription::dtos::NewSubscriptionDto;
-use crate::domain::subscription::entities::Subscription;
-use crate::domain::subscription::repository::SubscriptionRepository;
+use crate::application::subscription::dtos::{NewSubscriptionDto, PlanObject};
+use crate::domain::pagination::{Page, PageRequest};
+use crate::domain::payment::entities::subscription::SubscriptionDetails;
+use crate::domain::subscription::entities::{Subscription, WebhookEvent};
+use crate::domain::subscription::repository::{
+    SubscriptionCursor, SubscriptionFilter, SubscriptionRepository, SubscriptionSort,
+    WebhookEventRepository,
+};
 use crate::domain::subscription::service::SignatureVerificationService;
 use crate::prelude::*;
+use chrono::{DateTime, Utc};
 use hmac::Mac;
 use std::sync::Arc;
 use uuid::Uuid;
@@ -16,10 +22,6 @@ impl<C: SubscriptionRepository> SubscriptionService<C> {
         Self { repo }
     }
 
-    pub async fn create(&self, new_subscription: NewSubscriptionDto) -> Result<Subscription> {
-        let subscription = new_subscription.into_domain()?;
-        self.repo.save(&subscription).await
-    }
     pub async fn find(&self, id: i32) -> Result<Subscription> {
         self.repo.find(id).await
     }
@@ -37,8 +39,92 @@ impl<C: SubscriptionRepository> SubscriptionService<C> {
     pub async fn find_by_user_id(&self, user_id: &Uuid) -> Result<Subscription> {
         self.repo.find_by_user_id(user_id).await
     }
-    pub async fn update(&self, updates: &Subscription) -> Result<Subscription> {
-        self.repo.update(updates).await
+    pub async fn list(
+        &self,
+        filter: &SubscriptionFilter,
+        sort: SubscriptionSort,
+        page: &PageRequest<SubscriptionCursor>,
+    ) -> Result<Page<Subscription, SubscriptionCursor>> {
+        self.repo.find_page(filter, sort, page).await
+    }
+    pub async fn count(&self, filter: &SubscriptionFilter) -> Result<i64> {
+        self.repo.count(filter).await
+    }
+}
+
+/// Creates or refreshes the subscription of the user from the state the
+/// payment provider reported at `event_at`. `repo` must be bound to an open
+/// transaction: the existing row is locked until that transaction ends.
+pub async fn sync_subscription<R: SubscriptionRepository>(
+    repo: &R,
+    user_id: &Uuid,
+    details: &SubscriptionDetails,
+    event_at: DateTime<Utc>,
+) -> Result<Subscription> {
+    match repo.find_by_user_id_for_update(user_id).await {
+        Ok(mut subscription) => {
+            subscription.update(
+                event_at,
+                Some(details.price_id().to_string()),
+                Some(details.product_id().to_string()),
+                Some(details.id().to_string()),
+                Some(details.status().clone()),
+                details.current_period_end(),
+                Some(details.cancel_at_period_end()),
+                details.canceled_at(),
+            )?;
+            repo.update(&subscription).await
+        }
+        Err(Error::NotFound(_)) => {
+            let new_subscription = NewSubscriptionDto {
+                user_id: Some(*user_id),
+                subscription_id: details.id().to_string(),
+                customer_id: details.customer().to_string(),
+                plan: PlanObject {
+                    price_id: details.price_id().to_string(),
+                    product_id: details.product_id().to_string(),
+                },
+                status: details.status().clone(),
+                current_period_end: details.current_period_end().map(|end| end.timestamp()),
+                cancel_at_period_end: Some(details.cancel_at_period_end()),
+                event_at: Some(event_at),
+            };
+            repo.save(&new_subscription.into_domain()?).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[derive(Clone)]
+pub struct WebhookEventService<R> {
+    repo: Arc<R>,
+}
+impl<R: WebhookEventRepository> WebhookEventService<R> {
+    pub fn new(repo: Arc<R>) -> Self {
+        Self { repo }
+    }
+
+    /// Stores a newly received event, or returns the stored copy when Stripe
+    /// redelivers an event id we have already seen.
+    pub async fn record(&self, event: WebhookEvent) -> Result<WebhookEvent> {
+        match self.repo.save(&event).await {
+            Err(Error::RecordAlreadyExists) => {
+                tracing::info!("Webhook event {} was already received", event.id());
+                self.repo.find(event.id()).await
+            }
+            other => other,
+        }
+    }
+    pub async fn update(&self, event: &WebhookEvent) -> Result<WebhookEvent> {
+        self.repo.update(event).await
+    }
+    pub async fn claim_due(
+        &self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<WebhookEvent>> {
+        self.repo.claim_due(now, lease_until, limit).await
     }
 }
 
@@ -50,7 +136,7 @@ impl<S: SignatureVerificationService> SignatureService<S> {
     pub fn new(client: Arc<S>) -> Self {
         Self { client }
     }
-    pub fn verify(&self, payload: &str, signature: &str) -> Result<()> {
+    pub fn verify(&self, payload: &[u8], signature: &str) -> Result<()> {
         self.client.verify(payload, signature)
     }
 }
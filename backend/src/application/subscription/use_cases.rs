@@ -1,50 +1,106 @@
+use crate::application::payment::service::PaymentService;
 use crate::application::subscription::dtos::{NewSubscriptionDto, PlanObject};
-use crate::application::subscription::service::SubscriptionService;
+use crate::application::subscription::service::{sync_subscription, SubscriptionService};
 use crate::application::user::service::UserService;
+use crate::domain::payment::client::PaymentClient;
 use crate::domain::subscription::entities::Subscription;
 use crate::domain::subscription::repository::SubscriptionRepository;
 use crate::domain::subscription::value_objects::subscription_status::SubscriptionStatus;
+use crate::domain::unit_of_work::{Transaction, UnitOfWork};
+use crate::domain::user::entities::User;
 use crate::domain::user::repositories::UserRepository;
+use crate::infra::stripe::models::{StripeCheckoutSession, StripeInvoice, StripeSubscription};
 use crate::prelude::*;
-use crate::shared::extractors::{extract_bool, extract_number, extract_string, extract_timestamp};
-use serde_json::Value;
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use uuid::Uuid;
 
-pub struct InvoicePaidUseCase<S, U> {
-    pub subscription_service: SubscriptionService<S>,
-    pub user_service: UserService<U>,
+/// The user the Stripe customer was linked to.
+async fn find_customer_user<T: Transaction>(tx: &T, customer_id: &str) -> Result<User> {
+    tx.users()
+        .find_by_strip_customer_id(customer_id)
+        .await?
+        .ok_or_else(|| {
+            tracing::info!(
+                "User with subscription provider id {} not found",
+                customer_id
+            );
+            Error::NotFound("User not found".to_string())
+        })
 }
-impl<S: SubscriptionRepository, U: UserRepository> InvoicePaidUseCase<S, U> {
-    pub fn new(subscription_service: SubscriptionService<S>, user_service: UserService<U>) -> Self {
-        Self {
-            subscription_service,
-            user_service,
-        }
-    }
-    pub async fn execute(&self, data: Value) -> Result<()> {
-        let line_data = data["lines"]["data"][0].clone();
 
-        let billing_reason = extract_string(&data, "billing_reason")?;
-        let amount_paid = extract_number(&data, "amount_paid")?;
+/// Loads the subscription of the customer's user for update, applies
+/// `apply` and stores it, all in one transaction, so webhooks delivered
+/// concurrently for the same subscription are applied one after the other.
+async fn update_customer_subscription<W: UnitOfWork>(
+    unit_of_work: &W,
+    customer_id: &str,
+    apply: impl FnOnce(&mut Subscription) -> Result<()>,
+) -> Result<()> {
+    let tx = unit_of_work.begin().await?;
+    let user = find_customer_user(&tx, customer_id).await?;
+    let mut subscription = tx
+        .subscriptions()
+        .find_by_user_id_for_update(&user.id())
+        .await?;
+    apply(&mut subscription)?;
+    tx.subscriptions().update(&subscription).await?;
+    tx.commit().await
+}
 
-        let status = if (billing_reason == "subscription_create") && (amount_paid == 0) {
+pub struct InvoicePaidUseCase<W> {
+    pub unit_of_work: Arc<W>,
+}
+impl<W: UnitOfWork> InvoicePaidUseCase<W> {
+    pub fn new(unit_of_work: Arc<W>) -> Self {
+        Self { unit_of_work }
+    }
+    pub async fn execute(&self, invoice: StripeInvoice, event_at: DateTime<Utc>) -> Result<()> {
+        let billing_reason = invoice.billing_reason.as_deref().unwrap_or_default();
+        let status = if (billing_reason == "subscription_create") && (invoice.amount_paid == 0) {
             SubscriptionStatus::Trialing
         } else {
             SubscriptionStatus::Active
         };
 
-        let customer_id = extract_string(&data, "customer")?;
-        let customer_email = extract_string(&data, "customer_email")?;
-        let subscription_id = extract_string(&data, "subscription")?;
-        let current_period_end = extract_timestamp(&line_data, "period/end")?;
-        let price_id = extract_string(&line_data, "price/id")?;
-        let product_id = extract_string(&line_data, "price/product")?;
+        let line = invoice.lines.data.into_iter().next().ok_or_else(|| {
+            Error::BadRequest(format!("Invoice {} has no line items", invoice.id))
+        })?;
+        let price = line.price.ok_or_else(|| {
+            Error::BadRequest(format!("Invoice {} line has no price", invoice.id))
+        })?;
+        let customer_id = invoice.customer;
+        let subscription_id = invoice.subscription.ok_or_else(|| {
+            Error::BadRequest(format!("Invoice {} has no subscription", invoice.id))
+        })?;
+        let current_period_end = line.period.end;
+        let price_id = price.id;
+        let product_id = price.product;
 
-        let user = self.user_service.get_by_email(&customer_email).await?;
-        let subscription = self.subscription_service.find_by_user_id(&user.id()).await;
+        let tx = self.unit_of_work.begin().await?;
+        // The customer id is linked on checkout completion, so prefer it over
+        // the invoice email which may differ from the account email.
+        let user = match find_customer_user(&tx, &customer_id).await {
+            Err(Error::NotFound(_)) => {
+                let customer_email = invoice.customer_email.ok_or_else(|| {
+                    Error::BadRequest(format!("Invoice {} has no customer email", invoice.id))
+                })?;
+                tx.users()
+                    .find_by_email(&customer_email)
+                    .await?
+                    .ok_or_else(|| Error::NotFound("User not found".to_string()))?
+            }
+            other => other?,
+        };
+        let subscription = tx
+            .subscriptions()
+            .find_by_user_id_for_update(&user.id())
+            .await;
 
         match subscription {
             Ok(mut subscription) => {
                 subscription.update(
+                    event_at,
                     Some(price_id),
                     Some(product_id),
                     Some(subscription_id),
@@ -52,9 +108,8 @@ impl<S: SubscriptionRepository, U: UserRepository> InvoicePaidUseCase<S, U> {
                     Some(current_period_end),
                     Some(false),
                     None,
-                );
-                self.subscription_service.update(&subscription).await?;
-                Ok(())
+                )?;
+                tx.subscriptions().update(&subscription).await?;
             }
             Err(Error::NotFound(_)) => {
                 let new_subscription = NewSubscriptionDto {
@@ -66,133 +121,231 @@ impl<S: SubscriptionRepository, U: UserRepository> InvoicePaidUseCase<S, U> {
                         product_id,
                     },
                     status,
-                    current_period_end: current_period_end.timestamp(),
+                    current_period_end: Some(current_period_end.timestamp()),
                     cancel_at_period_end: Some(false),
+                    event_at: Some(event_at),
                 };
-                self.subscription_service.create(new_subscription).await?;
-                Ok(())
+                tx.subscriptions()
+                    .save(&new_subscription.into_domain()?)
+                    .await?;
             }
-            Err(e) => Err(e),
+            Err(e) => return Err(e),
         }
+        tx.commit().await
     }
 }
 
-pub struct InvoicePaymentFailedUseCase<S, U> {
-    pub subscription_service: SubscriptionService<S>,
-    pub user_service: UserService<U>,
+pub struct InvoicePaymentFailedUseCase<W> {
+    pub unit_of_work: Arc<W>,
 }
-impl<S: SubscriptionRepository, U: UserRepository> InvoicePaymentFailedUseCase<S, U> {
-    pub fn new(subscription_service: SubscriptionService<S>, user_service: UserService<U>) -> Self {
-        Self {
-            subscription_service,
-            user_service,
-        }
+impl<W: UnitOfWork> InvoicePaymentFailedUseCase<W> {
+    pub fn new(unit_of_work: Arc<W>) -> Self {
+        Self { unit_of_work }
     }
-    pub async fn execute(&self, data: Value) -> Result<()> {
-        let customer_id = extract_string(&data, "customer")?;
-        let user = self
-            .user_service
-            .get_by_payment_provider_id(&customer_id)
-            .await?;
-        let mut subscription = self
-            .subscription_service
-            .find_by_user_id(&user.id())
-            .await?;
-        subscription.update(
-            None,
-            None,
-            None,
-            Some(SubscriptionStatus::PastDue),
-            None,
-            None,
-            None,
-        );
-        self.subscription_service.update(&subscription).await?;
-        Ok(())
+    pub async fn execute(&self, invoice: StripeInvoice, event_at: DateTime<Utc>) -> Result<()> {
+        update_customer_subscription(
+            self.unit_of_work.as_ref(),
+            &invoice.customer,
+            |subscription| {
+                subscription.update(
+                    event_at,
+                    None,
+                    None,
+                    None,
+                    Some(SubscriptionStatus::PastDue),
+                    None,
+                    None,
+                    None,
+                )
+            },
+        )
+        .await
     }
 }
 
-pub struct SubscriptionUpdatedUseCase<S, U> {
-    pub subscription_service: SubscriptionService<S>,
-    pub user_service: UserService<U>,
+pub struct SubscriptionUpdatedUseCase<W> {
+    pub unit_of_work: Arc<W>,
 }
-impl<S: SubscriptionRepository, U: UserRepository> SubscriptionUpdatedUseCase<S, U> {
-    pub fn new(subscription_service: SubscriptionService<S>, user_service: UserService<U>) -> Self {
-        Self {
-            subscription_service,
-            user_service,
-        }
+impl<W: UnitOfWork> SubscriptionUpdatedUseCase<W> {
+    pub fn new(unit_of_work: Arc<W>) -> Self {
+        Self { unit_of_work }
     }
-    pub async fn execute(&self, data: Value) -> Result<()> {
-        let line_data = data["lines"]["data"][0].clone();
+    pub async fn execute(
+        &self,
+        stripe_subscription: StripeSubscription,
+        event_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let price = stripe_subscription.price().cloned().ok_or_else(|| {
+            Error::BadRequest(format!(
+                "Subscription {} has no price",
+                stripe_subscription.id
+            ))
+        })?;
 
-        let customer_id = extract_string(&data, "customer")?;
-        let subscription_id = extract_string(&data, "id")?;
-        let price_id = extract_string(&data, "plan/id")?;
-        let product_id = extract_string(&data, "plan/product")?;
-        let cancel_at_period_end = extract_bool(&data, "cancel_at_period_end")?;
+        // Stripe sends the full lifecycle status (incomplete, unpaid, paused, ...);
+        // an unrecognized one leaves the stored status untouched.
+        let status = match stripe_subscription.status {
+            SubscriptionStatus::Unknown => None,
+            status => Some(status),
+        };
 
-        let user = self
-            .user_service
-            .get_by_payment_provider_id(&customer_id)
-            .await?;
-        let mut subscription = self
-            .subscription_service
-            .find_by_user_id(&user.id())
-            .await?;
+        update_customer_subscription(
+            self.unit_of_work.as_ref(),
+            &stripe_subscription.customer,
+            |subscription| {
+                subscription.update(
+                    event_at,
+                    Some(price.id),
+                    Some(price.product),
+                    Some(stripe_subscription.id),
+                    status,
+                    None,
+                    Some(stripe_subscription.cancel_at_period_end),
+                    None,
+                )
+            },
+        )
+        .await
+    }
+}
 
-        subscription.update(
-            Some(price_id),
-            Some(product_id),
-            Some(subscription_id),
-            None,
-            None,
-            Some(cancel_at_period_end),
-            None,
-        );
-        self.subscription_service.update(&subscription).await?;
-        Ok(())
+pub struct SubscriptionCanceledUseCase<W> {
+    pub unit_of_work: Arc<W>,
+}
+impl<W: UnitOfWork> SubscriptionCanceledUseCase<W> {
+    pub fn new(unit_of_work: Arc<W>) -> Self {
+        Self { unit_of_work }
+    }
+    pub async fn execute(
+        &self,
+        stripe_subscription: StripeSubscription,
+        event_at: DateTime<Utc>,
+    ) -> Result<()> {
+        // The price is informational here, the cancellation applies without it.
+        let price = stripe_subscription.price().cloned();
+        let canceled_at = stripe_subscription.canceled_at.unwrap_or(event_at);
+
+        update_customer_subscription(
+            self.unit_of_work.as_ref(),
+            &stripe_subscription.customer,
+            |subscription| {
+                subscription.update(
+                    event_at,
+                    price.as_ref().map(|price| price.id.clone()),
+                    price.map(|price| price.product),
+                    Some(stripe_subscription.id),
+                    Some(SubscriptionStatus::Canceled),
+                    None,
+                    None,
+                    Some(canceled_at),
+                )
+            },
+        )
+        .await
     }
 }
 
-pub struct SubscriptionCanceledUseCase<S, U> {
-    pub subscription_service: SubscriptionService<S>,
+pub struct CheckoutCompletedUseCase<W, U, C> {
+    pub unit_of_work: Arc<W>,
     pub user_service: UserService<U>,
+    pub payment_service: PaymentService<C>,
 }
-impl<S: SubscriptionRepository, U: UserRepository> SubscriptionCanceledUseCase<S, U> {
-    pub fn new(subscription_service: SubscriptionService<S>, user_service: UserService<U>) -> Self {
+impl<W: UnitOfWork, U: UserRepository, C: PaymentClient> CheckoutCompletedUseCase<W, U, C> {
+    pub fn new(
+        unit_of_work: Arc<W>,
+        user_service: UserService<U>,
+        payment_service: PaymentService<C>,
+    ) -> Self {
         Self {
-            subscription_service,
+            unit_of_work,
             user_service,
+            payment_service,
         }
     }
-    pub async fn execute(&self, data: Value) -> Result<()> {
-        let customer_id = extract_string(&data, "customer")?;
-        let price_id = extract_string(&data, "plan/id")?;
-        let product_id = extract_string(&data, "plan/product")?;
-        let subscription_id = extract_string(&data, "id")?;
-        let canceled_at = extract_timestamp(&data, "canceled_at")?;
-        let user = self
-            .user_service
-            .get_by_payment_provider_id(&customer_id)
-            .await?;
-        let mut subscription = self
-            .subscription_service
-            .find_by_user_id(&user.id())
-            .await?;
 
-        subscription.update(
-            Some(price_id),
-            Some(product_id),
-            Some(subscription_id),
-            Some(SubscriptionStatus::Canceled),
-            None,
-            None,
-            Some(canceled_at),
-        );
-        self.subscription_service.update(&subscription).await?;
+    /// Links the Stripe customer to the user who started the checkout and
+    /// creates or refreshes their subscription without waiting for the
+    /// `customer.created` and `invoice.paid` events.
+    pub async fn execute(
+        &self,
+        session: StripeCheckoutSession,
+        event_at: DateTime<Utc>,
+    ) -> Result<()> {
+        if session.mode != "subscription" {
+            tracing::debug!("Ignoring {} checkout session {}", session.mode, session.id);
+            return Ok(());
+        }
+        let customer_id = session.customer.clone().ok_or_else(|| {
+            Error::BadRequest(format!("Checkout session {} has no customer", session.id))
+        })?;
+        let subscription_id = session.subscription.clone().ok_or_else(|| {
+            Error::BadRequest(format!(
+                "Checkout session {} has no subscription",
+                session.id
+            ))
+        })?;
+
+        let user = self.resolve_user(&session, &customer_id).await?;
+        let details = self
+            .payment_service
+            .get_subscription(&subscription_id)
+            .await?;
+        if details.customer() != customer_id {
+            return Err(Error::BadRequest(format!(
+                "Subscription {} does not belong to customer {}",
+                subscription_id, customer_id
+            )));
+        }
+        // The Stripe call above stays outside the transaction, so the row lock
+        // is only held for the writes themselves. The customer is linked in the
+        // same transaction, so a failed sync does not leave it linked alone.
+        let tx = self.unit_of_work.begin().await?;
+        let link = user.stripe_customer_id() != Some(customer_id.as_str());
+        if link {
+            tracing::info!("Linking customer {} to user {}", customer_id, user.id());
+            let mut linked = user.clone();
+            linked.update(None, user.role(), Some(customer_id.clone()));
+            tx.users().update(&linked).await?;
+        }
+        sync_subscription(tx.subscriptions(), &user.id(), &details, event_at).await?;
+        tx.commit().await?;
+        if link {
+            self.user_service.invalidate(&user.id());
+        }
         Ok(())
     }
+
+    async fn resolve_user(
+        &self,
+        session: &StripeCheckoutSession,
+        customer_id: &str,
+    ) -> Result<User> {
+        if let Some(reference) = session.client_reference_id.as_deref() {
+            let user_id = Uuid::parse_str(reference).map_err(|_| {
+                Error::BadRequest(format!(
+                    "Checkout session {} has an invalid client reference id",
+                    session.id
+                ))
+            })?;
+            return self.user_service.get_by_id(&user_id).await;
+        }
+        match self
+            .user_service
+            .get_by_payment_provider_id(customer_id)
+            .await
+        {
+            Err(Error::NotFound(_)) => {
+                let email = session.customer_email.as_deref().ok_or_else(|| {
+                    Error::BadRequest(format!(
+                        "Checkout session {} cannot be matched to a user",
+                        session.id
+                    ))
+                })?;
+                self.user_service.get_by_email(email).await
+            }
+            other => other,
+        }
+    }
 }
 
 pub struct GetSubscriptionUseCase<S> {
@@ -208,3 +361,206 @@ impl<S: SubscriptionRepository> GetSubscriptionUseCase<S> {
         self.subscription_service.find_by_user_id(&user_id).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::user::cache::UserCache;
+    use crate::domain::payment::entities::subscription::SubscriptionDetails;
+    use crate::domain::user::entities::{AuthProviderData, UserIdentity};
+    use crate::infra::memory::payment::InMemoryPaymentClient;
+    use crate::infra::memory::InMemoryDatabase;
+    use serde_json::json;
+    use std::time::Duration;
+
+    async fn register(db: &InMemoryDatabase, email: &str, customer_id: Option<&str>) -> User {
+        let auth = AuthProviderData::new(
+            "firebase".to_string(),
+            format!("uid-{}", email),
+            email.to_string(),
+            true,
+            None,
+            None,
+            Utc::now(),
+        );
+        let user = User::new(email.to_string(), None);
+        let identity = UserIdentity::new(user.id(), &auth);
+        let user = db.users().save(&user, &identity).await.unwrap();
+        if let Some(customer_id) = customer_id {
+            let mut linked = user.clone();
+            linked.update(None, user.role(), Some(customer_id.to_string()));
+            return db.users().update(&linked).await.unwrap();
+        }
+        user
+    }
+
+    fn invoice(customer_id: &str, amount_paid: i64) -> StripeInvoice {
+        serde_json::from_value(json!({
+            "id": "in_test",
+            "customer": customer_id,
+            "customer_email": null,
+            "subscription": "sub_test",
+            "billing_reason": "subscription_create",
+            "amount_paid": amount_paid,
+            "lines": {
+                "data": [{
+                    "price": { "id": "price_test", "product": "prod_test" },
+                    "period": { "start": 1700000000, "end": 1702592000 }
+                }]
+            }
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_invoice_paid_creates_subscription() {
+        let db = InMemoryDatabase::new();
+        let user = register(&db, "john@example.com", Some("cus_test")).await;
+        let use_case = InvoicePaidUseCase::new(Arc::new(db.unit_of_work()));
+
+        use_case
+            .execute(invoice("cus_test", 0), Utc::now())
+            .await
+            .unwrap();
+        let subscription = db
+            .subscriptions()
+            .find_by_user_id(&user.id())
+            .await
+            .unwrap();
+        assert_eq!(subscription.status(), &SubscriptionStatus::Trialing);
+        assert_eq!(subscription.stripe_subscription_id(), "sub_test");
+
+        use_case
+            .execute(invoice("cus_test", 900), Utc::now())
+            .await
+            .unwrap();
+        let subscription = db
+            .subscriptions()
+            .find_by_user_id(&user.id())
+            .await
+            .unwrap();
+        assert_eq!(subscription.status(), &SubscriptionStatus::Active);
+    }
+
+    #[tokio::test]
+    async fn test_invoice_paid_rolls_back_on_failure() {
+        let db = InMemoryDatabase::new();
+        let user = register(&db, "john@example.com", Some("cus_test")).await;
+        let use_case = InvoicePaidUseCase::new(Arc::new(db.unit_of_work()));
+
+        db.failures().fail_times("unit_of_work.commit", 1);
+        let result = use_case.execute(invoice("cus_test", 0), Utc::now()).await;
+        assert!(matches!(result, Err(Error::Database(_))));
+        let subscription = db.subscriptions().find_by_user_id(&user.id()).await;
+        assert!(matches!(subscription, Err(Error::NotFound(_))));
+
+        let result = use_case
+            .execute(invoice("cus_unknown", 0), Utc::now())
+            .await;
+        assert!(matches!(result, Err(Error::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn test_subscription_events_read_price_from_items() {
+        let db = InMemoryDatabase::new();
+        let user = register(&db, "john@example.com", Some("cus_test")).await;
+        InvoicePaidUseCase::new(Arc::new(db.unit_of_work()))
+            .execute(invoice("cus_test", 900), Utc::now())
+            .await
+            .unwrap();
+
+        // Current API versions send `items` without the legacy `plan`.
+        let subscription = |status: &str| -> StripeSubscription {
+            serde_json::from_value(json!({
+                "id": "sub_test",
+                "customer": "cus_test",
+                "status": status,
+                "plan": null,
+                "items": {
+                    "data": [{
+                        "price": { "id": "price_pro", "product": "prod_pro" },
+                        "current_period_end": 1702592000
+                    }]
+                },
+                "cancel_at_period_end": false
+            }))
+            .unwrap()
+        };
+        SubscriptionUpdatedUseCase::new(Arc::new(db.unit_of_work()))
+            .execute(subscription("past_due"), Utc::now())
+            .await
+            .unwrap();
+        let stored = db
+            .subscriptions()
+            .find_by_user_id(&user.id())
+            .await
+            .unwrap();
+        assert_eq!(stored.stripe_price_id(), "price_pro");
+        assert_eq!(stored.status(), &SubscriptionStatus::PastDue);
+
+        SubscriptionCanceledUseCase::new(Arc::new(db.unit_of_work()))
+            .execute(subscription("canceled"), Utc::now())
+            .await
+            .unwrap();
+        let stored = db
+            .subscriptions()
+            .find_by_user_id(&user.id())
+            .await
+            .unwrap();
+        assert_eq!(stored.status(), &SubscriptionStatus::Canceled);
+    }
+
+    #[tokio::test]
+    async fn test_checkout_completed_links_customer() {
+        let db = InMemoryDatabase::new();
+        let user = register(&db, "john@example.com", None).await;
+        let payment = InMemoryPaymentClient::new();
+        payment.add_subscription(SubscriptionDetails::construct(
+            "sub_test".to_string(),
+            "cus_test".to_string(),
+            SubscriptionStatus::Active,
+            "price_test".to_string(),
+            "prod_test".to_string(),
+            None,
+            false,
+            None,
+        ));
+        let use_case = CheckoutCompletedUseCase::new(
+            Arc::new(db.unit_of_work()),
+            UserService::new(Arc::new(db.users()), UserCache::new(Duration::ZERO, 0)),
+            PaymentService::new(Arc::new(payment.clone())),
+        );
+        let session: StripeCheckoutSession = serde_json::from_value(json!({
+            "id": "cs_test",
+            "mode": "subscription",
+            "customer": "cus_test",
+            "customer_email": null,
+            "subscription": "sub_test",
+            "client_reference_id": user.id().to_string(),
+            "payment_status": "paid"
+        }))
+        .unwrap();
+
+        payment.failures().fail_times("payment.get_subscription", 1);
+        let result = use_case.execute(session.clone(), Utc::now()).await;
+        assert!(matches!(result, Err(Error::ApiError(500, _))));
+
+        // The link is rolled back with the subscription it was made for.
+        db.failures().fail_times("unit_of_work.commit", 1);
+        let result = use_case.execute(session.clone(), Utc::now()).await;
+        assert!(matches!(result, Err(Error::Database(_))));
+        let unlinked = db.users().find(&user.id()).await.unwrap().unwrap();
+        assert_eq!(unlinked.stripe_customer_id(), None);
+
+        use_case.execute(session, Utc::now()).await.unwrap();
+        let linked = db.users().find(&user.id()).await.unwrap().unwrap();
+        assert_eq!(linked.stripe_customer_id(), Some("cus_test"));
+        let subscription = db
+            .subscriptions()
+            .find_by_user_id(&user.id())
+            .await
+            .unwrap();
+        assert_eq!(subscription.stripe_customer_id(), "cus_test");
+        assert_eq!(subscription.status(), &SubscriptionStatus::Active);
+    }
+}
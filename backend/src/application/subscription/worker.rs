@@ -0,0 +1,161 @@
+use crate::application::subscription::dispatcher::WebhookDispatcher;
+use crate::application::subscription::service::WebhookEventService;
+use crate::domain::catalog::repository::CatalogRepository;
+use crate::domain::payment::client::PaymentClient;
+use crate::domain::subscription::entities::WebhookEvent;
+use crate::domain::subscription::repository::WebhookEventRepository;
+use crate::domain::unit_of_work::UnitOfWork;
+use crate::domain::user::repositories::UserRepository;
+use crate::prelude::*;
+use chrono::{DateTime, TimeDelta, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: i32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+impl RetryPolicy {
+    /// Exponential backoff: `base_delay * 2^(attempt - 1)`, capped at `max_delay`.
+    pub fn delay_for(&self, attempt: i32) -> Duration {
+        let exponent = attempt.saturating_sub(1).clamp(0, 31) as u32;
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(exponent))
+            .min(self.max_delay)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WorkerSettings {
+    pub poll_interval: Duration,
+    pub batch_size: i64,
+    pub lease: Duration,
+    pub retry: RetryPolicy,
+}
+
+pub struct WebhookWorker<R, W, U, C, K> {
+    events: WebhookEventService<R>,
+    dispatcher: WebhookDispatcher<W, U, C, K>,
+    settings: WorkerSettings,
+    notifier: Arc<Notify>,
+}
+impl<R, W, U, C, K> WebhookWorker<R, W, U, C, K>
+where
+    R: WebhookEventRepository,
+    W: UnitOfWork,
+    U: UserRepository + Clone,
+    C: PaymentClient + Clone,
+    K: CatalogRepository,
+{
+    pub fn new(
+        events: WebhookEventService<R>,
+        dispatcher: WebhookDispatcher<W, U, C, K>,
+        settings: WorkerSettings,
+        notifier: Arc<Notify>,
+    ) -> Self {
+        Self {
+            events,
+            dispatcher,
+            settings,
+            notifier,
+        }
+    }
+
+    /// Drains due events, then sleeps until the poll interval elapses or the
+    /// webhook handler signals that a new event was stored.
+    pub async fn run(self) {
+        tracing::info!("Webhook worker started");
+        loop {
+            if let Err(e) = self.drain().await {
+                tracing::error!("Webhook worker failed to drain events: {}", e);
+            }
+            tokio::select! {
+                _ = self.notifier.notified() => {}
+                _ = tokio::time::sleep(self.settings.poll_interval) => {}
+            }
+        }
+    }
+
+    pub async fn drain(&self) -> Result<()> {
+        loop {
+            let now = Utc::now();
+            let events = self
+                .events
+                .claim_due(
+                    now,
+                    after(now, self.settings.lease),
+                    self.settings.batch_size,
+                )
+                .await?;
+            if events.is_empty() {
+                return Ok(());
+            }
+            for event in events {
+                self.process(event).await?;
+            }
+        }
+    }
+
+    /// Dispatches one claimed event and stores the outcome: processed, due
+    /// for a retry, or dead.
+    pub async fn process(&self, mut event: WebhookEvent) -> Result<WebhookEvent> {
+        event.start_attempt();
+        match self.dispatcher.dispatch(&event).await {
+            Ok(()) => {
+                tracing::info!("Webhook event {} processed", event.id());
+                event.mark_processed();
+            }
+            Err(e) if event.attempts() >= self.settings.retry.max_attempts => {
+                tracing::error!(
+                    "Webhook event {} moved to dead letter after {} attempts: {}",
+                    event.id(),
+                    event.attempts(),
+                    e
+                );
+                event.mark_dead(e.to_string());
+            }
+            Err(e) => {
+                let delay = self.settings.retry.delay_for(event.attempts());
+                tracing::warn!(
+                    "Webhook event {} failed (attempt {}), retrying in {:?}: {}",
+                    event.id(),
+                    event.attempts(),
+                    delay,
+                    e
+                );
+                event.schedule_retry(e.to_string(), after(Utc::now(), delay));
+            }
+        }
+        self.events.update(&event).await
+    }
+}
+
+fn after(from: DateTime<Utc>, delay: Duration) -> DateTime<Utc> {
+    TimeDelta::from_std(delay)
+        .ok()
+        .and_then(|delta| from.checked_add_signed(delta))
+        .unwrap_or(DateTime::<Utc>::MAX_UTC)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_policy_backoff() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_secs(30),
+            max_delay: Duration::from_secs(300),
+        };
+
+        assert_eq!(policy.delay_for(1), Duration::from_secs(30));
+        assert_eq!(policy.delay_for(2), Duration::from_secs(60));
+        assert_eq!(policy.delay_for(3), Duration::from_secs(120));
+        assert_eq!(policy.delay_for(5), Duration::from_secs(300));
+        assert_eq!(policy.delay_for(40), Duration::from_secs(300));
+    }
+}
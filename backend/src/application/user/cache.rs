@@ -0,0 +1,144 @@
+use crate::application::user::dtos::UserDto;
+use crate::domain::user::entities::AuthProviderData;
+use chrono::{DateTime, TimeDelta, Utc};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use uuid::Uuid;
+
+#[derive(Debug, Clone)]
+struct CachedUser {
+    auth: AuthProviderData,
+    user: UserDto,
+    expires_at: DateTime<Utc>,
+}
+
+/// Longest time an entry is kept, whatever the configuration says.
+const MAX_TTL: TimeDelta = TimeDelta::days(1);
+
+/// In-process map from bearer tokens to the users they resolved to, so a
+/// request does not pay for a token verification and a user lookup when the
+/// same token was seen recently. Entries never outlive their token and are
+/// dropped when the user changes.
+#[derive(Debug, Clone)]
+pub struct UserCache {
+    ttl: TimeDelta,
+    max_entries: usize,
+    entries: Arc<RwLock<HashMap<String, CachedUser>>>,
+}
+impl UserCache {
+    /// A zero `ttl` or `max_entries` disables the cache. `ttl` is capped at
+    /// a day.
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            ttl: TimeDelta::from_std(ttl).map_or(MAX_TTL, |ttl| ttl.min(MAX_TTL)),
+            max_entries,
+            entries: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub fn get(&self, token: &str) -> Option<(AuthProviderData, UserDto)> {
+        let entries = self.entries.read().unwrap();
+        entries
+            .get(&key(token))
+            .filter(|entry| entry.expires_at > Utc::now())
+            .map(|entry| (entry.auth.clone(), entry.user.clone()))
+    }
+
+    pub fn insert(&self, token: &str, auth: &AuthProviderData, user: &UserDto) {
+        if self.ttl.is_zero() || self.max_entries == 0 {
+            return;
+        }
+        let now = Utc::now();
+        let expires_at = now
+            .checked_add_signed(self.ttl)
+            .map_or(auth.expires_at, |expires_at| {
+                expires_at.min(auth.expires_at)
+            });
+        let mut entries = self.entries.write().unwrap();
+        if entries.len() >= self.max_entries {
+            entries.retain(|_, entry| entry.expires_at > now);
+        }
+        if entries.len() >= self.max_entries {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.expires_at)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(
+            key(token),
+            CachedUser {
+                auth: auth.clone(),
+                user: user.clone(),
+                expires_at,
+            },
+        );
+    }
+
+    /// Drops every token of the user, e.g. after its status or role changed.
+    pub fn invalidate(&self, user_id: &Uuid) {
+        let mut entries = self.entries.write().unwrap();
+        entries.retain(|_, entry| entry.user.id != *user_id);
+    }
+}
+
+/// Tokens are credentials, only their digest is kept in memory.
+fn key(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::user::entities::User;
+
+    fn auth(expires_at: DateTime<Utc>) -> AuthProviderData {
+        AuthProviderData::new(
+            "firebase".to_string(),
+            "uid".to_string(),
+            "john@example.com".to_string(),
+            true,
+            None,
+            None,
+            expires_at,
+        )
+    }
+
+    fn user() -> UserDto {
+        let user = User::new("john@example.com".to_string(), None);
+        let mut user = UserDto::try_from(&user).unwrap();
+        user.id = Uuid::new_v4();
+        user
+    }
+
+    #[test]
+    fn test_user_cache() {
+        let cache = UserCache::new(Duration::from_secs(60), 2);
+        let valid = auth(Utc::now() + TimeDelta::hours(1));
+        let (john, jane) = (user(), user());
+
+        cache.insert("token-1", &valid, &john);
+        assert_eq!(cache.get("token-1").unwrap().1.id, john.id);
+        assert!(cache.get("unknown").is_none());
+
+        // Entries never outlive the token, and make room once expired.
+        cache.insert("expired", &auth(Utc::now()), &jane);
+        assert!(cache.get("expired").is_none());
+        cache.insert("token-2", &valid, &jane);
+        assert_eq!(cache.entries.read().unwrap().len(), 2);
+
+        cache.invalidate(&john.id);
+        assert!(cache.get("token-1").is_none());
+        assert!(cache.get("token-2").is_some());
+
+        // An unbounded TTL is capped instead of overflowing.
+        let cache = UserCache::new(Duration::MAX, 1);
+        assert_eq!(cache.ttl, MAX_TTL);
+        cache.insert("token-1", &valid, &john);
+        assert!(cache.get("token-1").is_some());
+    }
+}
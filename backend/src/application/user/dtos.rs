@@ -1,4 +1,4 @@
-use crate::domain::user::entities::{Profile, User};
+use crate::domain::user::entities::{Profile, User, UserStatusChange};
 use crate::domain::user::value_objects::role::Role;
 use crate::domain::user::value_objects::user_status::UserStatus;
 use crate::prelude::*;
@@ -15,9 +15,9 @@ pub struct NewUserDto {
 pub struct UserDto {
     pub id: Uuid,
     pub email: String,
-    pub firebase_id: String,
     pub stripe_customer_id: Option<String>,
     pub status: UserStatus,
+    pub status_reason: Option<String>,
     pub role: Role,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
@@ -30,9 +30,9 @@ impl TryFrom<&User> for UserDto {
         Ok(Self {
             id: user.id(),
             email: user.email().to_string(),
-            firebase_id: user.firebase_id().to_string(),
             stripe_customer_id: user.stripe_customer_id().map(|s| s.to_string()),
             status: user.status(),
+            status_reason: user.status_reason().map(|s| s.to_string()),
             role: user.role(),
             created_at: user.created_at(),
             updated_at: user.updated_at(),
@@ -47,9 +47,9 @@ impl TryFrom<&UserDto> for User {
         Ok(User::construct(
             user_dto.id,
             user_dto.email.clone(),
-            user_dto.firebase_id.clone(),
             user_dto.stripe_customer_id.clone(),
             user_dto.status,
+            user_dto.status_reason.clone(),
             user_dto.role,
             user_dto.created_at,
             user_dto.updated_at,
@@ -69,7 +69,11 @@ impl TryFrom<&UserDto> for User {
 
 #[derive(Debug, Deserialize)]
 pub struct UpdateUserDto {
+    /// Not accepted from clients, linked by checkout and Stripe webhooks.
+    #[serde(skip_deserializing)]
     pub stripe_customer_id: Option<String>,
+    /// Not accepted from clients, admins change it through the status endpoint.
+    #[serde(skip_deserializing)]
     pub status: Option<UserStatus>,
     pub first_name: Option<String>,
     pub last_name: Option<String>,
@@ -123,3 +127,33 @@ impl TryFrom<&Profile> for ProfileDto {
         })
     }
 }
+
+#[derive(Debug, Deserialize)]
+pub struct ChangeUserStatusDto {
+    pub status: UserStatus,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UserStatusChangeDto {
+    pub id: i32,
+    pub user_id: Uuid,
+    pub actor_id: Option<Uuid>,
+    pub old_status: UserStatus,
+    pub new_status: UserStatus,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+impl From<&UserStatusChange> for UserStatusChangeDto {
+    fn from(change: &UserStatusChange) -> Self {
+        Self {
+            id: change.id(),
+            user_id: change.user_id(),
+            actor_id: change.actor_id(),
+            old_status: change.old_status(),
+            new_status: change.new_status(),
+            reason: change.reason().map(|s| s.to_string()),
+            created_at: change.created_at(),
+        }
+    }
+}
@@ -1,87 +1,112 @@
 use crate::application::user::dtos::UserDto;
-use crate::application::user::use_cases::GetUserByAuthProviderIdUseCase;
-use crate::domain::user::entities::AuthProviderData;
-use crate::infra::dependencies::AppState;
+use crate::application::user::middleware::Authentication;
+use crate::domain::user::entities::{AuthProviderData, User};
+use crate::domain::user::value_objects::permission::Permission;
 use crate::prelude::*;
 use actix_web::dev::Payload;
-use actix_web::web::Data;
-use actix_web::{FromRequest, HttpRequest};
-use futures_util::future::LocalBoxFuture;
+use actix_web::{FromRequest, HttpMessage, HttpRequest};
+use futures_util::future::{ready, Ready};
+use std::marker::PhantomData;
+
+fn authentication(req: &HttpRequest) -> Result<(AuthProviderData, Option<UserDto>)> {
+    match req.extensions().get::<Authentication>() {
+        Some(Authentication::Authenticated { auth, user }) => Ok((auth.clone(), user.clone())),
+        Some(Authentication::Rejected(cause)) => Err(Error::InvalidToken(cause.clone())),
+        Some(Authentication::Unavailable) => Err(Error::InternalError),
+        Some(Authentication::Anonymous) => {
+            tracing::error!("Unauthorized");
+            Err(Error::Unauthorized)
+        }
+        None => {
+            tracing::error!("Authentication middleware is not mounted");
+            Err(Error::Unauthorized)
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Authenticate(pub AuthProviderData);
 
 impl FromRequest for Authenticate {
     type Error = Error;
-    type Future = LocalBoxFuture<'static, Result<Self>>;
+    type Future = Ready<Result<Self>>;
 
     fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
-        tracing::info!("Extracting user from request");
-        let bearer_token = req
-            .headers()
-            .get("Authorization")
-            .and_then(|value| value.to_str().ok())
-            .map(|value| value.replace("Bearer ", ""))
-            .map(String::from);
-
-        let app_state = req.app_data::<Data<AppState>>().cloned();
-
-        Box::pin(async move {
-            tracing::debug!("Inside the Box::pin block");
+        ready(authentication(req).map(|(auth, _)| Authenticate(auth)))
+    }
+}
 
-            if let (Some(token), Some(state)) = (bearer_token, app_state) {
-                let auth_service = state.auth_service.clone();
-                match auth_service.authenticate(&token).await {
-                    Ok(auth) => Ok(Authenticate(auth)),
-                    Err(e) => {
-                        tracing::error!("Invalid credentials: {}", e);
-                        Err(Error::InvalidToken(e.to_string()))
-                    }
-                }
-            } else {
-                tracing::error!("Unauthorized");
-                Err(Error::Unauthorized)
-            }
-        })
+/// The registered user of the request, if its status lets it in. Pending
+/// users pass only when `onboarding` is set.
+fn current_user(req: &HttpRequest, onboarding: bool) -> Result<UserDto> {
+    let user = match authentication(req)? {
+        (_, Some(user)) => user,
+        (_, None) => return Err(Error::NotFound("User not found".to_string())),
+    };
+    if let Err(e) = User::try_from(&user)?.ensure_access(onboarding) {
+        tracing::warn!("Rejecting {} user {}", user.status, user.id);
+        return Err(e);
     }
+    Ok(user)
 }
 
-pub struct UserExtractor(pub UserDto);
+/// Type level marker naming the permission a [`RequirePermission`] checks.
+pub trait RequiredPermission {
+    const PERMISSION: Permission;
+}
+
+/// The current user, rejected with 403 unless its role grants
+/// `P::PERMISSION`, e.g. `RequirePermission<permissions::ReadUsers>`.
+/// Pending users pass only for onboarding permissions.
+pub struct RequirePermission<P: RequiredPermission>(pub UserDto, PhantomData<P>);
 
-impl FromRequest for UserExtractor {
+impl<P: RequiredPermission> FromRequest for RequirePermission<P> {
     type Error = Error;
-    type Future = LocalBoxFuture<'static, Result<Self>>;
+    type Future = Ready<Result<Self>>;
 
     fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
-        let bearer_token = req
-            .headers()
-            .get("Authorization")
-            .and_then(|value| value.to_str().ok())
-            .map(|value| value.replace("Bearer ", ""))
-            .map(String::from);
-
-        let app_state = req.app_data::<Data<AppState>>().cloned();
+        let user = current_user(req, P::PERMISSION.allowed_while_pending());
+        ready(user.and_then(|user| {
+            if user.role.can(P::PERMISSION) {
+                Ok(RequirePermission(user, PhantomData))
+            } else {
+                tracing::warn!(
+                    "User {} with role {} lacks permission {:?}",
+                    user.id,
+                    user.role,
+                    P::PERMISSION
+                );
+                Err(Error::Forbidden(format!(
+                    "Role {} may not perform this action",
+                    user.role
+                )))
+            }
+        }))
+    }
+}
 
-        Box::pin(async move {
-            if let (Some(token), Some(state)) = (bearer_token, app_state) {
-                let auth_service = state.auth_service.clone();
-                let user_service = state.user_service.clone();
-                let use_case = GetUserByAuthProviderIdUseCase::new(user_service);
+/// Markers for [`RequirePermission`], one per guarded [`Permission`].
+pub mod permissions {
+    use super::RequiredPermission;
+    use crate::domain::user::value_objects::permission::Permission;
 
-                match auth_service.authenticate(&token).await {
-                    Ok(auth) => {
-                        let user = use_case.execute(&auth.id).await?;
-                        Ok(UserExtractor(user))
-                    }
-                    Err(e) => {
-                        tracing::error!("Invalid credentials: {}", e);
-                        Err(Error::InvalidToken(e.to_string()))
-                    }
+    macro_rules! permission_markers {
+        ($($name:ident),* $(,)?) => {
+            $(
+                pub struct $name;
+                impl RequiredPermission for $name {
+                    const PERMISSION: Permission = Permission::$name;
                 }
-            } else {
-                tracing::error!("Unauthorized");
-                Err(Error::Unauthorized)
-            }
-        })
+            )*
+        };
     }
+
+    permission_markers!(
+        ReadOwnAccount,
+        ManageOwnAccount,
+        ManageOwnBilling,
+        ReadUsers,
+        ManageUsers,
+        ManageRoles,
+    );
 }
@@ -0,0 +1,69 @@
+use crate::application::user::dtos::UserDto;
+use crate::application::user::use_cases::ExtractUserUseCase;
+use crate::domain::user::entities::AuthProviderData;
+use crate::infra::dependencies::AppState;
+use crate::prelude::*;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::web::Data;
+use actix_web::{HttpMessage, HttpRequest};
+
+/// Outcome of authenticating a request, stored in its extensions by
+/// [`authenticate`] and read by the extractors.
+#[allow(clippy::large_enum_variant)] // One per request, never collected.
+#[derive(Debug, Clone)]
+pub enum Authentication {
+    /// No bearer token was sent.
+    Anonymous,
+    /// The bearer token was rejected.
+    Rejected(String),
+    /// The token or its user could not be looked up, e.g. the database was
+    /// unreachable. Only routes needing a user fail.
+    Unavailable,
+    /// The token is valid. `user` is `None` until the identity logged in once.
+    Authenticated {
+        auth: AuthProviderData,
+        user: Option<UserDto>,
+    },
+}
+
+/// Resolves the bearer token of every request once, so handlers taking
+/// several extractors do not verify it or look the user up repeatedly.
+/// Requests are never rejected here, routes decide through their extractors.
+pub async fn authenticate(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> std::result::Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let authentication = match (
+        bearer_token(req.request()),
+        req.app_data::<Data<AppState>>(),
+    ) {
+        (Some(token), Some(state)) => {
+            let use_case =
+                ExtractUserUseCase::new(state.auth_service.clone(), state.user_service.clone());
+            match use_case.execute(&token).await {
+                Ok((auth, user)) => Authentication::Authenticated { auth, user },
+                Err(e @ (Error::InvalidToken(_) | Error::AuthenticationFailed(_))) => {
+                    tracing::error!("Invalid credentials: {}", e);
+                    Authentication::Rejected(e.to_string())
+                }
+                Err(e) => {
+                    tracing::error!("Failed to authenticate request: {}", e);
+                    Authentication::Unavailable
+                }
+            }
+        }
+        _ => Authentication::Anonymous,
+    };
+    req.extensions_mut().insert(authentication);
+    next.call(req).await
+}
+
+fn bearer_token(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(String::from)
+}
@@ -1,4 +1,6 @@
+pub mod cache;
 pub mod dtos;
 pub mod extractor;
+pub mod middleware;
 pub mod service;
 pub mod use_cases;
@@ -1,7 +1,11 @@
+use crate::application::user::cache::UserCache;
 use crate::application::user::dtos::{UpdateUserDto, UserDto};
-use crate::domain::user::entities::{AuthProviderData, User};
-use crate::domain::user::repositories::UserRepository;
+use crate::domain::pagination::{Page, PageRequest};
+use crate::domain::user::entities::{AuthProviderData, User, UserIdentity, UserStatusChange};
+use crate::domain::user::repositories::{UserCursor, UserFilter, UserRepository, UserSort};
 use crate::domain::user::services::Authenticator;
+use crate::domain::user::value_objects::role::Role;
+use crate::domain::user::value_objects::user_status::UserStatus;
 use crate::prelude::*;
 use std::sync::Arc;
 use uuid::Uuid;
@@ -9,14 +13,15 @@ use uuid::Uuid;
 #[derive(Debug, Clone)]
 pub struct UserService<U> {
     user_repo: Arc<U>,
+    cache: UserCache,
 }
 impl<U: UserRepository> UserService<U> {
-    pub fn new(user_repo: Arc<U>) -> Self {
-        Self { user_repo }
+    pub fn new(user_repo: Arc<U>, cache: UserCache) -> Self {
+        Self { user_repo, cache }
     }
 
-    pub async fn register(&self, new_user: &User) -> Result<UserDto> {
-        let user = self.user_repo.save(new_user).await?;
+    pub async fn register(&self, new_user: &User, identity: &UserIdentity) -> Result<UserDto> {
+        let user = self.user_repo.save(new_user, identity).await?;
         let user = UserDto::try_from(&user)?;
         Ok(user)
     }
@@ -36,6 +41,17 @@ impl<U: UserRepository> UserService<U> {
         }
     }
 
+    pub async fn get_by_id(&self, id: &Uuid) -> Result<User> {
+        match self.user_repo.find(id).await {
+            Ok(Some(user)) => Ok(user),
+            Ok(None) => {
+                tracing::info!("User {} not found", id);
+                Err(Error::NotFound("User not found".to_string()))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
     pub async fn get_by_email(&self, email: &str) -> Result<User> {
         let user = self.user_repo.find_by_email(email).await;
         match user {
@@ -51,21 +67,26 @@ impl<U: UserRepository> UserService<U> {
         }
     }
 
-    pub async fn get_by_auth_provider_id(&self, auth_id: &str) -> Result<User> {
-        let user = self.user_repo.find_by_firebase_id(auth_id).await;
+    pub async fn get_by_identity(&self, provider: &str, subject: &str) -> Result<User> {
+        let user = self.user_repo.find_by_identity(provider, subject).await;
         match user {
-            Ok(Some(user)) => {
-                // let user = UserDto::try_from(&user)?;
-                Ok(user)
-            }
+            Ok(Some(user)) => Ok(user),
             Ok(None) => {
-                tracing::info!("User with auth id {} not found", auth_id);
+                tracing::info!("User with {} identity {} not found", provider, subject);
                 Err(Error::NotFound("User not found".to_string()))
             }
             Err(e) => Err(e),
         }
     }
 
+    pub async fn identities(&self, user_id: &Uuid) -> Result<Vec<UserIdentity>> {
+        self.user_repo.find_identities(user_id).await
+    }
+
+    pub async fn save_identity(&self, identity: &UserIdentity) -> Result<UserIdentity> {
+        self.user_repo.save_identity(identity).await
+    }
+
     pub async fn get_by_payment_provider_id(&self, pay_provider_id: &str) -> Result<User> {
         let user = self
             .user_repo
@@ -98,13 +119,75 @@ impl<U: UserRepository> UserService<U> {
         user.update(updates.status, user.role(), updates.stripe_customer_id);
 
         let user = self.user_repo.update(&user).await?;
+        self.cache.invalidate(&user.id());
 
         // let user = UserDto::try_from(&user)?;
         Ok(user)
     }
 
+    pub async fn list(
+        &self,
+        filter: &UserFilter,
+        sort: UserSort,
+        page: &PageRequest<UserCursor>,
+    ) -> Result<Page<User, UserCursor>> {
+        self.user_repo.find_page(filter, sort, page).await
+    }
+
+    pub async fn count(&self, filter: &UserFilter) -> Result<i64> {
+        self.user_repo.count(filter).await
+    }
+
+    pub async fn change_role(&self, user: &User, role: Role) -> Result<User> {
+        let mut user = user.clone();
+        user.update(None, role, None);
+        let user = self.user_repo.update(&user).await?;
+        self.cache.invalidate(&user.id());
+        Ok(user)
+    }
+
+    pub async fn change_status(
+        &self,
+        user: &User,
+        status: UserStatus,
+        reason: Option<String>,
+        actor_id: Option<Uuid>,
+    ) -> Result<User> {
+        let mut user = user.clone();
+        let change = user.change_status(status, reason, actor_id);
+        let user = self.user_repo.change_status(&user, &change).await?;
+        self.cache.invalidate(&user.id());
+        tracing::info!(
+            "User {} status changed from {} to {}",
+            user.id(),
+            change.old_status(),
+            change.new_status()
+        );
+        Ok(user)
+    }
+
+    pub async fn status_changes(&self, user_id: &Uuid) -> Result<Vec<UserStatusChange>> {
+        self.user_repo.find_status_changes(user_id).await
+    }
+
     pub async fn delete(&self, id: &Uuid) -> Result<()> {
-        self.user_repo.delete(id).await
+        self.user_repo.delete(id).await?;
+        self.cache.invalidate(id);
+        Ok(())
+    }
+
+    pub fn cached(&self, token: &str) -> Option<(AuthProviderData, UserDto)> {
+        self.cache.get(token)
+    }
+
+    pub fn cache(&self, token: &str, auth: &AuthProviderData, user: &UserDto) {
+        self.cache.insert(token, auth, user);
+    }
+
+    /// Drops the cached copies of a user changed outside this service, e.g.
+    /// in a transaction.
+    pub fn invalidate(&self, id: &Uuid) {
+        self.cache.invalidate(id);
     }
 }
 
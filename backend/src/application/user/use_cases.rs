@@ -1,8 +1,11 @@
-use crate::application::user::dtos::{UpdateUserDto, UserDto};
+use crate::application::user::dtos::{
+    ChangeUserStatusDto, UpdateUserDto, UserDto, UserStatusChangeDto,
+};
 use crate::application::user::service::{AuthenticationService, UserService};
-use crate::domain::user::entities::{AuthProviderData, User};
+use crate::domain::user::entities::{can_link_identity, AuthProviderData, User, UserIdentity};
 use crate::domain::user::repositories::UserRepository;
 use crate::domain::user::services::Authenticator;
+use crate::domain::user::value_objects::permission::Permission;
 use crate::prelude::*;
 use uuid::Uuid;
 
@@ -15,12 +18,55 @@ impl<U: UserRepository> LoginUseCase<U> {
         Self { user_service }
     }
 
+    /// Resolves the user by the token identity first. An unknown identity is
+    /// linked to the account owning its email when [`can_link_identity`]
+    /// allows it, and creates a new account when no account owns the email.
     pub async fn execute(&self, auth: &AuthProviderData) -> Result<UserDto> {
+        match self
+            .user_service
+            .get_by_identity(&auth.provider, &auth.id)
+            .await
+        {
+            Ok(user) => {
+                user.ensure_access(true)?;
+                // Keep the email claims fresh, they decide future linking.
+                let identity = UserIdentity::new(user.id(), auth);
+                self.user_service.save_identity(&identity).await?;
+                return UserDto::try_from(&user);
+            }
+            Err(Error::NotFound(_)) => {}
+            Err(e) => return Err(e),
+        }
+
         match self.user_service.get_by_email(&auth.email).await {
-            Ok(user) => UserDto::try_from(&user),
+            Ok(user) => {
+                user.ensure_access(true)?;
+                let identities = self.user_service.identities(&user.id()).await?;
+                if !can_link_identity(&identities, auth) {
+                    tracing::warn!(
+                        "Refusing to link {} identity {} to user {}",
+                        auth.provider,
+                        auth.id,
+                        user.id()
+                    );
+                    return Err(Error::IdentityConflict(
+                        "An account with this email already exists. Sign in with its original method and verify the email to link this one".to_string(),
+                    ));
+                }
+                let identity = UserIdentity::new(user.id(), auth);
+                self.user_service.save_identity(&identity).await?;
+                tracing::info!(
+                    "Linked {} identity {} to user {}",
+                    auth.provider,
+                    auth.id,
+                    user.id()
+                );
+                UserDto::try_from(&user)
+            }
             Err(Error::NotFound(_)) => {
-                let user = User::new(auth.email.clone(), auth.id.to_string(), None);
-                let user = self.user_service.register(&user).await?;
+                let user = User::new(auth.email.clone(), None);
+                let identity = UserIdentity::new(user.id(), auth);
+                let user = self.user_service.register(&user, &identity).await?;
                 Ok(user)
             }
             Err(e) => Err(e),
@@ -43,21 +89,6 @@ impl<U: UserRepository> GetUserByIdUseCase<U> {
     }
 }
 
-#[derive(Clone)]
-pub struct GetUserByAuthProviderIdUseCase<U: UserRepository> {
-    user_service: UserService<U>,
-}
-impl<U: UserRepository> GetUserByAuthProviderIdUseCase<U> {
-    pub fn new(user_service: UserService<U>) -> Self {
-        Self { user_service }
-    }
-
-    pub async fn execute(&self, auth_id: &str) -> Result<UserDto> {
-        let user = self.user_service.get_by_auth_provider_id(auth_id).await?;
-        UserDto::try_from(&user)
-    }
-}
-
 #[derive(Clone)]
 pub struct GetUserByPaymentProviderIdUseCase<U: UserRepository> {
     user_service: UserService<U>,
@@ -121,6 +152,60 @@ impl<U: UserRepository> DeleteUserUseCase<U> {
     }
 }
 
+#[derive(Clone)]
+pub struct ChangeUserStatusUseCase<U: UserRepository> {
+    user_service: UserService<U>,
+}
+impl<U: UserRepository> ChangeUserStatusUseCase<U> {
+    pub fn new(user_service: UserService<U>) -> Self {
+        Self { user_service }
+    }
+
+    pub async fn execute(
+        &self,
+        actor: &UserDto,
+        user_id: &Uuid,
+        change: ChangeUserStatusDto,
+    ) -> Result<UserDto> {
+        if actor.id == *user_id {
+            return Err(Error::BadRequest(
+                "Admins cannot change their own status".to_string(),
+            ));
+        }
+        let user = self.user_service.get_by_id(user_id).await?;
+        // Admins must not lock each other out, only supers manage admins.
+        if user.role().can(Permission::ManageUsers) && !actor.role.can(Permission::ManageRoles) {
+            return Err(Error::Forbidden(format!(
+                "Role {} may not change the status of role {}",
+                actor.role,
+                user.role()
+            )));
+        }
+        let reason = change.reason.filter(|reason| !reason.trim().is_empty());
+        let user = self
+            .user_service
+            .change_status(&user, change.status, reason, Some(actor.id))
+            .await?;
+        UserDto::try_from(&user)
+    }
+}
+
+#[derive(Clone)]
+pub struct GetUserStatusChangesUseCase<U: UserRepository> {
+    user_service: UserService<U>,
+}
+impl<U: UserRepository> GetUserStatusChangesUseCase<U> {
+    pub fn new(user_service: UserService<U>) -> Self {
+        Self { user_service }
+    }
+
+    pub async fn execute(&self, user_id: &Uuid) -> Result<Vec<UserStatusChangeDto>> {
+        let user = self.user_service.get_by_id(user_id).await?;
+        let changes = self.user_service.status_changes(&user.id()).await?;
+        Ok(changes.iter().map(UserStatusChangeDto::from).collect())
+    }
+}
+
 #[derive(Clone)]
 pub struct ExtractUserUseCase<U: UserRepository, A: Authenticator> {
     authenticator: AuthenticationService<A>,
@@ -134,13 +219,27 @@ impl<U: UserRepository, A: Authenticator> ExtractUserUseCase<U, A> {
         }
     }
 
-    pub async fn execute(&self, token: &str) -> Result<UserDto> {
-        let auth_data = self.authenticator.authenticate(token).await?;
-        let user = self
+    /// Resolves a bearer token to its claims and, once the identity is
+    /// registered, to its user. Resolved users are cached per token.
+    pub async fn execute(&self, token: &str) -> Result<(AuthProviderData, Option<UserDto>)> {
+        if let Some((auth, user)) = self.user_service.cached(token) {
+            return Ok((auth, Some(user)));
+        }
+
+        let auth = self.authenticator.authenticate(token).await?;
+        match self
             .user_service
-            .get_by_auth_provider_id(&auth_data.id)
-            .await?;
-        UserDto::try_from(&user)
+            .get_by_identity(&auth.provider, &auth.id)
+            .await
+        {
+            Ok(user) => {
+                let user = UserDto::try_from(&user)?;
+                self.user_service.cache(token, &auth, &user);
+                Ok((auth, Some(user)))
+            }
+            Err(Error::NotFound(_)) => Ok((auth, None)),
+            Err(e) => Err(e),
+        }
     }
 }
 
@@ -158,3 +257,105 @@ impl<A: Authenticator> AuthenticateUserUseCase<A> {
         Ok(auth_data)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::user::cache::UserCache;
+    use crate::infra::memory::authenticator::InMemoryAuthenticator;
+    use crate::infra::memory::InMemoryDatabase;
+    use chrono::Utc;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn claims(provider: &str, subject: &str, email_verified: bool) -> AuthProviderData {
+        AuthProviderData::new(
+            provider.to_string(),
+            subject.to_string(),
+            "john@example.com".to_string(),
+            email_verified,
+            None,
+            None,
+            Utc::now(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_login_links_identities() {
+        let db = InMemoryDatabase::new();
+        let authenticator = InMemoryAuthenticator::new();
+        authenticator.add_token("firebase-token", claims("firebase", "uid", true));
+        authenticator.add_token("oidc-token", claims("https://id.example.com", "sub", true));
+        authenticator.add_token(
+            "unverified-token",
+            claims("https://other.example.com", "sub", false),
+        );
+        let auth = AuthenticationService::new(Arc::new(authenticator.clone()));
+        let login = LoginUseCase::new(UserService::new(
+            Arc::new(db.users()),
+            UserCache::new(Duration::ZERO, 0),
+        ));
+
+        let claims = auth.authenticate("firebase-token").await.unwrap();
+        let registered = login.execute(&claims).await.unwrap();
+        assert_eq!(login.execute(&claims).await.unwrap().id, registered.id);
+
+        let claims = auth.authenticate("oidc-token").await.unwrap();
+        assert_eq!(login.execute(&claims).await.unwrap().id, registered.id);
+        assert_eq!(
+            db.users()
+                .find_identities(&registered.id)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+
+        let claims = auth.authenticate("unverified-token").await.unwrap();
+        assert!(matches!(
+            login.execute(&claims).await,
+            Err(Error::IdentityConflict(_))
+        ));
+
+        assert!(matches!(
+            auth.authenticate("unknown-token").await,
+            Err(Error::InvalidToken(_))
+        ));
+        authenticator
+            .failures()
+            .fail_times("authenticator.authenticate", 1);
+        assert!(matches!(
+            auth.authenticate("firebase-token").await,
+            Err(Error::AuthenticationFailed(_))
+        ));
+
+        db.failures().fail_always("users.find_by_identity");
+        assert!(matches!(
+            login.execute(&claims).await,
+            Err(Error::Database(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_update_ignores_customer_id_from_client() {
+        let db = InMemoryDatabase::new();
+        let service = UserService::new(Arc::new(db.users()), UserCache::new(Duration::ZERO, 0));
+        let user = LoginUseCase::new(service.clone())
+            .execute(&claims("firebase", "uid", true))
+            .await
+            .unwrap();
+
+        let updates: UpdateUserDto = serde_json::from_value(serde_json::json!({
+            "stripe_customer_id": "cus_someone_else",
+            "status": "active",
+            "first_name": "John"
+        }))
+        .unwrap();
+        let updated = UpdateUserUseCase::new(service)
+            .execute(updates, &user)
+            .await
+            .unwrap();
+        assert_eq!(updated.stripe_customer_id, None);
+        assert_eq!(updated.profile.first_name.as_deref(), Some("John"));
+    }
+}
@@ -0,0 +1,222 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Product mirrored from the payment provider catalog.
+#[derive(Debug, Clone, Serialize)]
+pub struct Product {
+    id: String,
+    name: String,
+    description: Option<String>,
+    active: bool,
+    created_at: DateTime<Utc>,
+    updated_at: Option<DateTime<Utc>>,
+}
+impl Product {
+    pub fn new(id: String, name: String, description: Option<String>, active: bool) -> Self {
+        Self {
+            id,
+            name,
+            description,
+            active,
+            created_at: Utc::now(),
+            updated_at: None,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+    pub fn updated_at(&self) -> Option<DateTime<Utc>> {
+        self.updated_at
+    }
+
+    pub fn construct(
+        id: String,
+        name: String,
+        description: Option<String>,
+        active: bool,
+        created_at: DateTime<Utc>,
+        updated_at: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            description,
+            active,
+            created_at,
+            updated_at,
+        }
+    }
+}
+
+/// Price of a [`Product`]. One-off prices have no recurring interval.
+/// Quantity limits apply to a single checkout line item.
+#[derive(Debug, Clone, Serialize)]
+pub struct Price {
+    id: String,
+    product_id: String,
+    active: bool,
+    currency: String,
+    unit_amount: Option<i64>,
+    nickname: Option<String>,
+    recurring_interval: Option<String>,
+    recurring_interval_count: Option<i32>,
+    min_quantity: i32,
+    max_quantity: Option<i32>,
+    created_at: DateTime<Utc>,
+    updated_at: Option<DateTime<Utc>>,
+}
+impl Price {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        id: String,
+        product_id: String,
+        active: bool,
+        currency: String,
+        unit_amount: Option<i64>,
+        nickname: Option<String>,
+        recurring_interval: Option<String>,
+        recurring_interval_count: Option<i32>,
+        min_quantity: i32,
+        max_quantity: Option<i32>,
+    ) -> Self {
+        Self {
+            id,
+            product_id,
+            active,
+            currency,
+            unit_amount,
+            nickname,
+            recurring_interval,
+            recurring_interval_count,
+            min_quantity,
+            max_quantity,
+            created_at: Utc::now(),
+            updated_at: None,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    pub fn product_id(&self) -> &str {
+        &self.product_id
+    }
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+    pub fn unit_amount(&self) -> Option<i64> {
+        self.unit_amount
+    }
+    pub fn nickname(&self) -> Option<&str> {
+        self.nickname.as_deref()
+    }
+    pub fn recurring_interval(&self) -> Option<&str> {
+        self.recurring_interval.as_deref()
+    }
+    pub fn recurring_interval_count(&self) -> Option<i32> {
+        self.recurring_interval_count
+    }
+    pub fn min_quantity(&self) -> i32 {
+        self.min_quantity
+    }
+    pub fn max_quantity(&self) -> Option<i32> {
+        self.max_quantity
+    }
+    pub fn allows_quantity(&self, quantity: i32) -> bool {
+        quantity >= self.min_quantity.max(1) && self.max_quantity.is_none_or(|max| quantity <= max)
+    }
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+    pub fn updated_at(&self) -> Option<DateTime<Utc>> {
+        self.updated_at
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn construct(
+        id: String,
+        product_id: String,
+        active: bool,
+        currency: String,
+        unit_amount: Option<i64>,
+        nickname: Option<String>,
+        recurring_interval: Option<String>,
+        recurring_interval_count: Option<i32>,
+        min_quantity: i32,
+        max_quantity: Option<i32>,
+        created_at: DateTime<Utc>,
+        updated_at: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self {
+            id,
+            product_id,
+            active,
+            currency,
+            unit_amount,
+            nickname,
+            recurring_interval,
+            recurring_interval_count,
+            min_quantity,
+            max_quantity,
+            created_at,
+            updated_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_price_allows_quantity() {
+        let price = Price::new(
+            "price_test".to_string(),
+            "prod_test".to_string(),
+            true,
+            "eur".to_string(),
+            Some(990),
+            None,
+            Some("month".to_string()),
+            Some(1),
+            2,
+            Some(5),
+        );
+        assert!(!price.allows_quantity(1));
+        assert!(price.allows_quantity(2));
+        assert!(price.allows_quantity(5));
+        assert!(!price.allows_quantity(6));
+
+        let unbounded = Price::new(
+            "price_test".to_string(),
+            "prod_test".to_string(),
+            true,
+            "eur".to_string(),
+            Some(990),
+            None,
+            None,
+            None,
+            0,
+            None,
+        );
+        assert!(!unbounded.allows_quantity(0));
+        assert!(!unbounded.allows_quantity(-3));
+        assert!(unbounded.allows_quantity(1000));
+    }
+}
@@ -0,0 +1,24 @@
+use crate::domain::catalog::entities::{Price, Product};
+use crate::prelude::*;
+use chrono::{DateTime, Utc};
+
+/// Every write carries `event_at`, the time the payment provider reported
+/// the state being written. Stripe does not guarantee delivery order, so a
+/// write older than the last one applied to the same object is skipped.
+pub trait CatalogRepository: Send + Sync {
+    /// Inserts the product, or overwrites the stored copy with the same id.
+    /// Fails with `StaleEvent` if the stored copy is newer than `event_at`.
+    async fn upsert_product(&self, product: &Product, event_at: DateTime<Utc>) -> Result<Product>;
+    /// Leaves a product changed after `event_at` in place.
+    async fn delete_product(&self, id: &str, event_at: DateTime<Utc>) -> Result<()>;
+    async fn find_active_products(&self) -> Result<Vec<Product>>;
+    /// Inserts the price, or overwrites the stored copy with the same id.
+    /// Fails with `NotFound` while its product has not been stored yet, and
+    /// with `StaleEvent` if the stored copy is newer than `event_at`.
+    async fn upsert_price(&self, price: &Price, event_at: DateTime<Utc>) -> Result<Price>;
+    /// Leaves a price changed after `event_at` in place.
+    async fn delete_price(&self, id: &str, event_at: DateTime<Utc>) -> Result<()>;
+    async fn find_active_prices(&self) -> Result<Vec<Price>>;
+    /// Prices among `ids` that exist locally, active or not.
+    async fn find_prices(&self, ids: &[String]) -> Result<Vec<Price>>;
+}
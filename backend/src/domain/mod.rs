@@ -1,3 +1,6 @@
+pub mod catalog;
+pub mod pagination;
 pub mod payment;
 pub mod subscription;
+pub mod unit_of_work;
 pub mod user;
@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+
+/// Order of a listing. The tie-breaking id follows the same direction.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Asks for up to `limit` rows following the row `after` points at, or the
+/// first rows when it is `None`.
+#[derive(Debug, Clone)]
+pub struct PageRequest<C> {
+    pub after: Option<C>,
+    pub limit: i64,
+}
+
+/// Rows of a keyset-paginated listing. `next` is set when more rows follow
+/// and is passed back as `PageRequest::after` to fetch them.
+#[derive(Debug, Clone)]
+pub struct Page<T, C> {
+    pub items: Vec<T>,
+    pub next: Option<C>,
+}
+impl<T, C> Page<T, C> {
+    /// Builds a page from a query that fetched up to `limit + 1` rows; the
+    /// extra row is dropped and only tells that another page follows.
+    pub fn from_rows(mut rows: Vec<T>, limit: i64, cursor_of: impl Fn(&T) -> C) -> Self {
+        let limit = usize::try_from(limit).unwrap_or(0);
+        let has_more = rows.len() > limit;
+        rows.truncate(limit);
+        let next = if has_more {
+            rows.last().map(cursor_of)
+        } else {
+            None
+        };
+        Self { items: rows, next }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_from_rows() {
+        let page = Page::from_rows(vec![1, 2, 3], 2, |row| *row);
+        assert_eq!(page.items, vec![1, 2]);
+        assert_eq!(page.next, Some(2));
+
+        let page = Page::from_rows(vec![1, 2], 2, |row| *row);
+        assert_eq!(page.items, vec![1, 2]);
+        assert_eq!(page.next, None);
+
+        let page = Page::<i32, i32>::from_rows(vec![], 2, |row| *row);
+        assert!(page.items.is_empty());
+        assert_eq!(page.next, None);
+    }
+}
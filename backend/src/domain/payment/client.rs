@@ -1,11 +1,26 @@
-use crate::domain::payment::entities::checkout::CheckoutSession;
+use crate::domain::catalog::entities::{Price, Product};
+use crate::domain::payment::entities::checkout::{
+    CheckoutSession, CheckoutSessionDetails, CheckoutSessionResult,
+};
 use crate::domain::payment::entities::customer::Customer;
 use crate::domain::payment::entities::portal::CustomerPortalSession;
+use crate::domain::payment::entities::subscription::SubscriptionDetails;
 use crate::prelude::*;
 
 pub trait PaymentClient: Send + Sync {
     async fn create_customer(&self, customer: &Customer) -> Result<Customer>;
     async fn get_customer(&self, email: &str) -> Result<Customer>;
-    async fn create_checkout_session(&self, checkout: &CheckoutSession) -> Result<String>;
+    async fn create_checkout_session(
+        &self,
+        checkout: &CheckoutSession,
+    ) -> Result<CheckoutSessionResult>;
+    async fn get_checkout_session(&self, session_id: &str) -> Result<CheckoutSessionDetails>;
     async fn create_portal_session(&self, portal: &CustomerPortalSession) -> Result<String>;
+    async fn get_subscription(&self, subscription_id: &str) -> Result<SubscriptionDetails>;
+    /// Every subscription of the customer, canceled ones included, newest first.
+    async fn list_subscriptions(&self, customer_id: &str) -> Result<Vec<SubscriptionDetails>>;
+    /// Every product in the catalog, archived ones included.
+    async fn list_products(&self) -> Result<Vec<Product>>;
+    /// Every price in the catalog, archived ones included.
+    async fn list_prices(&self) -> Result<Vec<Price>>;
 }
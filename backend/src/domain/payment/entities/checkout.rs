@@ -1,3 +1,6 @@
+use crate::domain::payment::value_objects::checkout_mode::CheckoutMode;
+use crate::domain::payment::value_objects::checkout_status::CheckoutStatus;
+use crate::domain::payment::value_objects::ui_mode::UiMode;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -9,22 +12,38 @@ pub struct LineItem {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CheckoutSession {
     customer: String,
+    client_reference_id: Option<String>,
+    mode: CheckoutMode,
+    ui_mode: UiMode,
     line_items: Vec<LineItem>,
     success_url: Option<String>,
     cancel_url: Option<String>,
+    return_url: Option<String>,
+    trial_period_days: Option<u32>,
 }
 impl CheckoutSession {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         customer: String,
+        client_reference_id: Option<String>,
+        mode: CheckoutMode,
+        ui_mode: UiMode,
         line_items: Vec<LineItem>,
         success_url: Option<String>,
         cancel_url: Option<String>,
+        return_url: Option<String>,
+        trial_period_days: Option<u32>,
     ) -> Self {
         Self {
             customer,
+            client_reference_id,
+            mode,
+            ui_mode,
             line_items,
             success_url,
             cancel_url,
+            return_url,
+            trial_period_days,
         }
     }
 
@@ -32,6 +51,18 @@ impl CheckoutSession {
         &self.customer
     }
 
+    pub fn client_reference_id(&self) -> Option<&str> {
+        self.client_reference_id.as_deref()
+    }
+
+    pub fn mode(&self) -> CheckoutMode {
+        self.mode
+    }
+
+    pub fn ui_mode(&self) -> UiMode {
+        self.ui_mode
+    }
+
     pub fn line_items(&self) -> &Vec<LineItem> {
         &self.line_items
     }
@@ -43,6 +74,19 @@ impl CheckoutSession {
     pub fn cancel_url(&self) -> Option<&str> {
         self.cancel_url.as_deref()
     }
+
+    /// Where Stripe sends the customer after an embedded checkout.
+    pub fn return_url(&self) -> Option<&str> {
+        self.return_url.as_deref()
+    }
+
+    /// Only subscription checkouts carry a trial.
+    pub fn trial_period_days(&self) -> Option<u32> {
+        match self.mode {
+            CheckoutMode::Subscription => self.trial_period_days.filter(|days| *days > 0),
+            _ => None,
+        }
+    }
     pub fn add_line_item(&mut self, item: LineItem) {
         self.line_items.push(item);
     }
@@ -56,3 +100,61 @@ impl CheckoutSession {
         self.line_items[index] = item;
     }
 }
+
+/// Checkout session as created by the payment provider. Hosted sessions are
+/// opened through `url`; embedded sessions are mounted with `client_secret`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckoutSessionResult {
+    pub id: String,
+    pub url: Option<String>,
+    pub client_secret: Option<String>,
+    pub ui_mode: UiMode,
+}
+
+/// Current state of a checkout session, as known by the payment provider.
+#[derive(Debug, Clone)]
+pub struct CheckoutSessionDetails {
+    id: String,
+    status: Option<CheckoutStatus>,
+    payment_status: String,
+    customer: Option<String>,
+    customer_email: Option<String>,
+    client_reference_id: Option<String>,
+}
+impl CheckoutSessionDetails {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    pub fn status(&self) -> Option<CheckoutStatus> {
+        self.status
+    }
+    pub fn payment_status(&self) -> &str {
+        &self.payment_status
+    }
+    pub fn customer(&self) -> Option<&str> {
+        self.customer.as_deref()
+    }
+    pub fn customer_email(&self) -> Option<&str> {
+        self.customer_email.as_deref()
+    }
+    pub fn client_reference_id(&self) -> Option<&str> {
+        self.client_reference_id.as_deref()
+    }
+    pub fn construct(
+        id: String,
+        status: Option<CheckoutStatus>,
+        payment_status: String,
+        customer: Option<String>,
+        customer_email: Option<String>,
+        client_reference_id: Option<String>,
+    ) -> Self {
+        Self {
+            id,
+            status,
+            payment_status,
+            customer,
+            customer_email,
+            client_reference_id,
+        }
+    }
+}
@@ -1,3 +1,4 @@
 pub mod checkout;
 pub mod customer;
 pub mod portal;
+pub mod subscription;
@@ -0,0 +1,63 @@
+use crate::domain::subscription::value_objects::subscription_status::SubscriptionStatus;
+use chrono::{DateTime, Utc};
+
+/// Subscription as currently known by the payment provider.
+#[derive(Debug, Clone)]
+pub struct SubscriptionDetails {
+    id: String,
+    customer: String,
+    status: SubscriptionStatus,
+    price_id: String,
+    product_id: String,
+    current_period_end: Option<DateTime<Utc>>,
+    cancel_at_period_end: bool,
+    canceled_at: Option<DateTime<Utc>>,
+}
+impl SubscriptionDetails {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    pub fn customer(&self) -> &str {
+        &self.customer
+    }
+    pub fn status(&self) -> &SubscriptionStatus {
+        &self.status
+    }
+    pub fn price_id(&self) -> &str {
+        &self.price_id
+    }
+    pub fn product_id(&self) -> &str {
+        &self.product_id
+    }
+    pub fn current_period_end(&self) -> Option<DateTime<Utc>> {
+        self.current_period_end
+    }
+    pub fn cancel_at_period_end(&self) -> bool {
+        self.cancel_at_period_end
+    }
+    pub fn canceled_at(&self) -> Option<DateTime<Utc>> {
+        self.canceled_at
+    }
+    #[allow(clippy::too_many_arguments)]
+    pub fn construct(
+        id: String,
+        customer: String,
+        status: SubscriptionStatus,
+        price_id: String,
+        product_id: String,
+        current_period_end: Option<DateTime<Utc>>,
+        cancel_at_period_end: bool,
+        canceled_at: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self {
+            id,
+            customer,
+            status,
+            price_id,
+            product_id,
+            current_period_end,
+            cancel_at_period_end,
+            canceled_at,
+        }
+    }
+}
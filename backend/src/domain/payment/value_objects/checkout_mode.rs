@@ -0,0 +1,48 @@
+use serde::Serialize;
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum CheckoutMode {
+    Payment,
+    #[default]
+    Subscription,
+    Setup,
+}
+impl CheckoutMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Payment => "payment",
+            Self::Subscription => "subscription",
+            Self::Setup => "setup",
+        }
+    }
+}
+impl Display for CheckoutMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+impl Serialize for CheckoutMode {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+impl<'de> serde::Deserialize<'de> for CheckoutMode {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        match s.as_str() {
+            "payment" => Ok(Self::Payment),
+            "subscription" => Ok(Self::Subscription),
+            "setup" => Ok(Self::Setup),
+            _ => Err(serde::de::Error::custom(
+                "expected 'payment', 'subscription' or 'setup'",
+            )),
+        }
+    }
+}
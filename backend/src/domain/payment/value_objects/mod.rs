@@ -1 +1,3 @@
+pub mod checkout_mode;
+pub mod checkout_status;
 pub mod ui_mode;
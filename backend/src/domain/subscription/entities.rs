@@ -1,6 +1,9 @@
 use crate::domain::subscription::value_objects::subscription_status::SubscriptionStatus;
+use crate::domain::subscription::value_objects::webhook_event_status::WebhookEventStatus;
+use crate::prelude::*;
 use chrono::{DateTime, Utc};
 use serde::Serialize;
+use serde_json::Value;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize)]
@@ -18,8 +21,11 @@ pub struct Subscription {
     canceled_at: Option<DateTime<Utc>>,
     created_at: DateTime<Utc>,
     updated_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing)]
+    last_event_at: Option<DateTime<Utc>>,
 }
 impl Subscription {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         user_id: Uuid,
         stripe_customer_id: String,
@@ -30,6 +36,7 @@ impl Subscription {
         has_used_trial: bool,
         current_period_end: Option<DateTime<Utc>>,
         cancel_at_period_end: bool,
+        last_event_at: Option<DateTime<Utc>>,
     ) -> Self {
         Self {
             id: Default::default(),
@@ -45,6 +52,7 @@ impl Subscription {
             canceled_at: None,
             created_at: Utc::now(),
             updated_at: None,
+            last_event_at,
         }
     }
 
@@ -100,8 +108,20 @@ impl Subscription {
         self.updated_at
     }
 
+    pub fn last_event_at(&self) -> Option<DateTime<Utc>> {
+        self.last_event_at
+    }
+
+    /// Applies the changes carried by a Stripe event created at `event_at`.
+    ///
+    /// Stripe does not guarantee delivery order, so an event older than the
+    /// last one applied is rejected with `Error::StaleEvent` and leaves the
+    /// subscription untouched. `created` only has second precision, so events
+    /// from the same second are told apart by the status: one moving the same
+    /// Stripe subscription out of a terminal status is stale too.
     pub fn update(
         &mut self,
+        event_at: DateTime<Utc>,
         stripe_price_id: Option<String>,
         stripe_product_id: Option<String>,
         stripe_subscription_id: Option<String>,
@@ -109,7 +129,26 @@ impl Subscription {
         current_period_end: Option<DateTime<Utc>>,
         cancel_at_period_end: Option<bool>,
         canceled_at: Option<DateTime<Utc>>,
-    ) {
+    ) -> Result<()> {
+        if let Some(last_event_at) = self.last_event_at {
+            if event_at < last_event_at {
+                return Err(Error::StaleEvent(format!(
+                    "Event from {} is older than the last applied event from {}",
+                    event_at, last_event_at
+                )));
+            }
+        }
+        let same_subscription = stripe_subscription_id
+            .as_deref()
+            .is_none_or(|id| id == self.stripe_subscription_id);
+        if let Some(status) = status.as_ref() {
+            if self.status.is_terminal() && same_subscription && *status != self.status {
+                return Err(Error::StaleEvent(format!(
+                    "Subscription {} is already {}, not {}",
+                    self.stripe_subscription_id, self.status, status
+                )));
+            }
+        }
         if let Some(stripe_price_id) = stripe_price_id {
             self.stripe_price_id = stripe_price_id;
         }
@@ -131,17 +170,20 @@ impl Subscription {
         if let Some(canceled_at) = canceled_at {
             self.canceled_at = Some(canceled_at);
         }
+        self.last_event_at = Some(event_at);
         self.updated_at = Some(Utc::now());
+        Ok(())
     }
 
     pub fn is_active(&self) -> bool {
-        self.status == SubscriptionStatus::Active || self.status == SubscriptionStatus::Trialing
+        self.status.grants_access()
     }
 
     pub fn is_canceled(&self) -> bool {
         self.status == SubscriptionStatus::Canceled
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn construct(
         id: i32,
         user_id: Uuid,
@@ -156,6 +198,7 @@ impl Subscription {
         canceled_at: Option<DateTime<Utc>>,
         created_at: DateTime<Utc>,
         updated_at: Option<DateTime<Utc>>,
+        last_event_at: Option<DateTime<Utc>>,
     ) -> Self {
         Self {
             id,
@@ -171,6 +214,224 @@ impl Subscription {
             canceled_at,
             created_at,
             updated_at,
+            last_event_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WebhookEvent {
+    id: String,
+    event_type: String,
+    payload: Value,
+    status: WebhookEventStatus,
+    received_at: DateTime<Utc>,
+    processed_at: Option<DateTime<Utc>>,
+    next_attempt_at: DateTime<Utc>,
+    attempts: i32,
+    last_error: Option<String>,
+}
+impl WebhookEvent {
+    pub fn new(id: String, event_type: String, payload: Value) -> Self {
+        let now = Utc::now();
+        Self {
+            id,
+            event_type,
+            payload,
+            status: WebhookEventStatus::Pending,
+            received_at: now,
+            processed_at: None,
+            next_attempt_at: now,
+            attempts: 0,
+            last_error: None,
         }
     }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn event_type(&self) -> &str {
+        &self.event_type
+    }
+
+    pub fn payload(&self) -> &Value {
+        &self.payload
+    }
+
+    pub fn status(&self) -> WebhookEventStatus {
+        self.status
+    }
+
+    pub fn received_at(&self) -> DateTime<Utc> {
+        self.received_at
+    }
+
+    pub fn processed_at(&self) -> Option<DateTime<Utc>> {
+        self.processed_at
+    }
+
+    pub fn next_attempt_at(&self) -> DateTime<Utc> {
+        self.next_attempt_at
+    }
+
+    pub fn attempts(&self) -> i32 {
+        self.attempts
+    }
+
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+
+    pub fn is_processed(&self) -> bool {
+        self.status == WebhookEventStatus::Processed
+    }
+
+    pub fn start_attempt(&mut self) {
+        self.attempts += 1;
+    }
+
+    pub fn mark_processed(&mut self) {
+        self.status = WebhookEventStatus::Processed;
+        self.processed_at = Some(Utc::now());
+        self.last_error = None;
+    }
+
+    /// Keeps the event pending so the worker picks it up again at `retry_at`.
+    pub fn schedule_retry(&mut self, error: String, retry_at: DateTime<Utc>) {
+        self.status = WebhookEventStatus::Pending;
+        self.next_attempt_at = retry_at;
+        self.last_error = Some(error);
+    }
+
+    /// Parks the event in the dead-letter state; it is never retried automatically.
+    pub fn mark_dead(&mut self, error: String) {
+        self.status = WebhookEventStatus::Dead;
+        self.last_error = Some(error);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn construct(
+        id: String,
+        event_type: String,
+        payload: Value,
+        status: WebhookEventStatus,
+        received_at: DateTime<Utc>,
+        processed_at: Option<DateTime<Utc>>,
+        next_attempt_at: DateTime<Utc>,
+        attempts: i32,
+        last_error: Option<String>,
+    ) -> Self {
+        Self {
+            id,
+            event_type,
+            payload,
+            status,
+            received_at,
+            processed_at,
+            next_attempt_at,
+            attempts,
+            last_error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscription(last_event_at: Option<DateTime<Utc>>) -> Subscription {
+        Subscription::new(
+            Uuid::nil(),
+            "cus_test".to_string(),
+            "price_test".to_string(),
+            "prod_test".to_string(),
+            "sub_test".to_string(),
+            SubscriptionStatus::Active,
+            false,
+            None,
+            false,
+            last_event_at,
+        )
+    }
+
+    #[test]
+    fn test_update_rejects_stale_event() {
+        let canceled_at = DateTime::<Utc>::from_timestamp(1_700_000_100, 0).unwrap();
+        let stale_at = DateTime::<Utc>::from_timestamp(1_700_000_000, 0).unwrap();
+        let mut subscription = subscription(None);
+
+        let result = subscription.update(
+            canceled_at,
+            None,
+            None,
+            None,
+            Some(SubscriptionStatus::Canceled),
+            None,
+            None,
+            Some(canceled_at),
+        );
+        assert!(result.is_ok());
+        assert_eq!(subscription.last_event_at(), Some(canceled_at));
+
+        let result = subscription.update(
+            stale_at,
+            None,
+            None,
+            None,
+            Some(SubscriptionStatus::Active),
+            None,
+            None,
+            None,
+        );
+        assert!(matches!(result, Err(Error::StaleEvent(_))));
+        assert!(subscription.is_canceled());
+        assert_eq!(subscription.last_event_at(), Some(canceled_at));
+    }
+
+    #[test]
+    fn test_update_rejects_same_second_event_after_cancel() {
+        let event_at = DateTime::<Utc>::from_timestamp(1_700_000_000, 0).unwrap();
+        let mut subscription = subscription(Some(event_at));
+
+        let result = subscription.update(
+            event_at,
+            None,
+            None,
+            None,
+            Some(SubscriptionStatus::Canceled),
+            None,
+            None,
+            Some(event_at),
+        );
+        assert!(result.is_ok());
+
+        // `customer.subscription.updated` from the same second, delivered late
+        let result = subscription.update(
+            event_at,
+            None,
+            None,
+            Some("sub_test".to_string()),
+            Some(SubscriptionStatus::Active),
+            None,
+            None,
+            None,
+        );
+        assert!(matches!(result, Err(Error::StaleEvent(_))));
+        assert!(subscription.is_canceled());
+
+        // A new subscription of the same customer replaces the canceled one.
+        let result = subscription.update(
+            event_at,
+            None,
+            None,
+            Some("sub_new".to_string()),
+            Some(SubscriptionStatus::Active),
+            None,
+            None,
+            None,
+        );
+        assert!(result.is_ok());
+        assert_eq!(subscription.status(), &SubscriptionStatus::Active);
+    }
 }
@@ -1,13 +1,87 @@
-use crate::domain::subscription::entities::Subscription;
+use crate::domain::pagination::{Page, PageRequest, SortDirection};
+use crate::domain::subscription::entities::{Subscription, WebhookEvent};
+use crate::domain::subscription::value_objects::subscription_status::SubscriptionStatus;
 use crate::prelude::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+/// Criteria for listing subscriptions. Unset fields match every subscription.
+#[derive(Debug, Clone, Default)]
+pub struct SubscriptionFilter {
+    pub status: Option<SubscriptionStatus>,
+    pub price_id: Option<String>,
+    pub product_id: Option<String>,
+    pub cancel_at_period_end: Option<bool>,
+    /// Only subscriptions whose current period ends before this instant.
+    pub period_ends_before: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriptionSortField {
+    #[default]
+    CreatedAt,
+    /// Subscriptions without a period end come last in ascending order and
+    /// first in descending order.
+    CurrentPeriodEnd,
+}
+
+/// Order of a subscription listing, ties are broken by id.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SubscriptionSort {
+    pub field: SubscriptionSortField,
+    pub direction: SortDirection,
+}
+
+/// Position after the last subscription of a page, see `UserCursor`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubscriptionCursor {
+    pub id: i32,
+    pub created_at: DateTime<Utc>,
+    pub current_period_end: Option<DateTime<Utc>>,
+}
+impl From<&Subscription> for SubscriptionCursor {
+    fn from(subscription: &Subscription) -> Self {
+        Self {
+            id: subscription.id(),
+            created_at: subscription.created_at(),
+            current_period_end: subscription.current_period_end(),
+        }
+    }
+}
+
 pub trait SubscriptionRepository: Send + Sync {
     async fn save(&self, subscription: &Subscription) -> Result<Subscription>;
     async fn find(&self, id: i32) -> Result<Subscription>;
     async fn find_by_strip_subscription_id(&self, subscription_id: &str) -> Result<Subscription>;
     async fn find_by_user_id(&self, user_id: &Uuid) -> Result<Subscription>;
+    /// Like `find_by_user_id`, and locks the row until the surrounding
+    /// transaction ends so concurrent writers wait for this one.
+    async fn find_by_user_id_for_update(&self, user_id: &Uuid) -> Result<Subscription>;
     async fn find_by_customer_id(&self, customer_id: &str) -> Result<Subscription>;
+    /// One page of the subscriptions matching `filter`.
+    async fn find_page(
+        &self,
+        filter: &SubscriptionFilter,
+        sort: SubscriptionSort,
+        page: &PageRequest<SubscriptionCursor>,
+    ) -> Result<Page<Subscription, SubscriptionCursor>>;
+    async fn count(&self, filter: &SubscriptionFilter) -> Result<i64>;
     async fn update(&self, subscription: &Subscription) -> Result<Subscription>;
     async fn delete(&self, id: i32) -> Result<()>;
 }
+
+pub trait WebhookEventRepository: Send + Sync {
+    async fn save(&self, event: &WebhookEvent) -> Result<WebhookEvent>;
+    async fn find(&self, id: &str) -> Result<WebhookEvent>;
+    async fn update(&self, event: &WebhookEvent) -> Result<WebhookEvent>;
+    /// Returns up to `limit` pending events due before `now`, pushing their
+    /// `next_attempt_at` to `lease_until` so concurrent workers skip them.
+    async fn claim_due(
+        &self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<WebhookEvent>>;
+}
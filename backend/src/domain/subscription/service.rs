@@ -1,5 +1,7 @@
 use crate::prelude::*;
 
 pub trait SignatureVerificationService: Send + Sync {
-    fn verify(&self, body: &str, signature: &str) -> Result<()>;
+    /// Checks `signature` against the raw request body, byte for byte as
+    /// received.
+    fn verify(&self, body: &[u8], signature: &str) -> Result<()>;
 }
@@ -1 +1,2 @@
 pub mod subscription_status;
+pub mod webhook_event_status;
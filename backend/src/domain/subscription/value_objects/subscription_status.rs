@@ -3,14 +3,40 @@ use serde::Serialize;
 use std::fmt::Display;
 use std::str::FromStr;
 
+/// Mirrors the Stripe subscription lifecycle. Only `Active` and `Trialing`
+/// grant access to paid features; see [`SubscriptionStatus::grants_access`].
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SubscriptionStatus {
+    /// Paid and in good standing. Grants access.
     Active,
+    /// In a free trial period. Grants access.
     Trialing,
+    /// Latest invoice failed and Stripe is retrying it. No access until paid.
     PastDue,
+    /// First payment still requires action (e.g. 3DS). No access yet.
+    Incomplete,
+    /// First payment was never completed; terminal. No access.
+    IncompleteExpired,
+    /// Retries were exhausted without payment. No access.
+    Unpaid,
+    /// Trial ended without a payment method. No access until resumed.
+    Paused,
+    /// Ended by the customer or by Stripe; terminal. No access.
     Canceled,
+    /// Status not recognized by this version. No access.
     Unknown,
 }
+impl SubscriptionStatus {
+    pub fn grants_access(&self) -> bool {
+        matches!(self, Self::Active | Self::Trialing)
+    }
+
+    /// Stripe never moves a subscription out of these statuses; the customer
+    /// has to start a new subscription.
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Self::Canceled | Self::IncompleteExpired)
+    }
+}
 impl FromStr for SubscriptionStatus {
     type Err = Error;
     fn from_str(s: &str) -> Result<Self> {
@@ -19,6 +45,10 @@ impl FromStr for SubscriptionStatus {
             "trialing" => Ok(Self::Trialing),
             "canceled" => Ok(Self::Canceled),
             "past_due" => Ok(Self::PastDue),
+            "incomplete" => Ok(Self::Incomplete),
+            "incomplete_expired" => Ok(Self::IncompleteExpired),
+            "unpaid" => Ok(Self::Unpaid),
+            "paused" => Ok(Self::Paused),
             _ => Ok(Self::Unknown),
         }
     }
@@ -31,6 +61,10 @@ impl Display for SubscriptionStatus {
             Self::Trialing => write!(f, "trialing"),
             Self::Canceled => write!(f, "canceled"),
             Self::PastDue => write!(f, "past_due"),
+            Self::Incomplete => write!(f, "incomplete"),
+            Self::IncompleteExpired => write!(f, "incomplete_expired"),
+            Self::Unpaid => write!(f, "unpaid"),
+            Self::Paused => write!(f, "paused"),
             Self::Unknown => write!(f, "unknown"),
         }
     }
@@ -54,3 +88,41 @@ impl<'de> serde::Deserialize<'de> for SubscriptionStatus {
         SubscriptionStatus::from_str(&s).map_err(serde::de::Error::custom)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_round_trip() {
+        let statuses = [
+            SubscriptionStatus::Active,
+            SubscriptionStatus::Trialing,
+            SubscriptionStatus::PastDue,
+            SubscriptionStatus::Incomplete,
+            SubscriptionStatus::IncompleteExpired,
+            SubscriptionStatus::Unpaid,
+            SubscriptionStatus::Paused,
+            SubscriptionStatus::Canceled,
+        ];
+        for status in statuses {
+            let parsed = SubscriptionStatus::from_str(&status.to_string()).unwrap();
+            assert_eq!(parsed, status);
+        }
+        assert_eq!(
+            SubscriptionStatus::from_str("something_new").unwrap(),
+            SubscriptionStatus::Unknown
+        );
+    }
+
+    #[test]
+    fn test_grants_access() {
+        assert!(SubscriptionStatus::Active.grants_access());
+        assert!(SubscriptionStatus::Trialing.grants_access());
+        assert!(!SubscriptionStatus::PastDue.grants_access());
+        assert!(!SubscriptionStatus::Incomplete.grants_access());
+        assert!(!SubscriptionStatus::Unpaid.grants_access());
+        assert!(!SubscriptionStatus::Paused.grants_access());
+        assert!(!SubscriptionStatus::Canceled.grants_access());
+    }
+}
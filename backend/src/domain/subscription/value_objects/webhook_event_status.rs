@@ -0,0 +1,44 @@
+use crate::prelude::*;
+use serde::Serialize;
+use std::fmt::Display;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookEventStatus {
+    Pending,
+    Processed,
+    Dead,
+}
+impl FromStr for WebhookEventStatus {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "pending" => Ok(Self::Pending),
+            "processed" => Ok(Self::Processed),
+            "dead" => Ok(Self::Dead),
+            _ => Err(Error::Parsing(format!(
+                "Unknown webhook event status `{}`",
+                s
+            ))),
+        }
+    }
+}
+
+impl Display for WebhookEventStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pending => write!(f, "pending"),
+            Self::Processed => write!(f, "processed"),
+            Self::Dead => write!(f, "dead"),
+        }
+    }
+}
+
+impl Serialize for WebhookEventStatus {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
@@ -0,0 +1,22 @@
+use crate::domain::subscription::repository::SubscriptionRepository;
+use crate::domain::user::repositories::UserRepository;
+use crate::prelude::*;
+
+/// Opens transactions in which user and subscription repository calls read
+/// and change the same state, and are committed or discarded together.
+pub trait UnitOfWork: Send + Sync {
+    type Transaction: Transaction;
+
+    async fn begin(&self) -> Result<Self::Transaction>;
+}
+
+/// Repositories bound to one open transaction. Dropping it without calling
+/// `commit` rolls every change back and releases the row locks it holds.
+pub trait Transaction: Send {
+    type Users: UserRepository;
+    type Subscriptions: SubscriptionRepository;
+
+    fn users(&self) -> &Self::Users;
+    fn subscriptions(&self) -> &Self::Subscriptions;
+    async fn commit(self) -> Result<()>;
+}
@@ -1,5 +1,6 @@
 use crate::domain::user::value_objects::role::Role;
 use crate::domain::user::value_objects::user_status::UserStatus;
+use crate::prelude::*;
 use chrono::{DateTime, Utc};
 use serde::Deserialize;
 use uuid::Uuid;
@@ -8,22 +9,23 @@ use uuid::Uuid;
 pub struct User {
     id: Uuid,
     email: String,
-    firebase_id: String,
     stripe_customer_id: Option<String>,
     status: UserStatus,
+    /// Why the status was last changed, shown to the user when it blocks access.
+    status_reason: Option<String>,
     role: Role,
     created_at: DateTime<Utc>,
     updated_at: Option<DateTime<Utc>>,
     profile: Profile,
 }
 impl User {
-    pub fn new(email: String, firebase_id: String, stripe_customer_id: Option<String>) -> Self {
+    pub fn new(email: String, stripe_customer_id: Option<String>) -> Self {
         Self {
             id: Uuid::nil(), // Real value will set by the repository database
             email,
-            firebase_id,
             stripe_customer_id,
             status: UserStatus::Active,
+            status_reason: None,
             role: Role::User,
             created_at: Utc::now(),
             updated_at: None,
@@ -39,10 +41,6 @@ impl User {
         &self.email
     }
 
-    pub fn firebase_id(&self) -> &str {
-        &self.firebase_id
-    }
-
     pub fn stripe_customer_id(&self) -> Option<&str> {
         self.stripe_customer_id.as_deref()
     }
@@ -51,6 +49,10 @@ impl User {
         self.status
     }
 
+    pub fn status_reason(&self) -> Option<&str> {
+        self.status_reason.as_deref()
+    }
+
     pub fn role(&self) -> Role {
         self.role
     }
@@ -83,6 +85,32 @@ impl User {
         }
     }
 
+    /// Changes the status and returns the audit record of the change.
+    pub fn change_status(
+        &mut self,
+        status: UserStatus,
+        reason: Option<String>,
+        actor_id: Option<Uuid>,
+    ) -> UserStatusChange {
+        let change = UserStatusChange::new(self.id, actor_id, self.status, status, reason.clone());
+        self.status = status;
+        self.status_reason = reason;
+        self.updated_at = Some(Utc::now());
+        change
+    }
+
+    /// Rejects users whose status does not allow them in. Pending users are
+    /// let through only when `onboarding` is set.
+    pub fn ensure_access(&self, onboarding: bool) -> Result<()> {
+        if self.status.allows_access(onboarding) {
+            return Ok(());
+        }
+        Err(Error::AccountDisabled(
+            self.status,
+            self.status_reason.clone(),
+        ))
+    }
+
     pub fn update_profile(
         &mut self,
         first_name: Option<String>,
@@ -93,12 +121,13 @@ impl User {
         self.profile.update(first_name, last_name, phone, photo_url);
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn construct(
         id: Uuid,
         email: String,
-        firebase_id: String,
         stripe_customer_id: Option<String>,
         status: UserStatus,
+        status_reason: Option<String>,
         role: Role,
         created_at: DateTime<Utc>,
         updated_at: Option<DateTime<Utc>>,
@@ -107,9 +136,9 @@ impl User {
         Self {
             id,
             email,
-            firebase_id,
             stripe_customer_id,
             status,
+            status_reason,
             role,
             created_at,
             updated_at,
@@ -223,20 +252,294 @@ impl Default for Profile {
     }
 }
 
+/// Audit record of a status change. `actor_id` is the admin who made it,
+/// `None` when the system did.
+#[derive(Debug, Clone)]
+pub struct UserStatusChange {
+    id: i32,
+    user_id: Uuid,
+    actor_id: Option<Uuid>,
+    old_status: UserStatus,
+    new_status: UserStatus,
+    reason: Option<String>,
+    created_at: DateTime<Utc>,
+}
+impl UserStatusChange {
+    pub fn new(
+        user_id: Uuid,
+        actor_id: Option<Uuid>,
+        old_status: UserStatus,
+        new_status: UserStatus,
+        reason: Option<String>,
+    ) -> Self {
+        Self {
+            id: 0, // Real value will set by the repository database
+            user_id,
+            actor_id,
+            old_status,
+            new_status,
+            reason,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn user_id(&self) -> Uuid {
+        self.user_id
+    }
+
+    pub fn actor_id(&self) -> Option<Uuid> {
+        self.actor_id
+    }
+
+    pub fn old_status(&self) -> UserStatus {
+        self.old_status
+    }
+
+    pub fn new_status(&self) -> UserStatus {
+        self.new_status
+    }
+
+    pub fn reason(&self) -> Option<&str> {
+        self.reason.as_deref()
+    }
+
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    pub fn construct(
+        id: i32,
+        user_id: Uuid,
+        actor_id: Option<Uuid>,
+        old_status: UserStatus,
+        new_status: UserStatus,
+        reason: Option<String>,
+        created_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id,
+            user_id,
+            actor_id,
+            old_status,
+            new_status,
+            reason,
+            created_at,
+        }
+    }
+}
+
+/// A login method of a user: the subject of a token issued by `provider`.
+/// A user may have several, e.g. Google and email/password accounts sharing
+/// the same verified email.
+#[derive(Debug, Clone)]
+pub struct UserIdentity {
+    id: i32,
+    user_id: Uuid,
+    provider: String,
+    subject: String,
+    email: String,
+    email_verified: bool,
+    created_at: DateTime<Utc>,
+    updated_at: Option<DateTime<Utc>>,
+}
+impl UserIdentity {
+    pub fn new(user_id: Uuid, auth: &AuthProviderData) -> Self {
+        Self {
+            id: 0, // Real value will set by the repository database
+            user_id,
+            provider: auth.provider.clone(),
+            subject: auth.id.clone(),
+            email: auth.email.clone(),
+            email_verified: auth.email_verified,
+            created_at: Utc::now(),
+            updated_at: None,
+        }
+    }
+
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn user_id(&self) -> Uuid {
+        self.user_id
+    }
+
+    pub fn provider(&self) -> &str {
+        &self.provider
+    }
+
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+
+    pub fn email(&self) -> &str {
+        &self.email
+    }
+
+    pub fn email_verified(&self) -> bool {
+        self.email_verified
+    }
+
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    pub fn updated_at(&self) -> Option<DateTime<Utc>> {
+        self.updated_at
+    }
+
+    /// Whether this identity proves ownership of `email`.
+    pub fn verifies(&self, email: &str) -> bool {
+        self.email_verified && self.email.eq_ignore_ascii_case(email)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn construct(
+        id: i32,
+        user_id: Uuid,
+        provider: String,
+        subject: String,
+        email: String,
+        email_verified: bool,
+        created_at: DateTime<Utc>,
+        updated_at: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self {
+            id,
+            user_id,
+            provider,
+            subject,
+            email,
+            email_verified,
+            created_at,
+            updated_at,
+        }
+    }
+}
+
+/// A new identity may be linked to the account that owns its email only if
+/// both sides have verified that email. Otherwise anyone able to create an
+/// unverified account with someone else's address could take it over.
+pub fn can_link_identity(existing: &[UserIdentity], auth: &AuthProviderData) -> bool {
+    auth.email_verified
+        && existing
+            .iter()
+            .any(|identity| identity.verifies(&auth.email))
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct AuthProviderData {
+    /// Issuer of the token, e.g. `firebase` or the OIDC issuer url.
+    pub provider: String,
+    /// Subject of the token at `provider`.
     pub id: String,
     pub email: String,
+    pub email_verified: bool,
     pub name: Option<String>,
     pub photo_url: Option<String>,
+    /// Expiration of the token these claims were read from.
+    pub expires_at: DateTime<Utc>,
 }
 impl AuthProviderData {
-    pub fn new(id: String, email: String, name: Option<String>, photo_url: Option<String>) -> Self {
+    pub fn new(
+        provider: String,
+        id: String,
+        email: String,
+        email_verified: bool,
+        name: Option<String>,
+        photo_url: Option<String>,
+        expires_at: DateTime<Utc>,
+    ) -> Self {
         Self {
+            provider,
             id,
             email,
+            email_verified,
             name,
             photo_url,
+            expires_at,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auth(email: &str, email_verified: bool) -> AuthProviderData {
+        AuthProviderData::new(
+            "firebase".to_string(),
+            "new-uid".to_string(),
+            email.to_string(),
+            email_verified,
+            None,
+            None,
+            Utc::now(),
+        )
+    }
+
+    fn identity(email: &str, email_verified: bool) -> UserIdentity {
+        UserIdentity::construct(
+            1,
+            Uuid::new_v4(),
+            "firebase".to_string(),
+            "old-uid".to_string(),
+            email.to_string(),
+            email_verified,
+            Utc::now(),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_change_status() {
+        let mut user = User::new("john@example.com".to_string(), None);
+        let admin_id = Uuid::new_v4();
+        assert!(user.ensure_access(false).is_ok());
+
+        let change = user.change_status(
+            UserStatus::Suspended,
+            Some("Chargeback".to_string()),
+            Some(admin_id),
+        );
+        assert_eq!(change.old_status(), UserStatus::Active);
+        assert_eq!(change.new_status(), UserStatus::Suspended);
+        assert_eq!(change.actor_id(), Some(admin_id));
+        assert!(matches!(
+            user.ensure_access(true),
+            Err(Error::AccountDisabled(UserStatus::Suspended, Some(reason))) if reason == "Chargeback"
+        ));
+
+        user.change_status(UserStatus::Pending, None, None);
+        assert!(user.ensure_access(false).is_err());
+        assert!(user.ensure_access(true).is_ok());
+    }
+
+    #[test]
+    fn test_can_link_identity() {
+        let verified = [identity("John@Example.com", true)];
+        let unverified = [identity("john@example.com", false)];
+
+        assert!(can_link_identity(
+            &verified,
+            &auth("john@example.com", true)
+        ));
+        assert!(!can_link_identity(
+            &verified,
+            &auth("john@example.com", false)
+        ));
+        assert!(!can_link_identity(
+            &unverified,
+            &auth("john@example.com", true)
+        ));
+        assert!(!can_link_identity(
+            &verified,
+            &auth("jane@example.com", true)
+        ));
+        assert!(!can_link_identity(&[], &auth("john@example.com", true)));
+    }
+}
@@ -1,13 +1,79 @@
-use crate::domain::user::entities::User;
+use crate::domain::pagination::{Page, PageRequest, SortDirection};
+use crate::domain::subscription::value_objects::subscription_status::SubscriptionStatus;
+use crate::domain::user::entities::{User, UserIdentity, UserStatusChange};
+use crate::domain::user::value_objects::role::Role;
+use crate::domain::user::value_objects::user_status::UserStatus;
 use crate::prelude::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+/// Criteria for listing users. Unset fields match every user.
+#[derive(Debug, Clone, Default)]
+pub struct UserFilter {
+    /// Case-insensitive substring of the email.
+    pub email: Option<String>,
+    pub status: Option<UserStatus>,
+    pub role: Option<Role>,
+    pub subscription_status: Option<SubscriptionStatus>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UserSortField {
+    #[default]
+    CreatedAt,
+    Email,
+}
+
+/// Order of a user listing, ties are broken by id.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UserSort {
+    pub field: UserSortField,
+    pub direction: SortDirection,
+}
+
+/// Position after the last user of a page. It carries every sortable key so
+/// it stays valid whichever `UserSort` the next page uses, and does not
+/// depend on that user still existing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserCursor {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub email: String,
+}
+impl From<&User> for UserCursor {
+    fn from(user: &User) -> Self {
+        Self {
+            id: user.id(),
+            created_at: user.created_at(),
+            email: user.email().to_string(),
+        }
+    }
+}
+
 pub trait UserRepository: Send + Sync {
-    async fn save(&self, user: &User) -> Result<User>;
+    /// Stores a new user together with the identity it signed up with.
+    async fn save(&self, user: &User, identity: &UserIdentity) -> Result<User>;
     async fn find(&self, user_id: &Uuid) -> Result<Option<User>>;
     async fn find_by_email(&self, email: &str) -> Result<Option<User>>;
-    async fn find_by_firebase_id(&self, firebase_id: &str) -> Result<Option<User>>;
+    async fn find_by_identity(&self, provider: &str, subject: &str) -> Result<Option<User>>;
     async fn find_by_strip_customer_id(&self, strip_customer_id: &str) -> Result<Option<User>>;
+    /// One page of the users matching `filter`.
+    async fn find_page(
+        &self,
+        filter: &UserFilter,
+        sort: UserSort,
+        page: &PageRequest<UserCursor>,
+    ) -> Result<Page<User, UserCursor>>;
+    async fn count(&self, filter: &UserFilter) -> Result<i64>;
+    async fn find_identities(&self, user_id: &Uuid) -> Result<Vec<UserIdentity>>;
+    /// Inserts the identity, or refreshes its email claims if it exists.
+    async fn save_identity(&self, identity: &UserIdentity) -> Result<UserIdentity>;
     async fn update(&self, user: &User) -> Result<User>;
+    /// Stores the new status of the user together with its audit record.
+    async fn change_status(&self, user: &User, change: &UserStatusChange) -> Result<User>;
+    /// Status changes of the user, newest first.
+    async fn find_status_changes(&self, user_id: &Uuid) -> Result<Vec<UserStatusChange>>;
     async fn delete(&self, user_id: &Uuid) -> Result<()>;
 }
@@ -1,2 +1,3 @@
+pub mod permission;
 pub mod role;
 pub mod user_status;
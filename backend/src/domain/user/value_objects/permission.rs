@@ -0,0 +1,27 @@
+use serde::Serialize;
+
+/// An action guarded by the role of the acting user. See `Role::permissions`
+/// for which role may do what.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    /// Read the own account and profile.
+    ReadOwnAccount,
+    /// Update or delete the own account.
+    ManageOwnAccount,
+    /// Start checkouts, open the billing portal and read the own subscription.
+    ManageOwnBilling,
+    /// List and inspect any user.
+    ReadUsers,
+    /// Change the status of any user or resync it from the payment provider.
+    ManageUsers,
+    /// Grant or revoke roles.
+    ManageRoles,
+}
+
+impl Permission {
+    /// Permissions needed to finish onboarding, granted to pending users too.
+    pub fn allowed_while_pending(&self) -> bool {
+        matches!(self, Self::ReadOwnAccount | Self::ManageOwnAccount)
+    }
+}
@@ -1,6 +1,29 @@
+use crate::domain::user::value_objects::permission::Permission;
 use crate::prelude::*;
 use serde::Serialize;
 
+const GUEST_PERMISSIONS: &[Permission] = &[Permission::ReadOwnAccount];
+const USER_PERMISSIONS: &[Permission] = &[
+    Permission::ReadOwnAccount,
+    Permission::ManageOwnAccount,
+    Permission::ManageOwnBilling,
+];
+const ADMIN_PERMISSIONS: &[Permission] = &[
+    Permission::ReadOwnAccount,
+    Permission::ManageOwnAccount,
+    Permission::ManageOwnBilling,
+    Permission::ReadUsers,
+    Permission::ManageUsers,
+];
+const SUPER_PERMISSIONS: &[Permission] = &[
+    Permission::ReadOwnAccount,
+    Permission::ManageOwnAccount,
+    Permission::ManageOwnBilling,
+    Permission::ReadUsers,
+    Permission::ManageUsers,
+    Permission::ManageRoles,
+];
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Role {
     Admin,
@@ -18,6 +41,20 @@ impl Role {
             Self::Super => "super",
         }
     }
+
+    /// The permission matrix: everything a user with this role may do.
+    pub fn permissions(&self) -> &'static [Permission] {
+        match self {
+            Self::Guest => GUEST_PERMISSIONS,
+            Self::User => USER_PERMISSIONS,
+            Self::Admin => ADMIN_PERMISSIONS,
+            Self::Super => SUPER_PERMISSIONS,
+        }
+    }
+
+    pub fn can(&self, permission: Permission) -> bool {
+        self.permissions().contains(&permission)
+    }
 }
 
 impl std::fmt::Display for Role {
@@ -57,3 +94,29 @@ impl<'de> serde::Deserialize<'de> for Role {
         Role::try_from(s).map_err(serde::de::Error::custom)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_permissions() {
+        assert!(Role::Guest.can(Permission::ReadOwnAccount));
+        assert!(!Role::Guest.can(Permission::ManageOwnBilling));
+        assert!(Role::User.can(Permission::ManageOwnBilling));
+        assert!(!Role::User.can(Permission::ReadUsers));
+        assert!(Role::Admin.can(Permission::ManageUsers));
+        assert!(!Role::Admin.can(Permission::ManageRoles));
+
+        // Higher roles keep every permission of the lower ones.
+        for (lower, higher) in [
+            (Role::Guest, Role::User),
+            (Role::User, Role::Admin),
+            (Role::Admin, Role::Super),
+        ] {
+            for permission in lower.permissions() {
+                assert!(higher.can(*permission), "{} lacks {:?}", higher, permission);
+            }
+        }
+    }
+}
@@ -20,6 +20,16 @@ impl UserStatus {
             Self::Pending => "pending",
         }
     }
+
+    /// Only active users get full access. Pending users may still finish
+    /// onboarding, everyone else is locked out.
+    pub fn allows_access(&self, onboarding: bool) -> bool {
+        match self {
+            Self::Active => true,
+            Self::Pending => onboarding,
+            Self::Inactive | Self::Banned | Self::Suspended => false,
+        }
+    }
 }
 
 impl std::fmt::Display for UserStatus {
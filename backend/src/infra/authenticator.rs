@@ -0,0 +1,39 @@
+use crate::domain::user::entities::AuthProviderData;
+use crate::domain::user::services::Authenticator;
+use crate::infra::config::AuthConfig;
+use crate::infra::firebase::service::FirebaseAuthenticatorService;
+use crate::infra::oidc::service::OidcAuthenticatorService;
+use crate::prelude::*;
+use std::sync::Arc;
+
+/// The identity provider selected in the configuration.
+#[derive(Clone)]
+pub enum AuthProvider {
+    Firebase(FirebaseAuthenticatorService),
+    Oidc(OidcAuthenticatorService),
+}
+impl AuthProvider {
+    pub fn new(config: &AuthConfig, http: Arc<reqwest::Client>) -> Self {
+        match config {
+            AuthConfig::Firebase { project_id } => {
+                Self::Firebase(FirebaseAuthenticatorService::new(project_id, http))
+            }
+            AuthConfig::Oidc {
+                issuer,
+                audience,
+                jwks_url,
+            } => Self::Oidc(OidcAuthenticatorService::new(
+                issuer, audience, jwks_url, http,
+            )),
+        }
+    }
+}
+
+impl Authenticator for AuthProvider {
+    async fn authenticate(&self, token: &str) -> Result<AuthProviderData> {
+        match self {
+            Self::Firebase(service) => service.authenticate(token).await,
+            Self::Oidc(service) => service.authenticate(token).await,
+        }
+    }
+}
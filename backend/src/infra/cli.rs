@@ -1,4 +1,5 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[clap(version, about, long_about = None)]
@@ -7,4 +8,42 @@ pub struct Args {
     pub config_path: String,
     #[clap(short, long)]
     pub secret_path: String,
+    /// Pull the full product and price catalog from Stripe before serving.
+    #[clap(long)]
+    pub sync_catalog: bool,
+    /// Runs a maintenance command instead of serving.
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Stripe webhook tooling.
+    Webhook {
+        #[clap(subcommand)]
+        command: WebhookCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum WebhookCommand {
+    /// Signs event fixtures with the configured `stripe-webhook-secret` and
+    /// delivers them, as Stripe would.
+    Replay(ReplayArgs),
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ReplayArgs {
+    /// Event files, or directories whose `*.json` files are replayed in name
+    /// order.
+    #[clap(default_value = "fixtures/webhooks")]
+    pub paths: Vec<PathBuf>,
+    /// Webhook endpoint to post to, by default the one of the configured
+    /// host and port.
+    #[clap(long, conflicts_with = "direct")]
+    pub url: Option<String>,
+    /// Stores and processes the events in this process, without a running
+    /// server.
+    #[clap(long)]
+    pub direct: bool,
 }
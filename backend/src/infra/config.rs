@@ -1,3 +1,5 @@
+use crate::domain::payment::value_objects::checkout_mode::CheckoutMode;
+use crate::domain::payment::value_objects::ui_mode::UiMode;
 use crate::prelude::*;
 use serde::Deserialize;
 use std::path::{Path, PathBuf};
@@ -6,8 +8,8 @@ use std::path::{Path, PathBuf};
 pub struct Secrets {
     stripe_secret_key: String,
     postgres_connection_string: String,
-    firebase_api_key: String,
-    stripe_webhook_secret: Option<String>,
+    /// One per line of `stripe-webhook-secret`, the current one first.
+    stripe_webhook_secrets: Vec<String>,
 }
 impl Secrets {
     pub fn new<P: AsRef<Path>>(secrets_path: P) -> Self {
@@ -17,14 +19,21 @@ impl Secrets {
             Self::read_secret_file(&base_path.join("stripe-secret-key")).unwrap();
         let postgres_connection_string =
             Self::read_secret_file(&base_path.join("postgres-connection-string")).unwrap();
-        let firebase_api_key = Self::read_secret_file(&base_path.join("firebase-api-key")).unwrap();
-        let stripe_webhook_secret =
-            Self::read_secret_file(&base_path.join("stripe-webhook-secret")).ok();
+        let stripe_webhook_secrets =
+            Self::read_secret_file(&base_path.join("stripe-webhook-secret"))
+                .map(|secrets| {
+                    secrets
+                        .lines()
+                        .map(str::trim)
+                        .filter(|secret| !secret.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default();
         Self {
             stripe_secret_key,
             postgres_connection_string,
-            firebase_api_key,
-            stripe_webhook_secret,
+            stripe_webhook_secrets,
         }
     }
     pub fn read_secret_file(path: &Path) -> Result<String> {
@@ -40,11 +49,14 @@ impl Secrets {
     pub fn postgres_connection_string(&self) -> &str {
         &self.postgres_connection_string
     }
-    pub fn firebase_api_key(&self) -> &str {
-        &self.firebase_api_key
-    }
+    /// The secret new events are signed with.
     pub fn stripe_webhook_secret(&self) -> &str {
-        self.stripe_webhook_secret.as_ref().unwrap()
+        self.stripe_webhook_secrets.first().unwrap()
+    }
+    /// Every secret a webhook signature is accepted with. Keep the previous
+    /// secret on the second line while rolling it in the Stripe dashboard.
+    pub fn stripe_webhook_secrets(&self) -> &[String] {
+        &self.stripe_webhook_secrets
     }
 }
 
@@ -55,6 +67,13 @@ pub struct AppConfig {
     pub log_level: String,
     pub cors_origin: String,
     pub environment: String,
+    #[serde(default)]
+    pub webhooks: WebhookConfig,
+    #[serde(default)]
+    pub checkout: CheckoutConfig,
+    pub auth: AuthConfig,
+    #[serde(default)]
+    pub user_cache: UserCacheConfig,
 }
 impl AppConfig {
     pub fn new(config_str: &str) -> Self {
@@ -62,6 +81,98 @@ impl AppConfig {
     }
 }
 
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    pub poll_interval_secs: u64,
+    pub batch_size: i64,
+    pub lease_secs: u64,
+    pub max_attempts: i32,
+    pub retry_base_delay_secs: u64,
+    pub retry_max_delay_secs: u64,
+    /// How old a `Stripe-Signature` timestamp may be before the event is
+    /// rejected as a possible replay.
+    pub signature_tolerance_secs: u64,
+    /// Larger webhook bodies are refused before being read in full.
+    pub max_payload_bytes: usize,
+}
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval_secs: 5,
+            batch_size: 20,
+            lease_secs: 300,
+            max_attempts: 8,
+            retry_base_delay_secs: 30,
+            retry_max_delay_secs: 3600,
+            signature_tolerance_secs: 300,
+            max_payload_bytes: 256 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CheckoutConfig {
+    /// Used when the client does not ask for a specific UI mode.
+    pub ui_mode: UiMode,
+    /// Used when the client does not ask for a specific mode.
+    pub mode: CheckoutMode,
+    pub allowed_ui_modes: Vec<UiMode>,
+    pub allowed_modes: Vec<CheckoutMode>,
+    /// Trial granted on subscription checkouts. Zero disables the trial.
+    pub trial_period_days: u32,
+    /// Products that may be purchased through checkout. Empty allows every
+    /// active product in the catalog.
+    pub allowed_products: Vec<String>,
+}
+impl Default for CheckoutConfig {
+    fn default() -> Self {
+        Self {
+            ui_mode: UiMode::Hosted,
+            mode: CheckoutMode::Subscription,
+            allowed_ui_modes: vec![UiMode::Hosted],
+            allowed_modes: vec![CheckoutMode::Subscription],
+            trial_period_days: 1,
+            allowed_products: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct UserCacheConfig {
+    /// How long a token keeps resolving to a cached user. Other instances do
+    /// not see invalidations, so keep it short. Zero disables the cache.
+    pub ttl_secs: u64,
+    pub max_entries: usize,
+}
+impl Default for UserCacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: 60,
+            max_entries: 10_000,
+        }
+    }
+}
+
+/// Identity provider whose ID tokens are accepted.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "provider", rename_all = "lowercase")]
+pub enum AuthConfig {
+    Firebase {
+        /// Expected `aud` of Firebase ID tokens.
+        project_id: String,
+    },
+    Oidc {
+        /// Expected `iss`, e.g. `https://tenant.eu.auth0.com/`.
+        issuer: String,
+        /// Expected `aud`, usually the client id or API identifier.
+        audience: String,
+        jwks_url: String,
+    },
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
     app: AppConfig,
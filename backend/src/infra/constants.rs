@@ -1,3 +0,0 @@
-pub static UI_MODE: &str = "hosted";
-pub static CHECKOUT_MODE: &str = "subscription";
-pub static TRIAL_PERIOD_DAYS: i32 = 1;
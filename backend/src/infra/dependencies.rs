@@ -1,24 +1,52 @@
+use crate::application::catalog::service::CatalogService;
 use crate::application::payment::service::PaymentService;
-use crate::application::subscription::service::{SignatureService, SubscriptionService};
+use crate::application::payment::use_cases::CheckoutSettings;
+use crate::application::subscription::dispatcher::WebhookDispatcher;
+use crate::application::subscription::receiver::WebhookReceiver;
+use crate::application::subscription::service::{
+    SignatureService, SubscriptionService, WebhookEventService,
+};
+use crate::application::subscription::worker::{RetryPolicy, WebhookWorker, WorkerSettings};
+use crate::application::user::cache::UserCache;
 use crate::application::user::service::{AuthenticationService, UserService};
+use crate::infra::authenticator::AuthProvider;
 use crate::infra::config::Config;
-use crate::infra::firebase::service::FirebaseAuthenticatorService;
 use crate::infra::postgres::connection::establish_connection;
 use crate::infra::postgres::migrations::run_migrations;
+use crate::infra::postgres::repositories::catalog::PostgresCatalogRepository;
 use crate::infra::postgres::repositories::subscription::PostgresSubscriptionRepository;
 use crate::infra::postgres::repositories::user::PostgresUserRepository;
+use crate::infra::postgres::repositories::webhook_event::PostgresWebhookEventRepository;
+use crate::infra::postgres::unit_of_work::PostgresUnitOfWork;
 use crate::infra::stripe::payment::StripePaymentClient;
 use crate::infra::stripe::service::StripeSignatureVerificationService;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// The event store and signature check behind the webhook endpoint.
+pub type WebhookEvents = PostgresWebhookEventRepository;
+pub type WebhookSignatures = StripeSignatureVerificationService;
 
 #[derive(Clone)]
 pub struct AppState {
     pub config: Config,
     pub user_service: UserService<PostgresUserRepository>,
-    pub auth_service: AuthenticationService<FirebaseAuthenticatorService>,
+    pub auth_service: AuthenticationService<AuthProvider>,
     pub payment_service: PaymentService<StripePaymentClient>,
     pub subscription_service: SubscriptionService<PostgresSubscriptionRepository>,
-    pub signature_service: SignatureService<StripeSignatureVerificationService>,
+    pub unit_of_work: Arc<PostgresUnitOfWork>,
+    pub webhook_receiver: WebhookReceiver<WebhookEvents, WebhookSignatures>,
+    pub webhook_event_service: WebhookEventService<PostgresWebhookEventRepository>,
+    pub webhook_notifier: Arc<Notify>,
+    pub webhook_dispatcher: WebhookDispatcher<
+        PostgresUnitOfWork,
+        PostgresUserRepository,
+        StripePaymentClient,
+        PostgresCatalogRepository,
+    >,
+    pub catalog_service: CatalogService<PostgresCatalogRepository>,
+    pub checkout_settings: CheckoutSettings,
 }
 
 impl AppState {
@@ -30,10 +58,7 @@ impl AppState {
         let http_client = Arc::new(reqwest::Client::new());
 
         let pg_user_repository = Arc::new(PostgresUserRepository::new(db_pool.clone()));
-        let auth_client = Arc::new(FirebaseAuthenticatorService::new(
-            config.secrets().firebase_api_key(),
-            http_client.clone(),
-        ));
+        let auth_client = Arc::new(AuthProvider::new(&config.app().auth, http_client.clone()));
         let payment_client = Arc::new(StripePaymentClient::new(
             config.secrets().stripe_secret_key(),
             http_client.clone(),
@@ -41,22 +66,94 @@ impl AppState {
         ));
         let subscription_repository =
             Arc::new(PostgresSubscriptionRepository::new(db_pool.clone()));
+        let webhook_event_repository =
+            Arc::new(PostgresWebhookEventRepository::new(db_pool.clone()));
+        let catalog_repository = Arc::new(PostgresCatalogRepository::new(db_pool.clone()));
+        let unit_of_work = Arc::new(PostgresUnitOfWork::new(db_pool.clone()));
         let stripe_signature_service = Arc::new(StripeSignatureVerificationService::new(
-            config.secrets().stripe_webhook_secret(),
+            config.secrets().stripe_webhook_secrets(),
+            Duration::from_secs(config.app().webhooks.signature_tolerance_secs),
         ));
 
-        let user_service = UserService::new(pg_user_repository);
+        let user_cache_config = &config.app().user_cache;
+        let user_cache = UserCache::new(
+            Duration::from_secs(user_cache_config.ttl_secs),
+            user_cache_config.max_entries,
+        );
+        let user_service = UserService::new(pg_user_repository, user_cache);
         let auth_service = AuthenticationService::new(auth_client);
         let payment_service = PaymentService::new(payment_client);
         let subscription_service = SubscriptionService::new(subscription_repository);
         let signature_service = SignatureService::new(stripe_signature_service);
+        let webhook_event_service = WebhookEventService::new(webhook_event_repository);
+        let catalog_service = CatalogService::new(catalog_repository);
+
+        let webhook_notifier = Arc::new(Notify::new());
+        let webhook_receiver = WebhookReceiver::new(
+            webhook_event_service.clone(),
+            signature_service,
+            webhook_notifier.clone(),
+            config.app().webhooks.max_payload_bytes,
+        );
+        let webhook_dispatcher = WebhookDispatcher::new(
+            unit_of_work.clone(),
+            user_service.clone(),
+            payment_service.clone(),
+            catalog_service.clone(),
+        );
+
+        let checkout_config = &config.app().checkout;
+        let checkout_settings = CheckoutSettings {
+            ui_mode: checkout_config.ui_mode,
+            mode: checkout_config.mode,
+            allowed_ui_modes: checkout_config.allowed_ui_modes.clone(),
+            allowed_modes: checkout_config.allowed_modes.clone(),
+            trial_period_days: checkout_config.trial_period_days,
+            allowed_products: checkout_config.allowed_products.clone(),
+        };
+
         Self {
             config,
             user_service,
             auth_service,
             payment_service,
             subscription_service,
-            signature_service,
+            unit_of_work,
+            webhook_receiver,
+            webhook_event_service,
+            webhook_notifier,
+            webhook_dispatcher,
+            catalog_service,
+            checkout_settings,
         }
     }
+
+    /// The worker processing stored webhook events, woken up by
+    /// `webhook_notifier`.
+    pub fn webhook_worker(
+        &self,
+    ) -> WebhookWorker<
+        PostgresWebhookEventRepository,
+        PostgresUnitOfWork,
+        PostgresUserRepository,
+        StripePaymentClient,
+        PostgresCatalogRepository,
+    > {
+        let webhook_config = &self.config.app().webhooks;
+        WebhookWorker::new(
+            self.webhook_event_service.clone(),
+            self.webhook_dispatcher.clone(),
+            WorkerSettings {
+                poll_interval: Duration::from_secs(webhook_config.poll_interval_secs),
+                batch_size: webhook_config.batch_size,
+                lease: Duration::from_secs(webhook_config.lease_secs),
+                retry: RetryPolicy {
+                    max_attempts: webhook_config.max_attempts,
+                    base_delay: Duration::from_secs(webhook_config.retry_base_delay_secs),
+                    max_delay: Duration::from_secs(webhook_config.retry_max_delay_secs),
+                },
+            },
+            self.webhook_notifier.clone(),
+        )
+    }
 }
@@ -1,27 +1,17 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Deserialize)]
-pub(super) struct FirebaseUser {
-    #[serde(alias = "localId")]
-    pub id: String,
-    pub email: String,
-    #[serde(alias = "emailVerified")]
+/// Claims of a Firebase ID token. `sub` is the Firebase uid.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct FirebaseClaims {
+    pub sub: String,
+    pub aud: String,
+    pub iss: String,
+    pub exp: i64,
+    pub iat: i64,
+    pub auth_time: i64,
+    pub email: Option<String>,
+    #[serde(default)]
     pub email_verified: bool,
-    #[serde(alias = "displayName")]
     pub name: Option<String>,
-    #[serde(alias = "photoUrl")]
-    pub photo: Option<String>,
-    #[serde(alias = "validSince")]
-    pub valid_since: String,
-    #[serde(alias = "lastLoginAt")]
-    pub last_login_at: String,
-    #[serde(alias = "createdAt")]
-    pub created_at: String,
-    #[serde(alias = "lastRefreshAt")]
-    pub last_refresh_at: String,
-}
-
-#[derive(Debug, Clone, Deserialize)]
-pub struct FirebaseResponse {
-    pub users: Vec<FirebaseUser>,
+    pub picture: Option<String>,
 }
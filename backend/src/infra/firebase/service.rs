@@ -1,64 +1,164 @@
 use crate::domain::user::entities::AuthProviderData;
 use crate::domain::user::services::Authenticator;
-use crate::infra::firebase::model::FirebaseResponse;
+use crate::infra::firebase::model::FirebaseClaims;
+use crate::infra::jwt::keys::{KeyFormat, SigningKeyCache};
 use crate::prelude::*;
-use serde_json::json;
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{decode, decode_header, Algorithm, Validation};
 use std::sync::Arc;
 
-#[derive(Debug, Clone)]
+const GOOGLE_CERTS_URL: &str =
+    "https://www.googleapis.com/robot/v1/metadata/x509/securetoken@system.gserviceaccount.com";
+const CLOCK_SKEW_SECS: u64 = 60;
+/// Provider name stored with identities of Firebase users.
+const PROVIDER: &str = "firebase";
+
+/// Verifies Firebase ID tokens locally against Google's signing certificates.
+#[derive(Clone)]
 pub struct FirebaseAuthenticatorService {
-    http: Arc<reqwest::Client>,
-    firebase_api_key: String,
+    project_id: String,
+    keys: SigningKeyCache,
 }
 impl FirebaseAuthenticatorService {
-    pub fn new(firebase_api_key: &str, http: Arc<reqwest::Client>) -> Self {
+    pub fn new(project_id: &str, http: Arc<reqwest::Client>) -> Self {
         Self {
-            http,
-            firebase_api_key: firebase_api_key.to_string(),
+            project_id: project_id.to_string(),
+            keys: SigningKeyCache::new(http, GOOGLE_CERTS_URL, KeyFormat::X509Certificates),
         }
     }
+
+    fn validation(&self) -> Validation {
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_audience(&[&self.project_id]);
+        validation.set_issuer(&[format!(
+            "https://securetoken.google.com/{}",
+            self.project_id
+        )]);
+        validation.set_required_spec_claims(&["exp", "iat", "aud", "iss", "sub"]);
+        validation.leeway = CLOCK_SKEW_SECS;
+        validation
+    }
 }
 
 impl Authenticator for FirebaseAuthenticatorService {
     async fn authenticate(&self, token: &str) -> Result<AuthProviderData> {
-        let url = format!(
-            "https://identitytoolkit.googleapis.com/v1/accounts:lookup?key={}",
-            self.firebase_api_key
-        );
-
-        let payload = json!({
-            "idToken": token
-        });
-
-        let response = self
-            .http
-            .post(&url)
-            .json(&payload)
-            .send()
-            .await
-            .map_err(|e| Error::AuthenticationFailed(e.to_string()))?;
+        let header = decode_header(token).map_err(|e| Error::InvalidToken(e.to_string()))?;
+        if header.alg != Algorithm::RS256 {
+            return Err(Error::InvalidToken(
+                "Unexpected signing algorithm".to_string(),
+            ));
+        }
+        let kid = header
+            .kid
+            .ok_or_else(|| Error::InvalidToken("Token has no key id".to_string()))?;
+        let key = self.keys.get(&kid).await?;
 
-        let response = response
-            .json::<FirebaseResponse>()
-            .await
-            .map_err(|e| Error::DeserializationError(e.to_string()))?;
+        let claims = decode::<FirebaseClaims>(token, &key, &self.validation())
+            .map_err(|e| Error::InvalidToken(e.to_string()))?
+            .claims;
 
-        if response.users.is_empty() {
-            tracing::error!("No users found");
-            return Err(Error::AuthenticationFailed(
-                "Authentication failed".to_string(),
-            ));
+        let latest = Utc::now().timestamp() + CLOCK_SKEW_SECS as i64;
+        if claims.sub.is_empty() {
+            return Err(Error::InvalidToken("Token has no subject".to_string()));
         }
-        if response.users.len() > 1 {
-            tracing::error!("Multiple users found");
-            return Err(Error::AuthenticationFailed(
-                "Authentication failed".to_string(),
+        if claims.iat > latest || claims.auth_time > latest {
+            return Err(Error::InvalidToken(
+                "Token was issued in the future".to_string(),
             ));
         }
-        let user = response.users.first().cloned().ok_or(Error::Unauthorized)?;
+        let email = claims
+            .email
+            .ok_or_else(|| Error::InvalidToken("Token has no email".to_string()))?;
+        let expires_at = DateTime::from_timestamp(claims.exp, 0)
+            .ok_or_else(|| Error::InvalidToken("Invalid expiration".to_string()))?;
 
         Ok(AuthProviderData::new(
-            user.id, user.email, user.name, user.photo,
+            PROVIDER.to_string(),
+            claims.sub,
+            email,
+            claims.email_verified,
+            claims.name,
+            claims.picture,
+            expires_at,
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{encode, DecodingKey, EncodingKey, Header};
+    use std::collections::HashMap;
+
+    const TEST_KEY: &str = include_str!("../../../fixtures/auth/test_key.pem");
+    const TEST_CERT: &str = include_str!("../../../fixtures/auth/test_cert.pem");
+    const PROJECT_ID: &str = "test-project";
+
+    fn authenticator() -> FirebaseAuthenticatorService {
+        let key = DecodingKey::from_rsa_pem(TEST_CERT.as_bytes()).unwrap();
+        FirebaseAuthenticatorService {
+            project_id: PROJECT_ID.to_string(),
+            keys: SigningKeyCache::with_keys(HashMap::from([("test-kid".to_string(), key)])),
+        }
+    }
+
+    fn claims() -> FirebaseClaims {
+        let now = Utc::now().timestamp();
+        FirebaseClaims {
+            sub: "firebase-uid".to_string(),
+            aud: PROJECT_ID.to_string(),
+            iss: format!("https://securetoken.google.com/{}", PROJECT_ID),
+            exp: now + 3600,
+            iat: now,
+            auth_time: now,
+            email: Some("john@example.com".to_string()),
+            email_verified: true,
+            name: Some("John".to_string()),
+            picture: None,
+        }
+    }
+
+    fn sign(claims: &FirebaseClaims) -> String {
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some("test-kid".to_string());
+        let key = EncodingKey::from_rsa_pem(TEST_KEY.as_bytes()).unwrap();
+        encode(&header, claims, &key).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_authenticate_valid_token() {
+        let auth = authenticator()
+            .authenticate(&sign(&claims()))
+            .await
+            .unwrap();
+        assert_eq!(auth.provider, "firebase");
+        assert_eq!(auth.id, "firebase-uid");
+        assert!(auth.email_verified);
+        assert_eq!(auth.email, "john@example.com");
+        assert_eq!(auth.name.as_deref(), Some("John"));
+    }
+
+    #[tokio::test]
+    async fn test_authenticate_rejects_invalid_claims() {
+        let service = authenticator();
+
+        let mut wrong_audience = claims();
+        wrong_audience.aud = "other-project".to_string();
+        let mut wrong_issuer = claims();
+        wrong_issuer.iss = "https://securetoken.google.com/other-project".to_string();
+        let mut expired = claims();
+        expired.exp = Utc::now().timestamp() - 3600;
+        let mut future_auth = claims();
+        future_auth.auth_time = Utc::now().timestamp() + 3600;
+
+        for claims in [wrong_audience, wrong_issuer, expired, future_auth] {
+            let result = service.authenticate(&sign(&claims)).await;
+            assert!(matches!(result, Err(Error::InvalidToken(_))));
+        }
+
+        let mut tampered = sign(&claims());
+        tampered.push('x');
+        let result = service.authenticate(&tampered).await;
+        assert!(matches!(result, Err(Error::InvalidToken(_))));
+    }
+}
@@ -0,0 +1,193 @@
+use crate::prelude::*;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::DecodingKey;
+use reqwest::header::{HeaderMap, CACHE_CONTROL};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// Used when the key endpoint does not send a `Cache-Control: max-age`.
+const DEFAULT_KEYS_TTL: Duration = Duration::from_secs(3600);
+/// Unknown key ids trigger a refetch at most this often.
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Document format served by a signing key endpoint.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyFormat {
+    /// A JSON object mapping key ids to PEM encoded x509 certificates.
+    X509Certificates,
+    /// A JSON Web Key Set.
+    Jwks,
+}
+
+#[derive(Default)]
+struct SigningKeys {
+    keys: HashMap<String, DecodingKey>,
+    fetched_at: Option<Instant>,
+    expires_at: Option<Instant>,
+}
+impl SigningKeys {
+    fn is_fresh(&self) -> bool {
+        self.expires_at.is_some_and(|at| Instant::now() < at)
+    }
+    fn recently_fetched(&self) -> bool {
+        self.fetched_at
+            .is_some_and(|at| at.elapsed() < MIN_REFRESH_INTERVAL)
+    }
+}
+
+/// Token signing keys fetched from a provider and cached for as long as its
+/// `Cache-Control` header allows.
+#[derive(Clone)]
+pub struct SigningKeyCache {
+    http: Arc<reqwest::Client>,
+    url: String,
+    format: KeyFormat,
+    keys: Arc<RwLock<SigningKeys>>,
+}
+impl SigningKeyCache {
+    pub fn new(http: Arc<reqwest::Client>, url: &str, format: KeyFormat) -> Self {
+        Self {
+            http,
+            url: url.to_string(),
+            format,
+            keys: Arc::new(RwLock::new(SigningKeys::default())),
+        }
+    }
+
+    /// Builds a cache that never hits the network, for tests.
+    #[cfg(test)]
+    pub fn with_keys(keys: HashMap<String, DecodingKey>) -> Self {
+        let now = Instant::now();
+        Self {
+            http: Arc::new(reqwest::Client::new()),
+            url: String::new(),
+            format: KeyFormat::Jwks,
+            keys: Arc::new(RwLock::new(SigningKeys {
+                keys,
+                fetched_at: Some(now),
+                expires_at: Some(now + DEFAULT_KEYS_TTL),
+            })),
+        }
+    }
+
+    pub async fn get(&self, kid: &str) -> Result<DecodingKey> {
+        {
+            let cache = self.keys.read().await;
+            if cache.is_fresh() {
+                if let Some(key) = cache.keys.get(kid) {
+                    return Ok(key.clone());
+                }
+            }
+        }
+
+        let mut cache = self.keys.write().await;
+        // Another request may have refreshed the keys while we waited.
+        let known = cache.is_fresh() && cache.keys.contains_key(kid);
+        if !known && (!cache.is_fresh() || !cache.recently_fetched()) {
+            *cache = self.fetch().await?;
+        }
+        cache
+            .keys
+            .get(kid)
+            .cloned()
+            .ok_or_else(|| Error::InvalidToken(format!("Unknown signing key {}", kid)))
+    }
+
+    async fn fetch(&self) -> Result<SigningKeys> {
+        tracing::debug!("Fetching signing keys from {}", self.url);
+        let response = self
+            .http
+            .get(&self.url)
+            .send()
+            .await
+            .map_err(|e| Error::AuthenticationFailed(e.to_string()))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            tracing::error!("Failed to fetch signing keys (HTTP {})", status);
+            return Err(Error::AuthenticationFailed(
+                "Failed to fetch signing keys".to_string(),
+            ));
+        }
+
+        let ttl = max_age(response.headers()).unwrap_or(DEFAULT_KEYS_TTL);
+        let keys = match self.format {
+            KeyFormat::X509Certificates => {
+                let certificates = response
+                    .json::<HashMap<String, String>>()
+                    .await
+                    .map_err(|e| Error::DeserializationError(e.to_string()))?;
+                certificates
+                    .into_iter()
+                    .filter_map(|(kid, certificate)| {
+                        match DecodingKey::from_rsa_pem(certificate.as_bytes()) {
+                            Ok(key) => Some((kid, key)),
+                            Err(e) => {
+                                tracing::warn!("Ignoring invalid certificate {}: {}", kid, e);
+                                None
+                            }
+                        }
+                    })
+                    .collect()
+            }
+            KeyFormat::Jwks => {
+                let jwks = response
+                    .json::<JwkSet>()
+                    .await
+                    .map_err(|e| Error::DeserializationError(e.to_string()))?;
+                jwks.keys
+                    .iter()
+                    .filter_map(|jwk| {
+                        let kid = jwk.common.key_id.clone()?;
+                        match DecodingKey::from_jwk(jwk) {
+                            Ok(key) => Some((kid, key)),
+                            Err(e) => {
+                                tracing::warn!("Ignoring invalid JWK {}: {}", kid, e);
+                                None
+                            }
+                        }
+                    })
+                    .collect()
+            }
+        };
+
+        let now = Instant::now();
+        Ok(SigningKeys {
+            keys,
+            fetched_at: Some(now),
+            expires_at: Some(now + ttl),
+        })
+    }
+}
+
+/// Reads `max-age` from a `Cache-Control` header.
+fn max_age(headers: &HeaderMap) -> Option<Duration> {
+    let cache_control = headers.get(CACHE_CONTROL)?.to_str().ok()?;
+    cache_control
+        .split(',')
+        .filter_map(|directive| directive.trim().strip_prefix("max-age="))
+        .find_map(|seconds| seconds.parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_max_age() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CACHE_CONTROL,
+            "public, max-age=19302, must-revalidate, no-transform"
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(max_age(&headers), Some(Duration::from_secs(19302)));
+
+        headers.insert(CACHE_CONTROL, "no-cache".parse().unwrap());
+        assert_eq!(max_age(&headers), None);
+    }
+}
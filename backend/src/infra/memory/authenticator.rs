@@ -0,0 +1,41 @@
+use crate::domain::user::entities::AuthProviderData;
+use crate::domain::user::services::Authenticator;
+use crate::infra::memory::failures::Failures;
+use crate::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Accepts the tokens registered with `add_token`, standing in for Firebase
+/// or an OIDC provider.
+#[derive(Clone, Default)]
+pub struct InMemoryAuthenticator {
+    tokens: Arc<Mutex<HashMap<String, AuthProviderData>>>,
+    failures: Failures,
+}
+impl InMemoryAuthenticator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn failures(&self) -> &Failures {
+        &self.failures
+    }
+
+    /// `token` authenticates as `claims` from now on.
+    pub fn add_token(&self, token: &str, claims: AuthProviderData) {
+        let mut tokens = self.tokens.lock().unwrap();
+        tokens.insert(token.to_string(), claims);
+    }
+}
+
+impl Authenticator for InMemoryAuthenticator {
+    async fn authenticate(&self, token: &str) -> Result<AuthProviderData> {
+        self.failures
+            .check("authenticator.authenticate", Error::AuthenticationFailed)?;
+        let tokens = self.tokens.lock().unwrap();
+        tokens
+            .get(token)
+            .cloned()
+            .ok_or(Error::InvalidToken("Unknown token".to_string()))
+    }
+}
@@ -0,0 +1,128 @@
+use crate::domain::catalog::entities::{Price, Product};
+use crate::domain::catalog::repository::CatalogRepository;
+use crate::infra::memory::InMemoryDatabase;
+use crate::prelude::*;
+use chrono::{DateTime, Utc};
+
+#[derive(Clone)]
+pub struct InMemoryCatalogRepository {
+    db: InMemoryDatabase,
+}
+impl InMemoryCatalogRepository {
+    pub fn new(db: InMemoryDatabase) -> Self {
+        Self { db }
+    }
+}
+
+impl CatalogRepository for InMemoryCatalogRepository {
+    async fn upsert_product(&self, product: &Product, event_at: DateTime<Utc>) -> Result<Product> {
+        let mut tables = self.db.tables("catalog.upsert_product")?;
+        if tables.changed_after(product.id(), event_at) {
+            return Err(Error::StaleEvent(format!(
+                "Product {} was changed after {}",
+                product.id(),
+                event_at
+            )));
+        }
+        tables.products.retain(|other| other.id() != product.id());
+        tables.products.push(product.clone());
+        tables
+            .catalog_events
+            .insert(product.id().to_string(), event_at);
+        Ok(product.clone())
+    }
+
+    /// Deletes the prices of the product too, as the foreign key cascades.
+    async fn delete_product(&self, id: &str, event_at: DateTime<Utc>) -> Result<()> {
+        let mut tables = self.db.tables("catalog.delete_product")?;
+        if tables.changed_after(id, event_at) {
+            return Ok(());
+        }
+        let prices: Vec<String> = tables
+            .prices
+            .iter()
+            .filter(|price| price.product_id() == id)
+            .map(|price| price.id().to_string())
+            .collect();
+        tables.products.retain(|product| product.id() != id);
+        tables.prices.retain(|price| price.product_id() != id);
+        tables.catalog_events.remove(id);
+        for price in prices {
+            tables.catalog_events.remove(&price);
+        }
+        Ok(())
+    }
+
+    async fn find_active_products(&self) -> Result<Vec<Product>> {
+        let tables = self.db.tables("catalog.find_active_products")?;
+        let mut products: Vec<Product> = tables
+            .products
+            .iter()
+            .filter(|product| product.is_active())
+            .cloned()
+            .collect();
+        products.sort_by(|a, b| a.name().cmp(b.name()));
+        Ok(products)
+    }
+
+    async fn upsert_price(&self, price: &Price, event_at: DateTime<Utc>) -> Result<Price> {
+        let mut tables = self.db.tables("catalog.upsert_price")?;
+        if !tables
+            .products
+            .iter()
+            .any(|product| product.id() == price.product_id())
+        {
+            return Err(Error::NotFound(format!(
+                "Product {} of price {} not found",
+                price.product_id(),
+                price.id()
+            )));
+        }
+        if tables.changed_after(price.id(), event_at) {
+            return Err(Error::StaleEvent(format!(
+                "Price {} was changed after {}",
+                price.id(),
+                event_at
+            )));
+        }
+        tables.prices.retain(|other| other.id() != price.id());
+        tables.prices.push(price.clone());
+        tables
+            .catalog_events
+            .insert(price.id().to_string(), event_at);
+        Ok(price.clone())
+    }
+
+    async fn delete_price(&self, id: &str, event_at: DateTime<Utc>) -> Result<()> {
+        let mut tables = self.db.tables("catalog.delete_price")?;
+        if tables.changed_after(id, event_at) {
+            return Ok(());
+        }
+        tables.prices.retain(|price| price.id() != id);
+        tables.catalog_events.remove(id);
+        Ok(())
+    }
+
+    async fn find_active_prices(&self) -> Result<Vec<Price>> {
+        let tables = self.db.tables("catalog.find_active_prices")?;
+        let mut prices: Vec<Price> = tables
+            .prices
+            .iter()
+            .filter(|price| price.is_active())
+            .cloned()
+            .collect();
+        // Prices without an amount come last, as with `ORDER BY unit_amount`.
+        prices.sort_by_key(|price| (price.unit_amount().is_none(), price.unit_amount()));
+        Ok(prices)
+    }
+
+    async fn find_prices(&self, ids: &[String]) -> Result<Vec<Price>> {
+        let tables = self.db.tables("catalog.find_prices")?;
+        Ok(tables
+            .prices
+            .iter()
+            .filter(|price| ids.iter().any(|id| id == price.id()))
+            .cloned()
+            .collect())
+    }
+}
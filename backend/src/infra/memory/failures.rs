@@ -0,0 +1,47 @@
+use crate::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Makes chosen operations of the fakes fail, to test how use cases cope
+/// with an unavailable database or payment provider. Operations are named
+/// after the trait method, prefixed by the fake: `users.update`,
+/// `subscriptions.find_by_user_id`, `payment.get_subscription`,
+/// `authenticator.authenticate`, ...
+#[derive(Debug, Clone, Default)]
+pub struct Failures {
+    /// Remaining failures per operation, `None` failing forever.
+    rules: Arc<Mutex<HashMap<String, Option<usize>>>>,
+}
+impl Failures {
+    /// Every following call of `operation` fails.
+    pub fn fail_always(&self, operation: &str) {
+        let mut rules = self.rules.lock().unwrap();
+        rules.insert(operation.to_string(), None);
+    }
+
+    /// The next `times` calls of `operation` fail.
+    pub fn fail_times(&self, operation: &str, times: usize) {
+        let mut rules = self.rules.lock().unwrap();
+        if times == 0 {
+            rules.remove(operation);
+        } else {
+            rules.insert(operation.to_string(), Some(times));
+        }
+    }
+
+    /// Fails with `error` when a failure of `operation` is pending.
+    pub(super) fn check(&self, operation: &str, error: impl FnOnce(String) -> Error) -> Result<()> {
+        let mut rules = self.rules.lock().unwrap();
+        match rules.get_mut(operation) {
+            None => Ok(()),
+            Some(None) => Err(error(format!("Injected failure of {}", operation))),
+            Some(Some(remaining)) => {
+                *remaining -= 1;
+                if *remaining == 0 {
+                    rules.remove(operation);
+                }
+                Err(error(format!("Injected failure of {}", operation)))
+            }
+        }
+    }
+}
@@ -0,0 +1,125 @@
+//! In-memory fakes of the repositories and external services, so use cases
+//! can be tested end to end without Postgres, Stripe or an identity
+//! provider. Ids are assigned in sequence, so tests can predict them.
+
+pub mod authenticator;
+pub mod catalog;
+pub mod failures;
+pub mod payment;
+pub mod subscription;
+pub mod unit_of_work;
+pub mod user;
+pub mod webhook_event;
+
+use crate::domain::catalog::entities::{Price, Product};
+use crate::domain::pagination::{Page, PageRequest, SortDirection};
+use crate::domain::subscription::entities::{Subscription, WebhookEvent};
+use crate::domain::user::entities::{User, UserIdentity, UserStatusChange};
+use crate::infra::memory::catalog::InMemoryCatalogRepository;
+use crate::infra::memory::failures::Failures;
+use crate::infra::memory::subscription::InMemorySubscriptionRepository;
+use crate::infra::memory::unit_of_work::InMemoryUnitOfWork;
+use crate::infra::memory::user::InMemoryUserRepository;
+use crate::infra::memory::webhook_event::InMemoryWebhookEventRepository;
+use crate::prelude::*;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+#[derive(Debug, Clone, Default)]
+struct Tables {
+    users: Vec<User>,
+    identities: Vec<UserIdentity>,
+    status_changes: Vec<UserStatusChange>,
+    subscriptions: Vec<Subscription>,
+    products: Vec<Product>,
+    prices: Vec<Price>,
+    /// `last_event_at` of the stored products and prices, by id.
+    catalog_events: HashMap<String, DateTime<Utc>>,
+    webhook_events: Vec<WebhookEvent>,
+    last_id: i32,
+}
+impl Tables {
+    fn next_id(&mut self) -> i32 {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    /// Whether the catalog object `id` was written by an event newer than
+    /// `event_at`, like the `last_event_at` check of the Postgres upserts.
+    fn changed_after(&self, id: &str, event_at: DateTime<Utc>) -> bool {
+        self.catalog_events
+            .get(id)
+            .is_some_and(|last_event_at| *last_event_at > event_at)
+    }
+}
+
+/// The tables behind the in-memory repositories. Repositories taken from the
+/// same database, or from its clones, see each other's changes, as they would
+/// through Postgres.
+#[derive(Clone, Default)]
+pub struct InMemoryDatabase {
+    tables: Arc<Mutex<Tables>>,
+    /// Held by the open transaction, standing in for its row locks.
+    transaction_lock: Arc<tokio::sync::Mutex<()>>,
+    failures: Failures,
+}
+impl InMemoryDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn failures(&self) -> &Failures {
+        &self.failures
+    }
+
+    pub fn users(&self) -> InMemoryUserRepository {
+        InMemoryUserRepository::new(self.clone())
+    }
+
+    pub fn subscriptions(&self) -> InMemorySubscriptionRepository {
+        InMemorySubscriptionRepository::new(self.clone())
+    }
+
+    pub fn catalog(&self) -> InMemoryCatalogRepository {
+        InMemoryCatalogRepository::new(self.clone())
+    }
+
+    pub fn webhook_events(&self) -> InMemoryWebhookEventRepository {
+        InMemoryWebhookEventRepository::new(self.clone())
+    }
+
+    pub fn unit_of_work(&self) -> InMemoryUnitOfWork {
+        InMemoryUnitOfWork::new(self.clone())
+    }
+
+    /// Locks the tables for one operation, unless a failure was injected.
+    fn tables(&self, operation: &str) -> Result<MutexGuard<'_, Tables>> {
+        self.failures.check(operation, Error::Database)?;
+        Ok(self.tables.lock().unwrap())
+    }
+}
+
+/// Keyset pagination over filtered rows, with the semantics of the Postgres
+/// repositories: rows strictly after the cursor in the `key` order.
+fn paginate<T, C, K: Ord>(
+    mut rows: Vec<T>,
+    direction: SortDirection,
+    page: &PageRequest<C>,
+    key: impl Fn(&C) -> K,
+    cursor_of: impl Fn(&T) -> C,
+) -> Page<T, C> {
+    rows.sort_by_key(|row| key(&cursor_of(row)));
+    if direction == SortDirection::Desc {
+        rows.reverse();
+    }
+    if let Some(after) = &page.after {
+        let after = key(after);
+        rows.retain(|row| match direction {
+            SortDirection::Asc => key(&cursor_of(row)) > after,
+            SortDirection::Desc => key(&cursor_of(row)) < after,
+        });
+    }
+    rows.truncate(usize::try_from(page.limit + 1).unwrap_or(0));
+    Page::from_rows(rows, page.limit, cursor_of)
+}
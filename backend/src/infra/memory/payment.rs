@@ -0,0 +1,177 @@
+use crate::domain::catalog::entities::{Price, Product};
+use crate::domain::payment::client::PaymentClient;
+use crate::domain::payment::entities::checkout::{
+    CheckoutSession, CheckoutSessionDetails, CheckoutSessionResult,
+};
+use crate::domain::payment::entities::customer::Customer;
+use crate::domain::payment::entities::portal::CustomerPortalSession;
+use crate::domain::payment::entities::subscription::SubscriptionDetails;
+use crate::domain::payment::value_objects::checkout_status::CheckoutStatus;
+use crate::domain::payment::value_objects::ui_mode::UiMode;
+use crate::infra::memory::failures::Failures;
+use crate::prelude::*;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+#[derive(Debug, Default)]
+struct Account {
+    /// `(id, email, name)`, in creation order.
+    customers: Vec<(String, String, Option<String>)>,
+    checkout_sessions: Vec<CheckoutSessionDetails>,
+    /// In creation order, `list_subscriptions` returns them reversed.
+    subscriptions: Vec<SubscriptionDetails>,
+    products: Vec<Product>,
+    prices: Vec<Price>,
+}
+
+/// A payment provider account kept in memory. Customers and sessions get
+/// Stripe-like ids; subscriptions, products and prices are seeded by tests.
+#[derive(Clone, Default)]
+pub struct InMemoryPaymentClient {
+    account: Arc<Mutex<Account>>,
+    failures: Failures,
+}
+impl InMemoryPaymentClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn failures(&self) -> &Failures {
+        &self.failures
+    }
+
+    /// Adds or replaces the subscription with the same id.
+    pub fn add_subscription(&self, subscription: SubscriptionDetails) {
+        let mut account = self.account.lock().unwrap();
+        account
+            .subscriptions
+            .retain(|other| other.id() != subscription.id());
+        account.subscriptions.push(subscription);
+    }
+
+    pub fn add_product(&self, product: Product) {
+        self.account.lock().unwrap().products.push(product);
+    }
+
+    pub fn add_price(&self, price: Price) {
+        self.account.lock().unwrap().prices.push(price);
+    }
+
+    /// Locks the account for one call, unless a failure was injected.
+    fn account(&self, operation: &str) -> Result<MutexGuard<'_, Account>> {
+        self.failures
+            .check(operation, |message| Error::ApiError(500, message))?;
+        Ok(self.account.lock().unwrap())
+    }
+}
+
+impl PaymentClient for InMemoryPaymentClient {
+    async fn create_customer(&self, customer: &Customer) -> Result<Customer> {
+        let mut account = self.account("payment.create_customer")?;
+        let id = format!("cus_{:04}", account.customers.len() + 1);
+        account.customers.push((
+            id.clone(),
+            customer.email(),
+            customer.name().map(|s| s.to_string()),
+        ));
+        Ok(Customer::construct(
+            id,
+            customer.email(),
+            customer.name().map(|s| s.to_string()),
+        ))
+    }
+
+    async fn get_customer(&self, email: &str) -> Result<Customer> {
+        let account = self.account("payment.get_customer")?;
+        account
+            .customers
+            .iter()
+            .find(|(_, customer_email, _)| customer_email == email)
+            .map(|(id, email, name)| Customer::construct(id.clone(), email.clone(), name.clone()))
+            .ok_or_else(|| Error::NotFound("Customer not found".to_string()))
+    }
+
+    async fn create_checkout_session(
+        &self,
+        checkout: &CheckoutSession,
+    ) -> Result<CheckoutSessionResult> {
+        let mut account = self.account("payment.create_checkout_session")?;
+        let id = format!("cs_test_{:04}", account.checkout_sessions.len() + 1);
+        let customer_email = account
+            .customers
+            .iter()
+            .find(|(id, _, _)| id == checkout.customer())
+            .map(|(_, email, _)| email.clone());
+        account
+            .checkout_sessions
+            .push(CheckoutSessionDetails::construct(
+                id.clone(),
+                Some(CheckoutStatus::Open),
+                "unpaid".to_string(),
+                Some(checkout.customer().to_string()),
+                customer_email,
+                checkout.client_reference_id().map(|s| s.to_string()),
+            ));
+        let (url, client_secret) = match checkout.ui_mode() {
+            UiMode::Hosted => (Some(format!("https://checkout.stripe.test/{}", id)), None),
+            UiMode::Embedded => (None, Some(format!("{}_secret", id))),
+        };
+        Ok(CheckoutSessionResult {
+            id,
+            url,
+            client_secret,
+            ui_mode: checkout.ui_mode(),
+        })
+    }
+
+    async fn get_checkout_session(&self, session_id: &str) -> Result<CheckoutSessionDetails> {
+        let account = self.account("payment.get_checkout_session")?;
+        account
+            .checkout_sessions
+            .iter()
+            .find(|session| session.id() == session_id)
+            .cloned()
+            .ok_or_else(|| Error::NotFound(format!("Checkout session {} not found", session_id)))
+    }
+
+    async fn create_portal_session(&self, portal: &CustomerPortalSession) -> Result<String> {
+        let _account = self.account("payment.create_portal_session")?;
+        Ok(format!(
+            "https://billing.stripe.test/{}?return_url={}",
+            portal.customer(),
+            portal.return_url()
+        ))
+    }
+
+    async fn get_subscription(&self, subscription_id: &str) -> Result<SubscriptionDetails> {
+        let account = self.account("payment.get_subscription")?;
+        account
+            .subscriptions
+            .iter()
+            .find(|subscription| subscription.id() == subscription_id)
+            .cloned()
+            .ok_or_else(|| {
+                Error::ApiError(404, format!("No such subscription: '{}'", subscription_id))
+            })
+    }
+
+    async fn list_subscriptions(&self, customer_id: &str) -> Result<Vec<SubscriptionDetails>> {
+        let account = self.account("payment.list_subscriptions")?;
+        Ok(account
+            .subscriptions
+            .iter()
+            .rev()
+            .filter(|subscription| subscription.customer() == customer_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn list_products(&self) -> Result<Vec<Product>> {
+        let account = self.account("payment.list_products")?;
+        Ok(account.products.clone())
+    }
+
+    async fn list_prices(&self) -> Result<Vec<Price>> {
+        let account = self.account("payment.list_prices")?;
+        Ok(account.prices.clone())
+    }
+}
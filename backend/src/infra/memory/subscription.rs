@@ -0,0 +1,224 @@
+use crate::domain::pagination::{Page, PageRequest};
+use crate::domain::subscription::entities::Subscription;
+use crate::domain::subscription::repository::{
+    SubscriptionCursor, SubscriptionFilter, SubscriptionRepository, SubscriptionSort,
+    SubscriptionSortField,
+};
+use crate::infra::memory::{paginate, InMemoryDatabase};
+use crate::prelude::*;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct InMemorySubscriptionRepository {
+    db: InMemoryDatabase,
+}
+impl InMemorySubscriptionRepository {
+    pub fn new(db: InMemoryDatabase) -> Self {
+        Self { db }
+    }
+
+    fn find_by(
+        &self,
+        operation: &str,
+        not_found: String,
+        predicate: impl Fn(&Subscription) -> bool,
+    ) -> Result<Subscription> {
+        let tables = self.db.tables(operation)?;
+        tables
+            .subscriptions
+            .iter()
+            .find(|subscription| predicate(subscription))
+            .cloned()
+            .ok_or(Error::NotFound(not_found))
+    }
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum SubscriptionKey {
+    CreatedAt(DateTime<Utc>, i32),
+    /// `None` orders after every date, as `NULLS LAST` does.
+    CurrentPeriodEnd(bool, Option<DateTime<Utc>>, i32),
+}
+
+fn subscription_key(field: SubscriptionSortField, cursor: &SubscriptionCursor) -> SubscriptionKey {
+    match field {
+        SubscriptionSortField::CreatedAt => {
+            SubscriptionKey::CreatedAt(cursor.created_at, cursor.id)
+        }
+        SubscriptionSortField::CurrentPeriodEnd => SubscriptionKey::CurrentPeriodEnd(
+            cursor.current_period_end.is_none(),
+            cursor.current_period_end,
+            cursor.id,
+        ),
+    }
+}
+
+fn matches(subscription: &Subscription, filter: &SubscriptionFilter) -> bool {
+    filter
+        .status
+        .as_ref()
+        .is_none_or(|status| subscription.status() == status)
+        && filter
+            .price_id
+            .as_ref()
+            .is_none_or(|price_id| subscription.stripe_price_id() == price_id)
+        && filter
+            .product_id
+            .as_ref()
+            .is_none_or(|product_id| subscription.stripe_product_id() == product_id)
+        && filter
+            .cancel_at_period_end
+            .is_none_or(|cancel| subscription.cancel_at_period_end() == cancel)
+        && filter.period_ends_before.is_none_or(|before| {
+            subscription
+                .current_period_end()
+                .is_some_and(|end| end < before)
+        })
+}
+
+impl SubscriptionRepository for InMemorySubscriptionRepository {
+    async fn save(&self, subscription: &Subscription) -> Result<Subscription> {
+        let mut tables = self.db.tables("subscriptions.save")?;
+        let taken = tables.subscriptions.iter().any(|other| {
+            other.user_id() == subscription.user_id()
+                || other.stripe_customer_id() == subscription.stripe_customer_id()
+        });
+        if taken {
+            return Err(Error::RecordAlreadyExists);
+        }
+
+        let saved = Subscription::construct(
+            tables.next_id(),
+            *subscription.user_id(),
+            subscription.stripe_customer_id().to_string(),
+            subscription.stripe_price_id().to_string(),
+            subscription.stripe_product_id().to_string(),
+            subscription.stripe_subscription_id().to_string(),
+            subscription.status().clone(),
+            subscription.has_used_trial(),
+            subscription.current_period_end(),
+            subscription.cancel_at_period_end(),
+            subscription.canceled_at(),
+            subscription.created_at(),
+            subscription.updated_at(),
+            subscription.last_event_at(),
+        );
+        tables.subscriptions.push(saved.clone());
+        Ok(saved)
+    }
+
+    async fn find(&self, id: i32) -> Result<Subscription> {
+        self.find_by(
+            "subscriptions.find",
+            format!("Subscription {} not found", id),
+            |subscription| subscription.id() == id,
+        )
+    }
+
+    async fn find_by_strip_subscription_id(&self, subscription_id: &str) -> Result<Subscription> {
+        self.find_by(
+            "subscriptions.find_by_strip_subscription_id",
+            format!(
+                "Subscription with stripe subscription id {} not found",
+                subscription_id
+            ),
+            |subscription| subscription.stripe_subscription_id() == subscription_id,
+        )
+    }
+
+    async fn find_by_user_id(&self, user_id: &Uuid) -> Result<Subscription> {
+        self.find_by(
+            "subscriptions.find_by_user_id",
+            format!("Subscription with user id {} not found", user_id),
+            |subscription| subscription.user_id() == user_id,
+        )
+    }
+
+    /// The transaction of `InMemoryUnitOfWork` already excludes every other
+    /// writer, so no row is locked here.
+    async fn find_by_user_id_for_update(&self, user_id: &Uuid) -> Result<Subscription> {
+        self.find_by(
+            "subscriptions.find_by_user_id_for_update",
+            format!("Subscription with user id {} not found", user_id),
+            |subscription| subscription.user_id() == user_id,
+        )
+    }
+
+    async fn find_by_customer_id(&self, customer_id: &str) -> Result<Subscription> {
+        self.find_by(
+            "subscriptions.find_by_customer_id",
+            format!("Subscription with customer id {} not found", customer_id),
+            |subscription| subscription.stripe_customer_id() == customer_id,
+        )
+    }
+
+    async fn find_page(
+        &self,
+        filter: &SubscriptionFilter,
+        sort: SubscriptionSort,
+        page: &PageRequest<SubscriptionCursor>,
+    ) -> Result<Page<Subscription, SubscriptionCursor>> {
+        let tables = self.db.tables("subscriptions.find_page")?;
+        let rows = tables
+            .subscriptions
+            .iter()
+            .filter(|subscription| matches(subscription, filter))
+            .cloned()
+            .collect();
+        Ok(paginate(
+            rows,
+            sort.direction,
+            page,
+            |cursor| subscription_key(sort.field, cursor),
+            |subscription| SubscriptionCursor::from(subscription),
+        ))
+    }
+
+    async fn count(&self, filter: &SubscriptionFilter) -> Result<i64> {
+        let tables = self.db.tables("subscriptions.count")?;
+        let count = tables
+            .subscriptions
+            .iter()
+            .filter(|subscription| matches(subscription, filter))
+            .count();
+        Ok(count as i64)
+    }
+
+    async fn update(&self, subscription: &Subscription) -> Result<Subscription> {
+        let mut tables = self.db.tables("subscriptions.update")?;
+        let stored = tables
+            .subscriptions
+            .iter_mut()
+            .find(|stored| stored.id() == subscription.id())
+            .ok_or_else(|| {
+                Error::NotFound(format!("Subscription {} not found", subscription.id()))
+            })?;
+        // The owner, the customer and the creation date are never rewritten.
+        *stored = Subscription::construct(
+            stored.id(),
+            *stored.user_id(),
+            stored.stripe_customer_id().to_string(),
+            subscription.stripe_price_id().to_string(),
+            subscription.stripe_product_id().to_string(),
+            subscription.stripe_subscription_id().to_string(),
+            subscription.status().clone(),
+            subscription.has_used_trial(),
+            subscription.current_period_end(),
+            subscription.cancel_at_period_end(),
+            subscription.canceled_at(),
+            stored.created_at(),
+            subscription.updated_at(),
+            subscription.last_event_at(),
+        );
+        Ok(stored.clone())
+    }
+
+    async fn delete(&self, id: i32) -> Result<()> {
+        let mut tables = self.db.tables("subscriptions.delete")?;
+        tables
+            .subscriptions
+            .retain(|subscription| subscription.id() != id);
+        Ok(())
+    }
+}
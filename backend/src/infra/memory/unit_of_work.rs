@@ -0,0 +1,72 @@
+use crate::domain::unit_of_work::{Transaction, UnitOfWork};
+use crate::infra::memory::subscription::InMemorySubscriptionRepository;
+use crate::infra::memory::user::InMemoryUserRepository;
+use crate::infra::memory::{InMemoryDatabase, Tables};
+use crate::prelude::*;
+use tokio::sync::OwnedMutexGuard;
+
+#[derive(Clone)]
+pub struct InMemoryUnitOfWork {
+    db: InMemoryDatabase,
+}
+impl InMemoryUnitOfWork {
+    pub fn new(db: InMemoryDatabase) -> Self {
+        Self { db }
+    }
+}
+impl UnitOfWork for InMemoryUnitOfWork {
+    type Transaction = InMemoryTransaction;
+
+    async fn begin(&self) -> Result<InMemoryTransaction> {
+        self.db
+            .failures
+            .check("unit_of_work.begin", Error::Database)?;
+        let lock = self.db.transaction_lock.clone().lock_owned().await;
+        let snapshot = self.db.tables.lock().unwrap().clone();
+        Ok(InMemoryTransaction {
+            users: self.db.users(),
+            subscriptions: self.db.subscriptions(),
+            db: self.db.clone(),
+            snapshot: Some(snapshot),
+            _lock: lock,
+        })
+    }
+}
+
+/// Writes straight through to the tables, and puts back the state they had
+/// at `begin` when dropped without `commit`. Transactions run one at a time,
+/// but calls made outside of any transaction meanwhile are rolled back too.
+pub struct InMemoryTransaction {
+    db: InMemoryDatabase,
+    users: InMemoryUserRepository,
+    subscriptions: InMemorySubscriptionRepository,
+    snapshot: Option<Tables>,
+    _lock: OwnedMutexGuard<()>,
+}
+impl Transaction for InMemoryTransaction {
+    type Users = InMemoryUserRepository;
+    type Subscriptions = InMemorySubscriptionRepository;
+
+    fn users(&self) -> &InMemoryUserRepository {
+        &self.users
+    }
+
+    fn subscriptions(&self) -> &InMemorySubscriptionRepository {
+        &self.subscriptions
+    }
+
+    async fn commit(mut self) -> Result<()> {
+        self.db
+            .failures
+            .check("unit_of_work.commit", Error::Database)?;
+        self.snapshot = None;
+        Ok(())
+    }
+}
+impl Drop for InMemoryTransaction {
+    fn drop(&mut self) {
+        if let Some(snapshot) = self.snapshot.take() {
+            *self.db.tables.lock().unwrap() = snapshot;
+        }
+    }
+}
@@ -0,0 +1,306 @@
+use crate::domain::pagination::{Page, PageRequest};
+use crate::domain::user::entities::{Profile, User, UserIdentity, UserStatusChange};
+use crate::domain::user::repositories::{
+    UserCursor, UserFilter, UserRepository, UserSort, UserSortField,
+};
+use crate::infra::memory::{paginate, InMemoryDatabase, Tables};
+use crate::prelude::*;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct InMemoryUserRepository {
+    db: InMemoryDatabase,
+}
+impl InMemoryUserRepository {
+    pub fn new(db: InMemoryDatabase) -> Self {
+        Self { db }
+    }
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum UserKey {
+    CreatedAt(DateTime<Utc>, Uuid),
+    Email(String, Uuid),
+}
+
+fn user_key(field: UserSortField, cursor: &UserCursor) -> UserKey {
+    match field {
+        UserSortField::CreatedAt => UserKey::CreatedAt(cursor.created_at, cursor.id),
+        UserSortField::Email => UserKey::Email(cursor.email.clone(), cursor.id),
+    }
+}
+
+fn matches(tables: &Tables, user: &User, filter: &UserFilter) -> bool {
+    let email = filter
+        .email
+        .as_ref()
+        .is_none_or(|email| user.email().to_lowercase().contains(&email.to_lowercase()));
+    let subscription_status = filter.subscription_status.as_ref().is_none_or(|status| {
+        tables.subscriptions.iter().any(|subscription| {
+            subscription.user_id() == &user.id() && subscription.status() == status
+        })
+    });
+    email
+        && subscription_status
+        && filter.status.is_none_or(|status| user.status() == status)
+        && filter.role.is_none_or(|role| user.role() == role)
+}
+
+fn identity_with(identity: &UserIdentity, id: i32, user_id: Uuid) -> UserIdentity {
+    UserIdentity::construct(
+        id,
+        user_id,
+        identity.provider().to_string(),
+        identity.subject().to_string(),
+        identity.email().to_string(),
+        identity.email_verified(),
+        identity.created_at(),
+        identity.updated_at(),
+    )
+}
+
+fn profile_with(profile: &Profile, id: i32, user_id: Uuid) -> Profile {
+    Profile::construct(
+        id,
+        user_id,
+        profile.first_name().map(|s| s.to_string()),
+        profile.last_name().map(|s| s.to_string()),
+        profile.phone().map(|s| s.to_string()),
+        profile.photo_url().map(|s| s.to_string()),
+        profile.created_at(),
+        profile.updated_at(),
+    )
+}
+
+impl UserRepository for InMemoryUserRepository {
+    async fn save(&self, user: &User, identity: &UserIdentity) -> Result<User> {
+        let mut tables = self.db.tables("users.save")?;
+        let taken = tables
+            .users
+            .iter()
+            .any(|other| other.email() == user.email())
+            || tables.identities.iter().any(|other| {
+                other.provider() == identity.provider() && other.subject() == identity.subject()
+            });
+        if taken {
+            return Err(Error::RecordAlreadyExists);
+        }
+
+        let id = Uuid::from_u128(tables.next_id() as u128);
+        let profile_id = tables.next_id();
+        let saved = User::construct(
+            id,
+            user.email().to_string(),
+            user.stripe_customer_id().map(|s| s.to_string()),
+            user.status(),
+            user.status_reason().map(|s| s.to_string()),
+            user.role(),
+            user.created_at(),
+            user.updated_at(),
+            profile_with(user.profile(), profile_id, id),
+        );
+        let identity_id = tables.next_id();
+        tables
+            .identities
+            .push(identity_with(identity, identity_id, id));
+        tables.users.push(saved.clone());
+        Ok(saved)
+    }
+
+    async fn find(&self, user_id: &Uuid) -> Result<Option<User>> {
+        let tables = self.db.tables("users.find")?;
+        Ok(tables
+            .users
+            .iter()
+            .find(|user| user.id() == *user_id)
+            .cloned())
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>> {
+        let tables = self.db.tables("users.find_by_email")?;
+        Ok(tables
+            .users
+            .iter()
+            .find(|user| user.email() == email)
+            .cloned())
+    }
+
+    async fn find_by_identity(&self, provider: &str, subject: &str) -> Result<Option<User>> {
+        let tables = self.db.tables("users.find_by_identity")?;
+        let user_id = tables
+            .identities
+            .iter()
+            .find(|identity| identity.provider() == provider && identity.subject() == subject)
+            .map(|identity| identity.user_id());
+        Ok(user_id.and_then(|id| tables.users.iter().find(|user| user.id() == id).cloned()))
+    }
+
+    async fn find_by_strip_customer_id(&self, strip_customer_id: &str) -> Result<Option<User>> {
+        let tables = self.db.tables("users.find_by_strip_customer_id")?;
+        Ok(tables
+            .users
+            .iter()
+            .find(|user| user.stripe_customer_id() == Some(strip_customer_id))
+            .cloned())
+    }
+
+    async fn find_page(
+        &self,
+        filter: &UserFilter,
+        sort: UserSort,
+        page: &PageRequest<UserCursor>,
+    ) -> Result<Page<User, UserCursor>> {
+        let tables = self.db.tables("users.find_page")?;
+        let rows = tables
+            .users
+            .iter()
+            .filter(|user| matches(&tables, user, filter))
+            .cloned()
+            .collect();
+        Ok(paginate(
+            rows,
+            sort.direction,
+            page,
+            |cursor| user_key(sort.field, cursor),
+            |user| UserCursor::from(user),
+        ))
+    }
+
+    async fn count(&self, filter: &UserFilter) -> Result<i64> {
+        let tables = self.db.tables("users.count")?;
+        let count = tables
+            .users
+            .iter()
+            .filter(|user| matches(&tables, user, filter))
+            .count();
+        Ok(count as i64)
+    }
+
+    async fn find_identities(&self, user_id: &Uuid) -> Result<Vec<UserIdentity>> {
+        let tables = self.db.tables("users.find_identities")?;
+        Ok(tables
+            .identities
+            .iter()
+            .filter(|identity| identity.user_id() == *user_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn save_identity(&self, identity: &UserIdentity) -> Result<UserIdentity> {
+        let mut tables = self.db.tables("users.save_identity")?;
+        let existing = tables.identities.iter().position(|other| {
+            other.provider() == identity.provider() && other.subject() == identity.subject()
+        });
+        if let Some(index) = existing {
+            let stored = &tables.identities[index];
+            let updated = UserIdentity::construct(
+                stored.id(),
+                stored.user_id(),
+                stored.provider().to_string(),
+                stored.subject().to_string(),
+                identity.email().to_string(),
+                identity.email_verified(),
+                stored.created_at(),
+                Some(Utc::now()),
+            );
+            tables.identities[index] = updated.clone();
+            return Ok(updated);
+        }
+        if !tables
+            .users
+            .iter()
+            .any(|user| user.id() == identity.user_id())
+        {
+            return Err(Error::NotFound(format!(
+                "User {} not found",
+                identity.user_id()
+            )));
+        }
+        let id = tables.next_id();
+        let saved = identity_with(identity, id, identity.user_id());
+        tables.identities.push(saved.clone());
+        Ok(saved)
+    }
+
+    async fn update(&self, user: &User) -> Result<User> {
+        let mut tables = self.db.tables("users.update")?;
+        let stored = tables
+            .users
+            .iter_mut()
+            .find(|stored| stored.id() == user.id())
+            .ok_or_else(|| Error::NotFound("User not found".to_string()))?;
+        // Only the columns the Postgres repository writes are changed.
+        let profile = stored.profile();
+        *stored = User::construct(
+            stored.id(),
+            stored.email().to_string(),
+            user.stripe_customer_id().map(|s| s.to_string()),
+            user.status(),
+            stored.status_reason().map(|s| s.to_string()),
+            user.role(),
+            stored.created_at(),
+            user.updated_at(),
+            profile_with(user.profile(), profile.id(), profile.user_id()),
+        );
+        Ok(stored.clone())
+    }
+
+    async fn change_status(&self, user: &User, change: &UserStatusChange) -> Result<User> {
+        let mut tables = self.db.tables("users.change_status")?;
+        let change_id = tables.next_id();
+        let stored = tables
+            .users
+            .iter_mut()
+            .find(|stored| stored.id() == user.id())
+            .ok_or_else(|| Error::NotFound("User not found".to_string()))?;
+        *stored = User::construct(
+            stored.id(),
+            stored.email().to_string(),
+            stored.stripe_customer_id().map(|s| s.to_string()),
+            user.status(),
+            user.status_reason().map(|s| s.to_string()),
+            stored.role(),
+            stored.created_at(),
+            user.updated_at(),
+            stored.profile().clone(),
+        );
+        let saved = stored.clone();
+        tables.status_changes.push(UserStatusChange::construct(
+            change_id,
+            change.user_id(),
+            change.actor_id(),
+            change.old_status(),
+            change.new_status(),
+            change.reason().map(|s| s.to_string()),
+            change.created_at(),
+        ));
+        Ok(saved)
+    }
+
+    async fn find_status_changes(&self, user_id: &Uuid) -> Result<Vec<UserStatusChange>> {
+        let tables = self.db.tables("users.find_status_changes")?;
+        Ok(tables
+            .status_changes
+            .iter()
+            .rev()
+            .filter(|change| change.user_id() == *user_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn delete(&self, user_id: &Uuid) -> Result<()> {
+        let mut tables = self.db.tables("users.delete")?;
+        tables.users.retain(|user| user.id() != *user_id);
+        tables
+            .identities
+            .retain(|identity| identity.user_id() != *user_id);
+        tables
+            .status_changes
+            .retain(|change| change.user_id() != *user_id);
+        tables
+            .subscriptions
+            .retain(|subscription| subscription.user_id() != user_id);
+        Ok(())
+    }
+}
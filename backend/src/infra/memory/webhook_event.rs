@@ -0,0 +1,87 @@
+use crate::domain::subscription::entities::WebhookEvent;
+use crate::domain::subscription::repository::WebhookEventRepository;
+use crate::domain::subscription::value_objects::webhook_event_status::WebhookEventStatus;
+use crate::infra::memory::InMemoryDatabase;
+use crate::prelude::*;
+use chrono::{DateTime, Utc};
+
+#[derive(Clone)]
+pub struct InMemoryWebhookEventRepository {
+    db: InMemoryDatabase,
+}
+impl InMemoryWebhookEventRepository {
+    pub fn new(db: InMemoryDatabase) -> Self {
+        Self { db }
+    }
+}
+
+impl WebhookEventRepository for InMemoryWebhookEventRepository {
+    async fn save(&self, event: &WebhookEvent) -> Result<WebhookEvent> {
+        let mut tables = self.db.tables("webhook_events.save")?;
+        if tables
+            .webhook_events
+            .iter()
+            .any(|other| other.id() == event.id())
+        {
+            return Err(Error::RecordAlreadyExists);
+        }
+        tables.webhook_events.push(event.clone());
+        Ok(event.clone())
+    }
+
+    async fn find(&self, id: &str) -> Result<WebhookEvent> {
+        let tables = self.db.tables("webhook_events.find")?;
+        tables
+            .webhook_events
+            .iter()
+            .find(|event| event.id() == id)
+            .cloned()
+            .ok_or_else(|| Error::NotFound(format!("Webhook event {} not found", id)))
+    }
+
+    async fn update(&self, event: &WebhookEvent) -> Result<WebhookEvent> {
+        let mut tables = self.db.tables("webhook_events.update")?;
+        let stored = tables
+            .webhook_events
+            .iter_mut()
+            .find(|stored| stored.id() == event.id())
+            .ok_or_else(|| Error::NotFound(format!("Webhook event {} not found", event.id())))?;
+        *stored = event.clone();
+        Ok(stored.clone())
+    }
+
+    async fn claim_due(
+        &self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<WebhookEvent>> {
+        let mut tables = self.db.tables("webhook_events.claim_due")?;
+        let mut due: Vec<&mut WebhookEvent> = tables
+            .webhook_events
+            .iter_mut()
+            .filter(|event| {
+                event.status() == WebhookEventStatus::Pending && event.next_attempt_at() <= now
+            })
+            .collect();
+        due.sort_by_key(|event| event.received_at());
+        Ok(due
+            .into_iter()
+            .take(limit as usize)
+            .map(|event| {
+                *event = WebhookEvent::construct(
+                    event.id().to_string(),
+                    event.event_type().to_string(),
+                    event.payload().clone(),
+                    event.status(),
+                    event.received_at(),
+                    event.processed_at(),
+                    lease_until,
+                    event.attempts(),
+                    event.last_error().map(|s| s.to_string()),
+                );
+                event.clone()
+            })
+            .collect())
+    }
+}
@@ -1,8 +1,13 @@
+pub(super) mod authenticator;
 pub mod cli;
 pub mod config;
-mod constants;
 pub mod dependencies;
 pub(super) mod firebase;
+pub(super) mod jwt;
+#[cfg(any(test, feature = "testing"))]
+pub mod memory;
+pub(super) mod oidc;
 pub(super) mod postgres;
+pub mod replay;
 pub(super) mod stripe;
 pub mod web;
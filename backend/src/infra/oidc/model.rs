@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+/// Claims read from an OIDC ID token. `aud` and `iss` are checked by the
+/// validator and not needed afterwards.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct OidcClaims {
+    pub sub: String,
+    pub exp: i64,
+    pub email: Option<String>,
+    #[serde(default)]
+    pub email_verified: bool,
+    pub name: Option<String>,
+    pub picture: Option<String>,
+}
@@ -0,0 +1,144 @@
+use crate::domain::user::entities::AuthProviderData;
+use crate::domain::user::services::Authenticator;
+use crate::infra::jwt::keys::{KeyFormat, SigningKeyCache};
+use crate::infra::oidc::model::OidcClaims;
+use crate::prelude::*;
+use chrono::DateTime;
+use jsonwebtoken::{decode, decode_header, Algorithm, Validation};
+use std::sync::Arc;
+
+const CLOCK_SKEW_SECS: u64 = 60;
+const ALGORITHMS: [Algorithm; 3] = [Algorithm::RS256, Algorithm::RS384, Algorithm::RS512];
+
+/// Verifies ID tokens of a generic OIDC provider (Auth0, Keycloak, ...)
+/// against the keys published at its JWKS url.
+#[derive(Clone)]
+pub struct OidcAuthenticatorService {
+    issuer: String,
+    audience: String,
+    keys: SigningKeyCache,
+}
+impl OidcAuthenticatorService {
+    pub fn new(issuer: &str, audience: &str, jwks_url: &str, http: Arc<reqwest::Client>) -> Self {
+        Self {
+            issuer: issuer.to_string(),
+            audience: audience.to_string(),
+            keys: SigningKeyCache::new(http, jwks_url, KeyFormat::Jwks),
+        }
+    }
+
+    fn validation(&self, algorithm: Algorithm) -> Validation {
+        let mut validation = Validation::new(algorithm);
+        validation.set_audience(&[&self.audience]);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_required_spec_claims(&["exp", "aud", "iss", "sub"]);
+        validation.leeway = CLOCK_SKEW_SECS;
+        validation
+    }
+}
+
+impl Authenticator for OidcAuthenticatorService {
+    async fn authenticate(&self, token: &str) -> Result<AuthProviderData> {
+        let header = decode_header(token).map_err(|e| Error::InvalidToken(e.to_string()))?;
+        if !ALGORITHMS.contains(&header.alg) {
+            return Err(Error::InvalidToken(
+                "Unexpected signing algorithm".to_string(),
+            ));
+        }
+        let kid = header
+            .kid
+            .ok_or_else(|| Error::InvalidToken("Token has no key id".to_string()))?;
+        let key = self.keys.get(&kid).await?;
+
+        let claims = decode::<OidcClaims>(token, &key, &self.validation(header.alg))
+            .map_err(|e| Error::InvalidToken(e.to_string()))?
+            .claims;
+
+        if claims.sub.is_empty() {
+            return Err(Error::InvalidToken("Token has no subject".to_string()));
+        }
+        let email = claims
+            .email
+            .ok_or_else(|| Error::InvalidToken("Token has no email".to_string()))?;
+        let expires_at = DateTime::from_timestamp(claims.exp, 0)
+            .ok_or_else(|| Error::InvalidToken("Invalid expiration".to_string()))?;
+
+        Ok(AuthProviderData::new(
+            self.issuer.clone(),
+            claims.sub,
+            email,
+            claims.email_verified,
+            claims.name,
+            claims.picture,
+            expires_at,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use jsonwebtoken::{encode, DecodingKey, EncodingKey, Header};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    const TEST_KEY: &str = include_str!("../../../fixtures/auth/test_key.pem");
+    const TEST_CERT: &str = include_str!("../../../fixtures/auth/test_cert.pem");
+    const ISSUER: &str = "https://auth.example.com/";
+
+    fn authenticator() -> OidcAuthenticatorService {
+        let key = DecodingKey::from_rsa_pem(TEST_CERT.as_bytes()).unwrap();
+        OidcAuthenticatorService {
+            issuer: ISSUER.to_string(),
+            audience: "backend".to_string(),
+            keys: SigningKeyCache::with_keys(HashMap::from([("test-kid".to_string(), key)])),
+        }
+    }
+
+    fn sign(claims: serde_json::Value) -> String {
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some("test-kid".to_string());
+        let key = EncodingKey::from_rsa_pem(TEST_KEY.as_bytes()).unwrap();
+        encode(&header, &claims, &key).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_authenticate() {
+        let service = authenticator();
+        let exp = Utc::now().timestamp() + 3600;
+
+        // Audiences may be a list.
+        let token = sign(json!({
+            "sub": "auth0|123",
+            "iss": ISSUER,
+            "aud": ["backend", "other"],
+            "exp": exp,
+            "email": "john@example.com",
+        }));
+        let auth = service.authenticate(&token).await.unwrap();
+        assert_eq!(auth.provider, ISSUER);
+        assert_eq!(auth.id, "auth0|123");
+        assert_eq!(auth.email, "john@example.com");
+        assert!(!auth.email_verified);
+
+        let token = sign(json!({
+            "sub": "auth0|123",
+            "iss": "https://evil.example.com/",
+            "aud": "backend",
+            "exp": exp,
+            "email": "john@example.com",
+        }));
+        let result = service.authenticate(&token).await;
+        assert!(matches!(result, Err(Error::InvalidToken(_))));
+
+        let token = sign(json!({
+            "sub": "auth0|123",
+            "iss": ISSUER,
+            "aud": "backend",
+            "exp": exp,
+        }));
+        let result = service.authenticate(&token).await;
+        assert!(matches!(result, Err(Error::InvalidToken(_))));
+    }
+}
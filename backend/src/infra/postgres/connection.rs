@@ -2,7 +2,8 @@ use crate::prelude::*;
 
 use diesel::r2d2::ConnectionManager;
 use diesel::{r2d2, PgConnection};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use tokio::task;
 
 pub type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
 pub type DbConnection = r2d2::PooledConnection<ConnectionManager<PgConnection>>;
@@ -26,3 +27,128 @@ pub fn get_connection(pool: Arc<DbPool>) -> Result<DbConnection> {
     let connection = pool.get().map_err(|e| Error::Connection(e.to_string()))?;
     Ok(connection)
 }
+
+/// Where repository queries run: on any pooled connection, or on the
+/// connection of an open transaction so they see and join its changes.
+#[derive(Clone)]
+pub enum DbHandle {
+    Pool(Arc<DbPool>),
+    Transaction(Arc<Mutex<DbConnection>>),
+}
+
+/// Runs `query` on a connection of `db` in the blocking thread pool. Diesel
+/// and r2d2 are synchronous, so calling them from an async fn would stall the
+/// worker thread, and every request scheduled on it, for as long as the query
+/// or the wait for a free connection takes.
+pub async fn with_connection<T, F>(db: &DbHandle, query: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut PgConnection) -> Result<T> + Send + 'static,
+{
+    let db = db.clone();
+    run_blocking(move || match db {
+        DbHandle::Pool(pool) => {
+            let mut connection = get_connection(pool)?;
+            query(&mut connection)
+        }
+        DbHandle::Transaction(connection) => {
+            let mut connection = connection
+                .lock()
+                .map_err(|e| Error::Connection(e.to_string()))?;
+            query(&mut connection)
+        }
+    })
+    .await
+}
+
+pub async fn run_blocking<T, F>(work: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    match task::spawn_blocking(work).await {
+        Ok(result) => result,
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(e) => {
+            tracing::error!("Database task was cancelled: {}", e);
+            Err(Error::InternalError)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, web, App, HttpResponse};
+    use diesel::RunQueryDsl;
+    use std::time::{Duration, Instant};
+
+    async fn health() -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    /// Actix tests run on a single thread, so a query blocking it would hold
+    /// back the health check until the query is done.
+    #[actix_web::test]
+    async fn test_server_responsive_during_slow_query() {
+        let app = test::init_service(App::new().route("/health", web::get().to(health))).await;
+        let started = Instant::now();
+        let slow_query = tokio::spawn(run_blocking(|| {
+            std::thread::sleep(Duration::from_millis(500));
+            Ok(())
+        }));
+        tokio::task::yield_now().await;
+
+        let request = test::TestRequest::get().uri("/health").to_request();
+        let response = test::call_service(&app, request).await;
+        assert!(response.status().is_success());
+        assert!(started.elapsed() < Duration::from_millis(250));
+        assert!(!slow_query.is_finished());
+
+        slow_query.await.unwrap().unwrap();
+    }
+
+    /// Saturates the pool with `pg_sleep` queries and checks health checks
+    /// keep being answered meanwhile. Run with
+    /// `DATABASE_URL=postgres://... cargo test -- --ignored`.
+    #[actix_web::test]
+    #[ignore = "needs a database in DATABASE_URL"]
+    async fn test_load_with_slow_queries() {
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL is not set");
+        let db = DbHandle::Pool(establish_connection(&database_url));
+        let app = test::init_service(App::new().route("/health", web::get().to(health))).await;
+
+        let slow_queries = (0..20)
+            .map(|_| {
+                let db = db.clone();
+                tokio::spawn(async move {
+                    with_connection(&db, |connection| {
+                        diesel::sql_query("SELECT pg_sleep(2)")
+                            .execute(connection)
+                            .map_err(|e| Error::Database(e.to_string()))
+                    })
+                    .await
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut slowest = Duration::ZERO;
+        for _ in 0..100 {
+            let started = Instant::now();
+            let request = test::TestRequest::get().uri("/health").to_request();
+            let response = test::call_service(&app, request).await;
+            assert!(response.status().is_success());
+            slowest = slowest.max(started.elapsed());
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(
+            slowest < Duration::from_millis(100),
+            "slowest {:?}",
+            slowest
+        );
+
+        for query in slow_queries {
+            query.await.unwrap().unwrap();
+        }
+    }
+}
@@ -2,3 +2,4 @@ pub(super) mod connection;
 pub(super) mod migrations;
 mod models;
 pub(super) mod repositories;
+pub(super) mod unit_of_work;
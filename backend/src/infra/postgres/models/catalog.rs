@@ -0,0 +1,185 @@
+use crate::domain::catalog::entities::{Price, Product};
+use crate::prelude::*;
+use crate::schema;
+use chrono::{DateTime, Utc};
+use diesel::{AsChangeset, Insertable, Queryable, Selectable};
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = schema::products)]
+pub struct CreateProductModel {
+    id: String,
+    name: String,
+    description: Option<String>,
+    active: bool,
+    last_event_at: Option<DateTime<Utc>>,
+}
+impl TryFrom<(&Product, DateTime<Utc>)> for CreateProductModel {
+    type Error = Error;
+
+    fn try_from((product, event_at): (&Product, DateTime<Utc>)) -> Result<Self> {
+        Ok(Self {
+            id: product.id().to_string(),
+            name: product.name().to_string(),
+            description: product.description().map(|s| s.to_string()),
+            active: product.is_active(),
+            last_event_at: Some(event_at),
+        })
+    }
+}
+
+#[derive(Debug, Queryable, Selectable)]
+#[diesel(table_name = schema::products, check_for_backend(diesel::pg::Pg))]
+pub struct ProductModel {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub last_event_at: Option<DateTime<Utc>>,
+}
+impl TryFrom<ProductModel> for Product {
+    type Error = Error;
+
+    fn try_from(model: ProductModel) -> Result<Self> {
+        Ok(Product::construct(
+            model.id,
+            model.name,
+            model.description,
+            model.active,
+            model.created_at,
+            model.updated_at,
+        ))
+    }
+}
+
+#[derive(Debug, Clone, AsChangeset)]
+#[diesel(table_name = schema::products, treat_none_as_null = true)]
+pub struct UpdateProductModel {
+    pub name: String,
+    pub description: Option<String>,
+    pub active: bool,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub last_event_at: Option<DateTime<Utc>>,
+}
+impl TryFrom<(&Product, DateTime<Utc>)> for UpdateProductModel {
+    type Error = Error;
+
+    fn try_from((product, event_at): (&Product, DateTime<Utc>)) -> Result<Self> {
+        Ok(Self {
+            name: product.name().to_string(),
+            description: product.description().map(|s| s.to_string()),
+            active: product.is_active(),
+            updated_at: Some(Utc::now()),
+            last_event_at: Some(event_at),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = schema::prices)]
+pub struct CreatePriceModel {
+    id: String,
+    product_id: String,
+    active: bool,
+    currency: String,
+    unit_amount: Option<i64>,
+    nickname: Option<String>,
+    recurring_interval: Option<String>,
+    recurring_interval_count: Option<i32>,
+    min_quantity: i32,
+    max_quantity: Option<i32>,
+    last_event_at: Option<DateTime<Utc>>,
+}
+impl TryFrom<(&Price, DateTime<Utc>)> for CreatePriceModel {
+    type Error = Error;
+
+    fn try_from((price, event_at): (&Price, DateTime<Utc>)) -> Result<Self> {
+        Ok(Self {
+            id: price.id().to_string(),
+            product_id: price.product_id().to_string(),
+            active: price.is_active(),
+            currency: price.currency().to_string(),
+            unit_amount: price.unit_amount(),
+            nickname: price.nickname().map(|s| s.to_string()),
+            recurring_interval: price.recurring_interval().map(|s| s.to_string()),
+            recurring_interval_count: price.recurring_interval_count(),
+            min_quantity: price.min_quantity(),
+            max_quantity: price.max_quantity(),
+            last_event_at: Some(event_at),
+        })
+    }
+}
+
+#[derive(Debug, Queryable, Selectable)]
+#[diesel(table_name = schema::prices, check_for_backend(diesel::pg::Pg))]
+pub struct PriceModel {
+    pub id: String,
+    pub product_id: String,
+    pub active: bool,
+    pub currency: String,
+    pub unit_amount: Option<i64>,
+    pub nickname: Option<String>,
+    pub recurring_interval: Option<String>,
+    pub recurring_interval_count: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub min_quantity: i32,
+    pub max_quantity: Option<i32>,
+    pub last_event_at: Option<DateTime<Utc>>,
+}
+impl TryFrom<PriceModel> for Price {
+    type Error = Error;
+
+    fn try_from(model: PriceModel) -> Result<Self> {
+        Ok(Price::construct(
+            model.id,
+            model.product_id,
+            model.active,
+            model.currency,
+            model.unit_amount,
+            model.nickname,
+            model.recurring_interval,
+            model.recurring_interval_count,
+            model.min_quantity,
+            model.max_quantity,
+            model.created_at,
+            model.updated_at,
+        ))
+    }
+}
+
+#[derive(Debug, Clone, AsChangeset)]
+#[diesel(table_name = schema::prices, treat_none_as_null = true)]
+pub struct UpdatePriceModel {
+    pub product_id: String,
+    pub active: bool,
+    pub currency: String,
+    pub unit_amount: Option<i64>,
+    pub nickname: Option<String>,
+    pub recurring_interval: Option<String>,
+    pub recurring_interval_count: Option<i32>,
+    pub min_quantity: i32,
+    pub max_quantity: Option<i32>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub last_event_at: Option<DateTime<Utc>>,
+}
+impl TryFrom<(&Price, DateTime<Utc>)> for UpdatePriceModel {
+    type Error = Error;
+
+    fn try_from((price, event_at): (&Price, DateTime<Utc>)) -> Result<Self> {
+        Ok(Self {
+            product_id: price.product_id().to_string(),
+            active: price.is_active(),
+            currency: price.currency().to_string(),
+            unit_amount: price.unit_amount(),
+            nickname: price.nickname().map(|s| s.to_string()),
+            recurring_interval: price.recurring_interval().map(|s| s.to_string()),
+            recurring_interval_count: price.recurring_interval_count(),
+            min_quantity: price.min_quantity(),
+            max_quantity: price.max_quantity(),
+            updated_at: Some(Utc::now()),
+            last_event_at: Some(event_at),
+        })
+    }
+}
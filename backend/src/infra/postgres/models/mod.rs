@@ -1,3 +1,7 @@
+pub(super) mod catalog;
 pub(super) mod profile;
 pub(super) mod subscription;
 pub(super) mod user;
+pub(super) mod user_identity;
+pub(super) mod user_status_change;
+pub(super) mod webhook_event;
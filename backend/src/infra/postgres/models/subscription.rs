@@ -19,6 +19,7 @@ pub struct CreateSubscriptionModel {
     has_used_trial: bool,
     current_period_end: Option<DateTime<Utc>>,
     cancel_at_period_end: bool,
+    last_event_at: Option<DateTime<Utc>>,
 }
 impl TryFrom<&Subscription> for CreateSubscriptionModel {
     type Error = Error;
@@ -34,6 +35,7 @@ impl TryFrom<&Subscription> for CreateSubscriptionModel {
             has_used_trial: subscription.has_used_trial(),
             current_period_end: subscription.current_period_end(),
             cancel_at_period_end: subscription.cancel_at_period_end(),
+            last_event_at: subscription.last_event_at(),
         })
     }
 }
@@ -55,6 +57,7 @@ pub struct SubscriptionModel {
     pub canceled_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
+    pub last_event_at: Option<DateTime<Utc>>,
 }
 impl TryFrom<SubscriptionModel> for Subscription {
     type Error = Error;
@@ -74,6 +77,7 @@ impl TryFrom<SubscriptionModel> for Subscription {
             model.canceled_at,
             model.created_at,
             model.updated_at,
+            model.last_event_at,
         ))
     }
 }
@@ -90,6 +94,7 @@ pub struct UpdateSubscriptionModel {
     pub cancel_at_period_end: bool,
     pub canceled_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
+    pub last_event_at: Option<DateTime<Utc>>,
 }
 impl TryFrom<&Subscription> for UpdateSubscriptionModel {
     type Error = Error;
@@ -105,6 +110,7 @@ impl TryFrom<&Subscription> for UpdateSubscriptionModel {
             cancel_at_period_end: subscription.cancel_at_period_end(),
             canceled_at: subscription.canceled_at(),
             updated_at: subscription.updated_at(),
+            last_event_at: subscription.last_event_at(),
         })
     }
 }
@@ -12,7 +12,6 @@ use uuid::Uuid;
 #[diesel(table_name = schema::users, check_for_backend(diesel::pg::Pg))]
 pub struct CreateUserModel {
     pub email: String,
-    pub firebase_id: String,
     pub status: String,
     pub role: String,
 }
@@ -22,7 +21,6 @@ impl TryFrom<&User> for CreateUserModel {
     fn try_from(user: &User) -> Result<Self> {
         Ok(Self {
             email: user.email().to_string(),
-            firebase_id: user.firebase_id().to_string(),
             status: user.status().to_string(),
             role: user.role().to_string(),
         })
@@ -34,12 +32,12 @@ impl TryFrom<&User> for CreateUserModel {
 pub struct UserModel {
     pub id: Uuid,
     pub email: String,
-    pub firebase_id: String,
     pub stripe_customer_id: Option<String>,
     pub status: String,
     pub role: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
+    pub status_reason: Option<String>,
 }
 
 impl TryFrom<(UserModel, ProfileModel)> for User {
@@ -52,9 +50,9 @@ impl TryFrom<(UserModel, ProfileModel)> for User {
         Ok(User::construct(
             user.id,
             user.email,
-            user.firebase_id,
             user.stripe_customer_id,
             UserStatus::try_from(user.status)?,
+            user.status_reason,
             Role::try_from(user.role)?,
             user.created_at,
             user.updated_at,
@@ -0,0 +1,80 @@
+use crate::domain::user::entities::UserIdentity;
+use crate::prelude::*;
+use crate::schema;
+use chrono::{DateTime, Utc};
+use diesel::{AsChangeset, Insertable, Queryable, Selectable};
+use uuid::Uuid;
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = schema::user_identities)]
+pub struct CreateUserIdentityModel {
+    pub user_id: Uuid,
+    pub provider: String,
+    pub subject: String,
+    pub email: String,
+    pub email_verified: bool,
+}
+impl TryFrom<&UserIdentity> for CreateUserIdentityModel {
+    type Error = Error;
+
+    fn try_from(identity: &UserIdentity) -> Result<Self> {
+        Ok(Self {
+            user_id: identity.user_id(),
+            provider: identity.provider().to_string(),
+            subject: identity.subject().to_string(),
+            email: identity.email().to_string(),
+            email_verified: identity.email_verified(),
+        })
+    }
+}
+
+#[derive(Debug, Queryable, Selectable)]
+#[diesel(table_name = schema::user_identities, check_for_backend(diesel::pg::Pg))]
+pub struct UserIdentityModel {
+    pub id: i32,
+    pub user_id: Uuid,
+    pub provider: String,
+    pub subject: String,
+    pub email: String,
+    pub email_verified: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<UserIdentityModel> for UserIdentity {
+    type Error = Error;
+
+    fn try_from(model: UserIdentityModel) -> Result<Self> {
+        Ok(UserIdentity::construct(
+            model.id,
+            model.user_id,
+            model.provider,
+            model.subject,
+            model.email,
+            model.email_verified,
+            model.created_at,
+            model.updated_at,
+        ))
+    }
+}
+
+/// Claims that may change between logins of the same identity.
+#[derive(Debug, AsChangeset)]
+#[diesel(table_name = schema::user_identities)]
+pub struct UpdateUserIdentityModel {
+    pub email: String,
+    pub email_verified: bool,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<&UserIdentity> for UpdateUserIdentityModel {
+    type Error = Error;
+
+    fn try_from(identity: &UserIdentity) -> Result<Self> {
+        Ok(Self {
+            email: identity.email().to_string(),
+            email_verified: identity.email_verified(),
+            updated_at: Some(Utc::now()),
+        })
+    }
+}
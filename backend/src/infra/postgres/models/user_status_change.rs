@@ -0,0 +1,58 @@
+use crate::domain::user::entities::UserStatusChange;
+use crate::domain::user::value_objects::user_status::UserStatus;
+use crate::prelude::*;
+use crate::schema;
+use chrono::{DateTime, Utc};
+use diesel::{Insertable, Queryable, Selectable};
+use uuid::Uuid;
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = schema::user_status_changes)]
+pub struct CreateUserStatusChangeModel {
+    pub user_id: Uuid,
+    pub actor_id: Option<Uuid>,
+    pub old_status: String,
+    pub new_status: String,
+    pub reason: Option<String>,
+}
+impl TryFrom<&UserStatusChange> for CreateUserStatusChangeModel {
+    type Error = Error;
+
+    fn try_from(change: &UserStatusChange) -> Result<Self> {
+        Ok(Self {
+            user_id: change.user_id(),
+            actor_id: change.actor_id(),
+            old_status: change.old_status().to_string(),
+            new_status: change.new_status().to_string(),
+            reason: change.reason().map(|s| s.to_string()),
+        })
+    }
+}
+
+#[derive(Debug, Queryable, Selectable)]
+#[diesel(table_name = schema::user_status_changes, check_for_backend(diesel::pg::Pg))]
+pub struct UserStatusChangeModel {
+    pub id: i32,
+    pub user_id: Uuid,
+    pub actor_id: Option<Uuid>,
+    pub old_status: String,
+    pub new_status: String,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TryFrom<UserStatusChangeModel> for UserStatusChange {
+    type Error = Error;
+
+    fn try_from(model: UserStatusChangeModel) -> Result<Self> {
+        Ok(UserStatusChange::construct(
+            model.id,
+            model.user_id,
+            model.actor_id,
+            UserStatus::try_from(model.old_status)?,
+            UserStatus::try_from(model.new_status)?,
+            model.reason,
+            model.created_at,
+        ))
+    }
+}
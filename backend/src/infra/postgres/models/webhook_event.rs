@@ -0,0 +1,89 @@
+use crate::domain::subscription::entities::WebhookEvent;
+use crate::domain::subscription::value_objects::webhook_event_status::WebhookEventStatus;
+use crate::prelude::*;
+use crate::schema;
+use chrono::{DateTime, Utc};
+use diesel::{AsChangeset, Insertable, Queryable, Selectable};
+use serde_json::Value;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = schema::webhook_events)]
+pub struct CreateWebhookEventModel {
+    id: String,
+    event_type: String,
+    payload: Value,
+    status: String,
+    received_at: DateTime<Utc>,
+    next_attempt_at: DateTime<Utc>,
+    attempts: i32,
+}
+impl TryFrom<&WebhookEvent> for CreateWebhookEventModel {
+    type Error = Error;
+
+    fn try_from(event: &WebhookEvent) -> Result<Self> {
+        Ok(Self {
+            id: event.id().to_string(),
+            event_type: event.event_type().to_string(),
+            payload: event.payload().clone(),
+            status: event.status().to_string(),
+            received_at: event.received_at(),
+            next_attempt_at: event.next_attempt_at(),
+            attempts: event.attempts(),
+        })
+    }
+}
+
+#[derive(Debug, Queryable, Selectable)]
+#[diesel(table_name = schema::webhook_events, check_for_backend(diesel::pg::Pg))]
+pub struct WebhookEventModel {
+    pub id: String,
+    pub event_type: String,
+    pub payload: Value,
+    pub received_at: DateTime<Utc>,
+    pub processed_at: Option<DateTime<Utc>>,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub status: String,
+    pub next_attempt_at: DateTime<Utc>,
+}
+impl TryFrom<WebhookEventModel> for WebhookEvent {
+    type Error = Error;
+
+    fn try_from(model: WebhookEventModel) -> Result<Self> {
+        Ok(WebhookEvent::construct(
+            model.id,
+            model.event_type,
+            model.payload,
+            WebhookEventStatus::from_str(&model.status)?,
+            model.received_at,
+            model.processed_at,
+            model.next_attempt_at,
+            model.attempts,
+            model.last_error,
+        ))
+    }
+}
+
+#[derive(Debug, Clone, AsChangeset)]
+#[diesel(table_name = schema::webhook_events, treat_none_as_null = true)]
+pub struct UpdateWebhookEventModel {
+    pub status: String,
+    pub processed_at: Option<DateTime<Utc>>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+}
+impl TryFrom<&WebhookEvent> for UpdateWebhookEventModel {
+    type Error = Error;
+
+    fn try_from(event: &WebhookEvent) -> Result<Self> {
+        Ok(Self {
+            status: event.status().to_string(),
+            processed_at: event.processed_at(),
+            next_attempt_at: event.next_attempt_at(),
+            attempts: event.attempts(),
+            last_error: event.last_error().map(|s| s.to_string()),
+        })
+    }
+}
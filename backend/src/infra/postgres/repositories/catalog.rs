@@ -0,0 +1,181 @@
+use crate::domain::catalog::entities::{Price, Product};
+use crate::domain::catalog::repository::CatalogRepository;
+use crate::infra::postgres::connection::{with_connection, DbHandle, DbPool};
+use crate::infra::postgres::models::catalog::{
+    CreatePriceModel, CreateProductModel, PriceModel, ProductModel, UpdatePriceModel,
+    UpdateProductModel,
+};
+use crate::prelude::*;
+use crate::schema;
+use crate::schema::prices::dsl::prices;
+use crate::schema::products::dsl::products;
+use chrono::{DateTime, Utc};
+use diesel::{BoolExpressionMethods, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct PostgresCatalogRepository {
+    db: DbHandle,
+}
+impl PostgresCatalogRepository {
+    pub fn new(pool: Arc<DbPool>) -> Self {
+        Self {
+            db: DbHandle::Pool(pool),
+        }
+    }
+}
+impl CatalogRepository for PostgresCatalogRepository {
+    async fn upsert_product(&self, product: &Product, event_at: DateTime<Utc>) -> Result<Product> {
+        let create = CreateProductModel::try_from((product, event_at))?;
+        let update = UpdateProductModel::try_from((product, event_at))?;
+
+        // The row is only overwritten if no newer event was applied to it.
+        let model = with_connection(&self.db, move |connection| {
+            let upsert = diesel::insert_into(products)
+                .values(&create)
+                .on_conflict(schema::products::id)
+                .do_update()
+                .set(&update);
+            diesel::query_dsl::methods::FilterDsl::filter(
+                upsert,
+                schema::products::last_event_at
+                    .is_null()
+                    .or(schema::products::last_event_at.le(event_at)),
+            )
+            .get_result::<ProductModel>(connection)
+            .optional()
+            .map_err(|e| Error::Database(e.to_string()))
+        })
+        .await?
+        .ok_or_else(|| {
+            Error::StaleEvent(format!(
+                "Product {} was changed after {}",
+                product.id(),
+                event_at
+            ))
+        })?;
+
+        Product::try_from(model)
+    }
+
+    async fn delete_product(&self, id: &str, event_at: DateTime<Utc>) -> Result<()> {
+        let id = id.to_string();
+
+        with_connection(&self.db, move |connection| {
+            diesel::delete(products)
+                .filter(schema::products::id.eq(id))
+                .filter(
+                    schema::products::last_event_at
+                        .is_null()
+                        .or(schema::products::last_event_at.le(event_at)),
+                )
+                .execute(connection)
+                .map_err(|e| Error::Database(e.to_string()))
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    async fn find_active_products(&self) -> Result<Vec<Product>> {
+        let models = with_connection(&self.db, |connection| {
+            products
+                .filter(schema::products::active.eq(true))
+                .order(schema::products::name.asc())
+                .load::<ProductModel>(connection)
+                .map_err(|e| Error::Database(e.to_string()))
+        })
+        .await?;
+
+        models.into_iter().map(Product::try_from).collect()
+    }
+
+    async fn upsert_price(&self, price: &Price, event_at: DateTime<Utc>) -> Result<Price> {
+        let create = CreatePriceModel::try_from((price, event_at))?;
+        let update = UpdatePriceModel::try_from((price, event_at))?;
+        let not_found = format!(
+            "Product {} of price {} not found",
+            price.product_id(),
+            price.id()
+        );
+
+        let model = with_connection(&self.db, move |connection| {
+            let upsert = diesel::insert_into(prices)
+                .values(&create)
+                .on_conflict(schema::prices::id)
+                .do_update()
+                .set(&update);
+            diesel::query_dsl::methods::FilterDsl::filter(
+                upsert,
+                schema::prices::last_event_at
+                    .is_null()
+                    .or(schema::prices::last_event_at.le(event_at)),
+            )
+            .get_result::<PriceModel>(connection)
+            .optional()
+            .map_err(|e| match e {
+                diesel::result::Error::DatabaseError(
+                    diesel::result::DatabaseErrorKind::ForeignKeyViolation,
+                    _,
+                ) => Error::NotFound(not_found),
+                other => Error::Database(other.to_string()),
+            })
+        })
+        .await?
+        .ok_or_else(|| {
+            Error::StaleEvent(format!(
+                "Price {} was changed after {}",
+                price.id(),
+                event_at
+            ))
+        })?;
+
+        Price::try_from(model)
+    }
+
+    async fn delete_price(&self, id: &str, event_at: DateTime<Utc>) -> Result<()> {
+        let id = id.to_string();
+
+        with_connection(&self.db, move |connection| {
+            diesel::delete(prices)
+                .filter(schema::prices::id.eq(id))
+                .filter(
+                    schema::prices::last_event_at
+                        .is_null()
+                        .or(schema::prices::last_event_at.le(event_at)),
+                )
+                .execute(connection)
+                .map_err(|e| Error::Database(e.to_string()))
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    async fn find_active_prices(&self) -> Result<Vec<Price>> {
+        let models = with_connection(&self.db, |connection| {
+            prices
+                .filter(schema::prices::active.eq(true))
+                .order(schema::prices::unit_amount.asc())
+                .load::<PriceModel>(connection)
+                .map_err(|e| Error::Database(e.to_string()))
+        })
+        .await?;
+
+        models.into_iter().map(Price::try_from).collect()
+    }
+
+    async fn find_prices(&self, ids: &[String]) -> Result<Vec<Price>> {
+        let ids = ids.to_vec();
+
+        let models = with_connection(&self.db, move |connection| {
+            prices
+                .filter(schema::prices::id.eq_any(ids))
+                .load::<PriceModel>(connection)
+                .map_err(|e| Error::Database(e.to_string()))
+        })
+        .await?;
+
+        models.into_iter().map(Price::try_from).collect()
+    }
+}
@@ -1,2 +1,4 @@
+pub mod catalog;
 pub mod subscription;
 pub mod user;
+pub mod webhook_event;
@@ -1,58 +1,156 @@
+use crate::domain::pagination::{Page, PageRequest, SortDirection};
 use crate::domain::subscription::entities::Subscription;
-use crate::domain::subscription::repository::SubscriptionRepository;
-use crate::infra::postgres::connection::{get_connection, DbPool};
+use crate::domain::subscription::repository::{
+    SubscriptionCursor, SubscriptionFilter, SubscriptionRepository, SubscriptionSort,
+    SubscriptionSortField,
+};
+use crate::infra::postgres::connection::{with_connection, DbConnection, DbHandle, DbPool};
 use crate::infra::postgres::models::subscription::{
     CreateSubscriptionModel, SubscriptionModel, UpdateSubscriptionModel,
 };
 use crate::prelude::*;
 use crate::schema;
 use crate::schema::subscriptions::dsl::subscriptions;
-use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
-use std::sync::Arc;
+use diesel::pg::Pg;
+use diesel::sql_types::Bool;
+use diesel::{
+    BoolExpressionMethods, BoxableExpression, ExpressionMethods, NullableExpressionMethods,
+    OptionalExtension, PgSortExpressionMethods, QueryDsl, RunQueryDsl,
+};
+use std::sync::{Arc, Mutex};
 use uuid::Uuid;
 
 #[derive(Clone)]
 pub struct PostgresSubscriptionRepository {
-    pool: Arc<DbPool>,
+    db: DbHandle,
 }
 impl PostgresSubscriptionRepository {
     pub fn new(pool: Arc<DbPool>) -> Self {
-        Self { pool }
+        Self {
+            db: DbHandle::Pool(pool),
+        }
+    }
+
+    /// A repository whose queries run inside the transaction open on
+    /// `connection`.
+    pub fn in_transaction(connection: Arc<Mutex<DbConnection>>) -> Self {
+        Self {
+            db: DbHandle::Transaction(connection),
+        }
+    }
+}
+
+type SubscriptionPredicate =
+    Box<dyn BoxableExpression<schema::subscriptions::table, Pg, SqlType = Bool>>;
+
+/// Builds the `WHERE` clause of a subscription listing.
+fn subscription_predicate(filter: &SubscriptionFilter) -> SubscriptionPredicate {
+    use schema::subscriptions::{
+        cancel_at_period_end, current_period_end, status, stripe_price_id, stripe_product_id,
+    };
+    let mut predicate: SubscriptionPredicate = Box::new(diesel::dsl::sql::<Bool>("TRUE"));
+    if let Some(value) = &filter.status {
+        predicate = Box::new(predicate.and(status.eq(value.to_string())));
+    }
+    if let Some(value) = &filter.price_id {
+        predicate = Box::new(predicate.and(stripe_price_id.eq(value.clone())));
     }
+    if let Some(value) = &filter.product_id {
+        predicate = Box::new(predicate.and(stripe_product_id.eq(value.clone())));
+    }
+    if let Some(value) = filter.cancel_at_period_end {
+        predicate = Box::new(predicate.and(cancel_at_period_end.eq(value)));
+    }
+    if let Some(before) = filter.period_ends_before {
+        predicate = Box::new(
+            predicate.and(
+                current_period_end
+                    .is_not_null()
+                    .and(current_period_end.assume_not_null().lt(before)),
+            ),
+        );
+    }
+    predicate
 }
+
+/// Rows strictly after `cursor` in the `sort` order. Missing period ends sort
+/// as if larger than any other, like Postgres does.
+fn subscription_after(
+    sort: SubscriptionSort,
+    cursor: &SubscriptionCursor,
+) -> SubscriptionPredicate {
+    use schema::subscriptions::{created_at, current_period_end, id};
+    let period_end = current_period_end.assume_not_null();
+    match (sort.field, sort.direction, cursor.current_period_end) {
+        (SubscriptionSortField::CreatedAt, SortDirection::Asc, _) => Box::new(
+            created_at
+                .gt(cursor.created_at)
+                .or(created_at.eq(cursor.created_at).and(id.gt(cursor.id))),
+        ),
+        (SubscriptionSortField::CreatedAt, SortDirection::Desc, _) => Box::new(
+            created_at
+                .lt(cursor.created_at)
+                .or(created_at.eq(cursor.created_at).and(id.lt(cursor.id))),
+        ),
+        (SubscriptionSortField::CurrentPeriodEnd, SortDirection::Asc, Some(end)) => Box::new(
+            period_end
+                .gt(end)
+                .or(period_end.eq(end).and(id.gt(cursor.id)))
+                .or(current_period_end.is_null()),
+        ),
+        (SubscriptionSortField::CurrentPeriodEnd, SortDirection::Asc, None) => {
+            Box::new(current_period_end.is_null().and(id.gt(cursor.id)))
+        }
+        (SubscriptionSortField::CurrentPeriodEnd, SortDirection::Desc, Some(end)) => Box::new(
+            period_end
+                .lt(end)
+                .or(period_end.eq(end).and(id.lt(cursor.id))),
+        ),
+        (SubscriptionSortField::CurrentPeriodEnd, SortDirection::Desc, None) => Box::new(
+            current_period_end
+                .is_null()
+                .and(id.lt(cursor.id))
+                .or(current_period_end.is_not_null()),
+        ),
+    }
+}
+
 impl SubscriptionRepository for PostgresSubscriptionRepository {
     async fn save(&self, subscription: &Subscription) -> Result<Subscription> {
         let model = CreateSubscriptionModel::try_from(subscription)?;
-        let mut connection = get_connection(self.pool.clone())?;
-
-        let model = diesel::insert_into(subscriptions)
-            .values(&model)
-            .get_result::<SubscriptionModel>(&mut connection)
-            .map_err(|e| match e {
-                diesel::result::Error::DatabaseError(
-                    diesel::result::DatabaseErrorKind::UniqueViolation,
-                    _,
-                ) => Error::RecordAlreadyExists,
-                other => Error::Database(other.to_string()),
-            })?;
+
+        let model = with_connection(&self.db, move |connection| {
+            diesel::insert_into(subscriptions)
+                .values(&model)
+                .get_result::<SubscriptionModel>(connection)
+                .map_err(|e| match e {
+                    diesel::result::Error::DatabaseError(
+                        diesel::result::DatabaseErrorKind::UniqueViolation,
+                        _,
+                    ) => Error::RecordAlreadyExists,
+                    other => Error::Database(other.to_string()),
+                })
+        })
+        .await?;
 
         let subscription = Subscription::try_from(model)?;
         Ok(subscription)
     }
     async fn find(&self, id: i32) -> Result<Subscription> {
-        let mut connection = get_connection(self.pool.clone())?;
-
-        let model = subscriptions
-            .filter(schema::subscriptions::id.eq(id))
-            .get_result::<SubscriptionModel>(&mut connection)
-            .optional()
-            .map_err(|e| match e {
-                diesel::result::Error::NotFound => {
-                    let msg = format!("Subscription {} not found", id);
-                    Error::NotFound(msg)
-                }
-                other => Error::Database(other.to_string()),
-            })?;
+        let model = with_connection(&self.db, move |connection| {
+            subscriptions
+                .filter(schema::subscriptions::id.eq(id))
+                .get_result::<SubscriptionModel>(connection)
+                .optional()
+                .map_err(|e| match e {
+                    diesel::result::Error::NotFound => {
+                        let msg = format!("Subscription {} not found", id);
+                        Error::NotFound(msg)
+                    }
+                    other => Error::Database(other.to_string()),
+                })
+        })
+        .await?;
 
         match model {
             Some(model) => {
@@ -63,22 +161,25 @@ impl SubscriptionRepository for PostgresSubscriptionRepository {
         }
     }
     async fn find_by_strip_subscription_id(&self, subscription_id: &str) -> Result<Subscription> {
-        let mut connection = get_connection(self.pool.clone())?;
-
-        let model = subscriptions
-            .filter(schema::subscriptions::stripe_subscription_id.eq(subscription_id))
-            .get_result::<SubscriptionModel>(&mut connection)
-            .optional()
-            .map_err(|e| match e {
-                diesel::result::Error::NotFound => {
-                    let msg = format!(
-                        "Subscription with stripe subscription id {} not found",
-                        subscription_id
-                    );
-                    Error::NotFound(msg)
-                }
-                other => Error::Database(other.to_string()),
-            })?;
+        let subscription_id = subscription_id.to_string();
+
+        let model = with_connection(&self.db, move |connection| {
+            subscriptions
+                .filter(schema::subscriptions::stripe_subscription_id.eq(&subscription_id))
+                .get_result::<SubscriptionModel>(connection)
+                .optional()
+                .map_err(|e| match e {
+                    diesel::result::Error::NotFound => {
+                        let msg = format!(
+                            "Subscription with stripe subscription id {} not found",
+                            subscription_id
+                        );
+                        Error::NotFound(msg)
+                    }
+                    other => Error::Database(other.to_string()),
+                })
+        })
+        .await?;
 
         match model {
             Some(model) => {
@@ -89,39 +190,65 @@ impl SubscriptionRepository for PostgresSubscriptionRepository {
         }
     }
     async fn find_by_user_id(&self, user_id: &Uuid) -> Result<Subscription> {
-        let mut connection = get_connection(self.pool.clone())?;
-
-        let models = subscriptions
-            .filter(schema::subscriptions::user_id.eq(user_id))
-            .get_result::<SubscriptionModel>(&mut connection)
-            .map_err(|e| match e {
-                diesel::result::Error::NotFound => {
-                    let msg = format!("Subscription with user id {} not found", user_id);
-                    Error::NotFound(msg)
-                }
-                other => Error::Database(other.to_string()),
-            })?;
+        let user_id = *user_id;
+
+        let models = with_connection(&self.db, move |connection| {
+            subscriptions
+                .filter(schema::subscriptions::user_id.eq(user_id))
+                .get_result::<SubscriptionModel>(connection)
+                .map_err(|e| match e {
+                    diesel::result::Error::NotFound => {
+                        let msg = format!("Subscription with user id {} not found", user_id);
+                        Error::NotFound(msg)
+                    }
+                    other => Error::Database(other.to_string()),
+                })
+        })
+        .await?;
 
         let subscription = Subscription::try_from(models)?;
         Ok(subscription)
     }
+    async fn find_by_user_id_for_update(&self, user_id: &Uuid) -> Result<Subscription> {
+        let user_id = *user_id;
+
+        let model = with_connection(&self.db, move |connection| {
+            subscriptions
+                .filter(schema::subscriptions::user_id.eq(user_id))
+                .for_update()
+                .get_result::<SubscriptionModel>(connection)
+                .map_err(|e| match e {
+                    diesel::result::Error::NotFound => {
+                        let msg = format!("Subscription with user id {} not found", user_id);
+                        Error::NotFound(msg)
+                    }
+                    other => Error::Database(other.to_string()),
+                })
+        })
+        .await?;
+
+        Subscription::try_from(model)
+    }
     async fn find_by_customer_id(&self, customer_id: &str) -> Result<Subscription> {
-        let mut connection = get_connection(self.pool.clone())?;
-
-        let model = subscriptions
-            .filter(schema::subscriptions::stripe_customer_id.eq(customer_id))
-            .get_result::<SubscriptionModel>(&mut connection)
-            .optional()
-            .map_err(|e| match e {
-                diesel::result::Error::NotFound => {
-                    let msg = format!(
-                        "Subscription with stripe customer id {} not found",
-                        customer_id
-                    );
-                    Error::NotFound(msg)
-                }
-                other => Error::Database(other.to_string()),
-            })?;
+        let customer_id = customer_id.to_string();
+
+        let model = with_connection(&self.db, move |connection| {
+            subscriptions
+                .filter(schema::subscriptions::stripe_customer_id.eq(&customer_id))
+                .get_result::<SubscriptionModel>(connection)
+                .optional()
+                .map_err(|e| match e {
+                    diesel::result::Error::NotFound => {
+                        let msg = format!(
+                            "Subscription with stripe customer id {} not found",
+                            customer_id
+                        );
+                        Error::NotFound(msg)
+                    }
+                    other => Error::Database(other.to_string()),
+                })
+        })
+        .await?;
 
         match model {
             Some(model) => {
@@ -131,40 +258,104 @@ impl SubscriptionRepository for PostgresSubscriptionRepository {
             None => Err(Error::NotFound("Subscription {} not found".to_string())),
         }
     }
+    async fn find_page(
+        &self,
+        filter: &SubscriptionFilter,
+        sort: SubscriptionSort,
+        page: &PageRequest<SubscriptionCursor>,
+    ) -> Result<Page<Subscription, SubscriptionCursor>> {
+        use schema::subscriptions::{created_at, current_period_end, id};
+        let filter = filter.clone();
+        let page = page.clone();
+        let limit = page.limit;
+
+        let models = with_connection(&self.db, move |connection| {
+            let mut query = subscriptions
+                .filter(subscription_predicate(&filter))
+                .into_boxed();
+            if let Some(cursor) = &page.after {
+                query = query.filter(subscription_after(sort, cursor));
+            }
+            query = match (sort.field, sort.direction) {
+                (SubscriptionSortField::CreatedAt, SortDirection::Asc) => {
+                    query.order((created_at.asc(), id.asc()))
+                }
+                (SubscriptionSortField::CreatedAt, SortDirection::Desc) => {
+                    query.order((created_at.desc(), id.desc()))
+                }
+                (SubscriptionSortField::CurrentPeriodEnd, SortDirection::Asc) => {
+                    query.order((current_period_end.asc().nulls_last(), id.asc()))
+                }
+                (SubscriptionSortField::CurrentPeriodEnd, SortDirection::Desc) => {
+                    query.order((current_period_end.desc().nulls_first(), id.desc()))
+                }
+            };
+            query
+                .limit(page.limit + 1)
+                .load::<SubscriptionModel>(connection)
+                .map_err(|e| Error::Database(e.to_string()))
+        })
+        .await?;
+
+        let result = models
+            .into_iter()
+            .map(Subscription::try_from)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Page::from_rows(result, limit, |subscription| {
+            SubscriptionCursor::from(subscription)
+        }))
+    }
+
+    async fn count(&self, filter: &SubscriptionFilter) -> Result<i64> {
+        let filter = filter.clone();
+
+        with_connection(&self.db, move |connection| {
+            subscriptions
+                .filter(subscription_predicate(&filter))
+                .count()
+                .get_result(connection)
+                .map_err(|e| Error::Database(e.to_string()))
+        })
+        .await
+    }
 
     async fn update(&self, subscription: &Subscription) -> Result<Subscription> {
         let model = UpdateSubscriptionModel::try_from(subscription)?;
-        let mut connection = get_connection(self.pool.clone())?;
-
-        let model = diesel::update(subscriptions)
-            .filter(schema::subscriptions::id.eq(subscription.id()))
-            .set(&model)
-            .get_result::<SubscriptionModel>(&mut connection)
-            .map_err(|e| match e {
-                diesel::result::Error::NotFound => {
-                    let msg = format!("Subscription {} not found", subscription.id());
-                    Error::NotFound(msg)
-                }
-                other => Error::Database(other.to_string()),
-            })?;
+        let id = subscription.id();
+
+        let model = with_connection(&self.db, move |connection| {
+            diesel::update(subscriptions)
+                .filter(schema::subscriptions::id.eq(id))
+                .set(&model)
+                .get_result::<SubscriptionModel>(connection)
+                .map_err(|e| match e {
+                    diesel::result::Error::NotFound => {
+                        let msg = format!("Subscription {} not found", id);
+                        Error::NotFound(msg)
+                    }
+                    other => Error::Database(other.to_string()),
+                })
+        })
+        .await?;
 
         let subscription = Subscription::try_from(model)?;
         Ok(subscription)
     }
 
     async fn delete(&self, id: i32) -> Result<()> {
-        let mut connection = get_connection(self.pool.clone())?;
-
-        diesel::delete(subscriptions)
-            .filter(schema::subscriptions::id.eq(id))
-            .execute(&mut connection)
-            .map_err(|e| match e {
-                diesel::result::Error::NotFound => {
-                    let msg = format!("Subscription {} not found", id);
-                    Error::NotFound(msg)
-                }
-                other => Error::Database(other.to_string()),
-            })?;
+        with_connection(&self.db, move |connection| {
+            diesel::delete(subscriptions)
+                .filter(schema::subscriptions::id.eq(id))
+                .execute(connection)
+                .map_err(|e| match e {
+                    diesel::result::Error::NotFound => {
+                        let msg = format!("Subscription {} not found", id);
+                        Error::NotFound(msg)
+                    }
+                    other => Error::Database(other.to_string()),
+                })
+        })
+        .await?;
 
         Ok(())
     }
@@ -1,77 +1,174 @@
-use crate::domain::user::entities::User;
-use crate::domain::user::repositories::UserRepository;
-use crate::infra::postgres::connection::{get_connection, DbPool};
+use crate::domain::pagination::{Page, PageRequest, SortDirection};
+use crate::domain::user::entities::{User, UserIdentity, UserStatusChange};
+use crate::domain::user::repositories::{
+    UserCursor, UserFilter, UserRepository, UserSort, UserSortField,
+};
+use crate::infra::postgres::connection::{with_connection, DbConnection, DbHandle, DbPool};
 use crate::infra::postgres::models::profile::{
     CreateProfileModel, ProfileModel, UpdateProfileModel,
 };
 use crate::infra::postgres::models::user::{CreateUserModel, UpdateUserModel, UserModel};
+use crate::infra::postgres::models::user_identity::{
+    CreateUserIdentityModel, UpdateUserIdentityModel, UserIdentityModel,
+};
+use crate::infra::postgres::models::user_status_change::{
+    CreateUserStatusChangeModel, UserStatusChangeModel,
+};
 use crate::prelude::*;
 use crate::schema;
 use crate::schema::profiles::dsl::profiles;
+use crate::schema::subscriptions::dsl::subscriptions;
+use crate::schema::user_identities::dsl::user_identities;
+use crate::schema::user_status_changes::dsl::user_status_changes;
 use crate::schema::users::dsl::users;
+use diesel::dsl::InnerJoinQuerySource;
+use diesel::pg::Pg;
+use diesel::sql_types::Bool;
 use diesel::ExpressionMethods;
-use diesel::{OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper};
-use std::sync::Arc;
+use diesel::{
+    BoolExpressionMethods, BoxableExpression, Connection, OptionalExtension,
+    PgTextExpressionMethods, QueryDsl, RunQueryDsl, SelectableHelper,
+};
+use std::sync::{Arc, Mutex};
 use uuid::Uuid;
 
 #[derive(Clone)]
 pub struct PostgresUserRepository {
-    pool: Arc<DbPool>,
+    db: DbHandle,
 }
 
 impl PostgresUserRepository {
     pub fn new(pool: Arc<DbPool>) -> Self {
-        Self { pool }
+        Self {
+            db: DbHandle::Pool(pool),
+        }
+    }
+
+    /// A repository whose queries run inside the transaction open on
+    /// `connection`.
+    pub fn in_transaction(connection: Arc<Mutex<DbConnection>>) -> Self {
+        Self {
+            db: DbHandle::Transaction(connection),
+        }
     }
 }
 
-impl UserRepository for PostgresUserRepository {
-    async fn save(&self, user: &User) -> Result<User> {
-        let mut connection = get_connection(self.pool.clone())?;
+type UserSource = InnerJoinQuerySource<schema::users::table, schema::profiles::table>;
+type UserPredicate = Box<dyn BoxableExpression<UserSource, Pg, SqlType = Bool>>;
+
+/// Builds the `WHERE` clause of a user listing.
+fn user_predicate(filter: &UserFilter) -> UserPredicate {
+    let mut predicate: UserPredicate = Box::new(diesel::dsl::sql::<Bool>("TRUE"));
+    if let Some(email) = &filter.email {
+        let pattern = format!("%{}%", escape_like(email));
+        predicate = Box::new(predicate.and(schema::users::email.ilike(pattern)));
+    }
+    if let Some(status) = filter.status {
+        predicate = Box::new(predicate.and(schema::users::status.eq(status.to_string())));
+    }
+    if let Some(role) = filter.role {
+        predicate = Box::new(predicate.and(schema::users::role.eq(role.to_string())));
+    }
+    if let Some(status) = &filter.subscription_status {
+        let subscribed = subscriptions
+            .select(schema::subscriptions::user_id)
+            .filter(schema::subscriptions::status.eq(status.to_string()));
+        predicate = Box::new(predicate.and(schema::users::id.eq_any(subscribed)));
+    }
+    predicate
+}
+
+/// Rows strictly after `cursor` in the `sort` order.
+fn user_after(sort: UserSort, cursor: &UserCursor) -> UserPredicate {
+    use schema::users::{created_at, email, id};
+    match (sort.field, sort.direction) {
+        (UserSortField::CreatedAt, SortDirection::Asc) => Box::new(
+            created_at
+                .gt(cursor.created_at)
+                .or(created_at.eq(cursor.created_at).and(id.gt(cursor.id))),
+        ),
+        (UserSortField::CreatedAt, SortDirection::Desc) => Box::new(
+            created_at
+                .lt(cursor.created_at)
+                .or(created_at.eq(cursor.created_at).and(id.lt(cursor.id))),
+        ),
+        (UserSortField::Email, SortDirection::Asc) => Box::new(
+            email
+                .gt(cursor.email.clone())
+                .or(email.eq(cursor.email.clone()).and(id.gt(cursor.id))),
+        ),
+        (UserSortField::Email, SortDirection::Desc) => Box::new(
+            email
+                .lt(cursor.email.clone())
+                .or(email.eq(cursor.email.clone()).and(id.lt(cursor.id))),
+        ),
+    }
+}
+
+/// Makes user input match literally inside a `LIKE` pattern.
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
 
+impl UserRepository for PostgresUserRepository {
+    async fn save(&self, user: &User, identity: &UserIdentity) -> Result<User> {
         let new_user = CreateUserModel::try_from(user)?;
         let mut new_profile = CreateProfileModel::try_from(user.profile())?;
+        let mut new_identity = CreateUserIdentityModel::try_from(identity)?;
 
-        let response = connection
-            .build_transaction()
-            .run::<_, diesel::result::Error, _>(|conn| {
-                let user = diesel::insert_into(users)
-                    .values(&new_user)
-                    .returning(UserModel::as_select())
-                    .get_result(conn)?;
-                new_profile.user_id = user.id;
-                let profile = diesel::insert_into(profiles)
-                    .values(&new_profile)
-                    .returning(ProfileModel::as_select())
-                    .get_result(conn)?;
-                Ok((user, profile))
-            })
-            .map_err(|e| match e {
-                diesel::result::Error::DatabaseError(
-                    diesel::result::DatabaseErrorKind::UniqueViolation,
-                    _,
-                ) => Error::RecordAlreadyExists,
-                other => Error::Database(other.to_string()),
-            })?;
+        let response = with_connection(&self.db, move |connection| {
+            // Becomes a savepoint when run inside a unit of work.
+            connection
+                .transaction::<_, diesel::result::Error, _>(|conn| {
+                    let user = diesel::insert_into(users)
+                        .values(&new_user)
+                        .returning(UserModel::as_select())
+                        .get_result(conn)?;
+                    new_profile.user_id = user.id;
+                    let profile = diesel::insert_into(profiles)
+                        .values(&new_profile)
+                        .returning(ProfileModel::as_select())
+                        .get_result(conn)?;
+                    new_identity.user_id = user.id;
+                    diesel::insert_into(user_identities)
+                        .values(&new_identity)
+                        .execute(conn)?;
+                    Ok((user, profile))
+                })
+                .map_err(|e| match e {
+                    diesel::result::Error::DatabaseError(
+                        diesel::result::DatabaseErrorKind::UniqueViolation,
+                        _,
+                    ) => Error::RecordAlreadyExists,
+                    other => Error::Database(other.to_string()),
+                })
+        })
+        .await?;
 
         let user = User::try_from(response)?;
         Ok(user)
     }
 
     async fn find(&self, user_id: &Uuid) -> Result<Option<User>> {
-        let mut connection = get_connection(self.pool.clone())?;
-        let user = users
-            .inner_join(profiles)
-            .filter(schema::users::id.eq(user_id))
-            .get_result::<(UserModel, ProfileModel)>(&mut connection)
-            .optional()
-            .map_err(|e| match e {
-                diesel::result::Error::NotFound => {
-                    let msg = format!("User {} not found", user_id);
-                    Error::NotFound(msg)
-                }
-                other => Error::Database(other.to_string()),
-            })?;
+        let user_id = *user_id;
+        let user = with_connection(&self.db, move |connection| {
+            users
+                .inner_join(profiles)
+                .filter(schema::users::id.eq(user_id))
+                .get_result::<(UserModel, ProfileModel)>(connection)
+                .optional()
+                .map_err(|e| match e {
+                    diesel::result::Error::NotFound => {
+                        let msg = format!("User {} not found", user_id);
+                        Error::NotFound(msg)
+                    }
+                    other => Error::Database(other.to_string()),
+                })
+        })
+        .await?;
 
         let result = user
             .map(|(user, profile)| {
@@ -83,19 +180,22 @@ impl UserRepository for PostgresUserRepository {
     }
 
     async fn find_by_email(&self, email: &str) -> Result<Option<User>> {
-        let mut connection = get_connection(self.pool.clone())?;
-        let user = users
-            .inner_join(profiles)
-            .filter(schema::users::email.eq(email))
-            .get_result::<(UserModel, ProfileModel)>(&mut connection)
-            .optional()
-            .map_err(|e| match e {
-                diesel::result::Error::NotFound => {
-                    let msg = format!("User with email {} not found", email);
-                    Error::NotFound(msg)
-                }
-                other => Error::Database(other.to_string()),
-            })?;
+        let email = email.to_string();
+        let user = with_connection(&self.db, move |connection| {
+            users
+                .inner_join(profiles)
+                .filter(schema::users::email.eq(&email))
+                .get_result::<(UserModel, ProfileModel)>(connection)
+                .optional()
+                .map_err(|e| match e {
+                    diesel::result::Error::NotFound => {
+                        let msg = format!("User with email {} not found", email);
+                        Error::NotFound(msg)
+                    }
+                    other => Error::Database(other.to_string()),
+                })
+        })
+        .await?;
 
         let result = user
             .map(|(user, profile)| {
@@ -106,47 +206,45 @@ impl UserRepository for PostgresUserRepository {
         Ok(result)
     }
 
-    async fn find_by_firebase_id(&self, firebase_id: &str) -> Result<Option<User>> {
-        let mut connection = get_connection(self.pool.clone())?;
-        let user = users
-            .inner_join(profiles)
-            .filter(schema::users::firebase_id.eq(firebase_id))
-            .get_result::<(UserModel, ProfileModel)>(&mut connection)
-            .optional()
-            .map_err(|e| match e {
-                diesel::result::Error::NotFound => {
-                    let msg = format!("User with firebase id {} not found", firebase_id);
-                    Error::NotFound(msg)
-                }
-                other => Error::Database(other.to_string()),
-            })?;
+    async fn find_by_identity(&self, provider: &str, subject: &str) -> Result<Option<User>> {
+        let (provider, subject) = (provider.to_string(), subject.to_string());
+        let user = with_connection(&self.db, move |connection| {
+            users
+                .inner_join(profiles)
+                .inner_join(user_identities)
+                .filter(schema::user_identities::provider.eq(provider))
+                .filter(schema::user_identities::subject.eq(subject))
+                .select((UserModel::as_select(), ProfileModel::as_select()))
+                .get_result::<(UserModel, ProfileModel)>(connection)
+                .optional()
+                .map_err(|e| Error::Database(e.to_string()))
+        })
+        .await?;
 
-        let result = user
-            .map(|(user, profile)| {
-                
-                User::try_from((user, profile))
-            })
-            .transpose()?;
+        let result = user.map(User::try_from).transpose()?;
         Ok(result)
     }
 
     async fn find_by_strip_customer_id(&self, strip_customer_id: &str) -> Result<Option<User>> {
-        let mut connection = get_connection(self.pool.clone())?;
-        let user = users
-            .inner_join(profiles)
-            .filter(schema::users::stripe_customer_id.eq(strip_customer_id))
-            .get_result::<(UserModel, ProfileModel)>(&mut connection)
-            .optional()
-            .map_err(|e| match e {
-                diesel::result::Error::NotFound => {
-                    let msg = format!(
-                        "User with stripe customer id {} not found",
-                        strip_customer_id
-                    );
-                    Error::NotFound(msg)
-                }
-                other => Error::Database(other.to_string()),
-            })?;
+        let strip_customer_id = strip_customer_id.to_string();
+        let user = with_connection(&self.db, move |connection| {
+            users
+                .inner_join(profiles)
+                .filter(schema::users::stripe_customer_id.eq(&strip_customer_id))
+                .get_result::<(UserModel, ProfileModel)>(connection)
+                .optional()
+                .map_err(|e| match e {
+                    diesel::result::Error::NotFound => {
+                        let msg = format!(
+                            "User with stripe customer id {} not found",
+                            strip_customer_id
+                        );
+                        Error::NotFound(msg)
+                    }
+                    other => Error::Database(other.to_string()),
+                })
+        })
+        .await?;
 
         let result = user
             .map(|(user, profile)| {
@@ -157,37 +255,200 @@ impl UserRepository for PostgresUserRepository {
         Ok(result)
     }
 
+    async fn find_page(
+        &self,
+        filter: &UserFilter,
+        sort: UserSort,
+        page: &PageRequest<UserCursor>,
+    ) -> Result<Page<User, UserCursor>> {
+        use schema::users::{created_at, email, id};
+        let filter = filter.clone();
+        let page = page.clone();
+        let limit = page.limit;
+
+        let result = with_connection(&self.db, move |connection| {
+            let mut query = users
+                .inner_join(profiles)
+                .filter(user_predicate(&filter))
+                .into_boxed();
+            if let Some(cursor) = &page.after {
+                query = query.filter(user_after(sort, cursor));
+            }
+            query = match (sort.field, sort.direction) {
+                (UserSortField::CreatedAt, SortDirection::Asc) => {
+                    query.order((created_at.asc(), id.asc()))
+                }
+                (UserSortField::CreatedAt, SortDirection::Desc) => {
+                    query.order((created_at.desc(), id.desc()))
+                }
+                (UserSortField::Email, SortDirection::Asc) => query.order((email.asc(), id.asc())),
+                (UserSortField::Email, SortDirection::Desc) => {
+                    query.order((email.desc(), id.desc()))
+                }
+            };
+            query
+                .limit(page.limit + 1)
+                .load::<(UserModel, ProfileModel)>(connection)
+                .map_err(|e| Error::Database(e.to_string()))
+        })
+        .await?;
+
+        let result = result
+            .into_iter()
+            .map(User::try_from)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Page::from_rows(result, limit, |user| {
+            UserCursor::from(user)
+        }))
+    }
+
+    async fn count(&self, filter: &UserFilter) -> Result<i64> {
+        let filter = filter.clone();
+        with_connection(&self.db, move |connection| {
+            users
+                .inner_join(profiles)
+                .filter(user_predicate(&filter))
+                .count()
+                .get_result(connection)
+                .map_err(|e| Error::Database(e.to_string()))
+        })
+        .await
+    }
+
+    async fn find_identities(&self, user_id: &Uuid) -> Result<Vec<UserIdentity>> {
+        let user_id = *user_id;
+        let identities = with_connection(&self.db, move |connection| {
+            user_identities
+                .filter(schema::user_identities::user_id.eq(user_id))
+                .order(schema::user_identities::created_at.asc())
+                .load::<UserIdentityModel>(connection)
+                .map_err(|e| Error::Database(e.to_string()))
+        })
+        .await?;
+
+        identities.into_iter().map(UserIdentity::try_from).collect()
+    }
+
+    async fn save_identity(&self, identity: &UserIdentity) -> Result<UserIdentity> {
+        let create = CreateUserIdentityModel::try_from(identity)?;
+        let update = UpdateUserIdentityModel::try_from(identity)?;
+        let user_id = identity.user_id();
+
+        let model = with_connection(&self.db, move |connection| {
+            diesel::insert_into(user_identities)
+                .values(&create)
+                .on_conflict((
+                    schema::user_identities::provider,
+                    schema::user_identities::subject,
+                ))
+                .do_update()
+                .set(&update)
+                .get_result::<UserIdentityModel>(connection)
+                .map_err(|e| match e {
+                    diesel::result::Error::DatabaseError(
+                        diesel::result::DatabaseErrorKind::ForeignKeyViolation,
+                        _,
+                    ) => Error::NotFound(format!("User {} not found", user_id)),
+                    other => Error::Database(other.to_string()),
+                })
+        })
+        .await?;
+
+        UserIdentity::try_from(model)
+    }
+
     async fn update(&self, user: &User) -> Result<User> {
         let user_model = UpdateUserModel::try_from(user)?;
         let profile_model = UpdateProfileModel::try_from(user.profile())?;
+        let user_id = user.id();
 
-        let mut connection = get_connection(self.pool.clone())?;
-
-        let result = connection
-            .build_transaction()
-            .run::<_, diesel::result::Error, _>(|conn| {
-                let updated_user = diesel::update(users.find(user.id()))
-                    .set(&user_model)
-                    .get_result::<UserModel>(conn)?;
-                let updated_profile =
-                    diesel::update(profiles.filter(schema::profiles::user_id.eq(user.id())))
-                        .set(&profile_model)
-                        .get_result::<ProfileModel>(conn)?;
-                Ok((updated_user, updated_profile))
-            })
-            .map_err(|e| match e {
-                diesel::result::Error::NotFound => Error::NotFound("User not found".to_string()),
-                other => Error::Database(other.to_string()),
-            })?;
+        let result = with_connection(&self.db, move |connection| {
+            // Becomes a savepoint when run inside a unit of work.
+            connection
+                .transaction::<_, diesel::result::Error, _>(|conn| {
+                    let updated_user = diesel::update(users.find(user_id))
+                        .set(&user_model)
+                        .get_result::<UserModel>(conn)?;
+                    let updated_profile =
+                        diesel::update(profiles.filter(schema::profiles::user_id.eq(user_id)))
+                            .set(&profile_model)
+                            .get_result::<ProfileModel>(conn)?;
+                    Ok((updated_user, updated_profile))
+                })
+                .map_err(|e| match e {
+                    diesel::result::Error::NotFound => {
+                        Error::NotFound("User not found".to_string())
+                    }
+                    other => Error::Database(other.to_string()),
+                })
+        })
+        .await?;
         let user = User::try_from(result)?;
         Ok(user)
     }
 
+    async fn change_status(&self, user: &User, change: &UserStatusChange) -> Result<User> {
+        let new_change = CreateUserStatusChangeModel::try_from(change)?;
+        let user_id = user.id();
+        let status = user.status().to_string();
+        let status_reason = user.status_reason().map(|reason| reason.to_string());
+        let updated_at = user.updated_at();
+
+        let result = with_connection(&self.db, move |connection| {
+            // Becomes a savepoint when run inside a unit of work.
+            connection
+                .transaction::<_, diesel::result::Error, _>(|conn| {
+                    let updated_user = diesel::update(users.find(user_id))
+                        .set((
+                            schema::users::status.eq(status),
+                            schema::users::status_reason.eq(status_reason),
+                            schema::users::updated_at.eq(updated_at),
+                        ))
+                        .get_result::<UserModel>(conn)?;
+                    diesel::insert_into(user_status_changes)
+                        .values(&new_change)
+                        .execute(conn)?;
+                    let profile = profiles
+                        .filter(schema::profiles::user_id.eq(user_id))
+                        .get_result::<ProfileModel>(conn)?;
+                    Ok((updated_user, profile))
+                })
+                .map_err(|e| match e {
+                    diesel::result::Error::NotFound => {
+                        Error::NotFound("User not found".to_string())
+                    }
+                    other => Error::Database(other.to_string()),
+                })
+        })
+        .await?;
+        User::try_from(result)
+    }
+
+    async fn find_status_changes(&self, user_id: &Uuid) -> Result<Vec<UserStatusChange>> {
+        let user_id = *user_id;
+        let changes = with_connection(&self.db, move |connection| {
+            user_status_changes
+                .filter(schema::user_status_changes::user_id.eq(user_id))
+                .order(schema::user_status_changes::created_at.desc())
+                .load::<UserStatusChangeModel>(connection)
+                .map_err(|e| Error::Database(e.to_string()))
+        })
+        .await?;
+
+        changes
+            .into_iter()
+            .map(UserStatusChange::try_from)
+            .collect()
+    }
+
     async fn delete(&self, user_id: &Uuid) -> Result<()> {
-        let mut connection = get_connection(self.pool.clone())?;
-        diesel::delete(users.find(user_id))
-            .execute(&mut connection)
-            .map_err(|e| Error::Database(e.to_string()))?;
+        let user_id = *user_id;
+        with_connection(&self.db, move |connection| {
+            diesel::delete(users.find(user_id))
+                .execute(connection)
+                .map_err(|e| Error::Database(e.to_string()))
+        })
+        .await?;
         Ok(())
     }
 }
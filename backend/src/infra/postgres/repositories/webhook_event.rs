@@ -0,0 +1,121 @@
+use crate::domain::subscription::entities::WebhookEvent;
+use crate::domain::subscription::repository::WebhookEventRepository;
+use crate::domain::subscription::value_objects::webhook_event_status::WebhookEventStatus;
+use crate::infra::postgres::connection::{with_connection, DbHandle, DbPool};
+use crate::infra::postgres::models::webhook_event::{
+    CreateWebhookEventModel, UpdateWebhookEventModel, WebhookEventModel,
+};
+use crate::prelude::*;
+use crate::schema;
+use crate::schema::webhook_events::dsl::webhook_events;
+use chrono::{DateTime, Utc};
+use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct PostgresWebhookEventRepository {
+    db: DbHandle,
+}
+impl PostgresWebhookEventRepository {
+    pub fn new(pool: Arc<DbPool>) -> Self {
+        Self {
+            db: DbHandle::Pool(pool),
+        }
+    }
+}
+impl WebhookEventRepository for PostgresWebhookEventRepository {
+    async fn save(&self, event: &WebhookEvent) -> Result<WebhookEvent> {
+        let model = CreateWebhookEventModel::try_from(event)?;
+
+        let model = with_connection(&self.db, move |connection| {
+            diesel::insert_into(webhook_events)
+                .values(&model)
+                .get_result::<WebhookEventModel>(connection)
+                .map_err(|e| match e {
+                    diesel::result::Error::DatabaseError(
+                        diesel::result::DatabaseErrorKind::UniqueViolation,
+                        _,
+                    ) => Error::RecordAlreadyExists,
+                    other => Error::Database(other.to_string()),
+                })
+        })
+        .await?;
+
+        WebhookEvent::try_from(model)
+    }
+
+    async fn find(&self, id: &str) -> Result<WebhookEvent> {
+        let event_id = id.to_string();
+
+        let model = with_connection(&self.db, move |connection| {
+            webhook_events
+                .filter(schema::webhook_events::id.eq(event_id))
+                .get_result::<WebhookEventModel>(connection)
+                .optional()
+                .map_err(|e| Error::Database(e.to_string()))
+        })
+        .await?;
+
+        match model {
+            Some(model) => WebhookEvent::try_from(model),
+            None => Err(Error::NotFound(format!("Webhook event {} not found", id))),
+        }
+    }
+
+    async fn update(&self, event: &WebhookEvent) -> Result<WebhookEvent> {
+        let model = UpdateWebhookEventModel::try_from(event)?;
+        let event_id = event.id().to_string();
+
+        let model = with_connection(&self.db, move |connection| {
+            diesel::update(webhook_events)
+                .filter(schema::webhook_events::id.eq(&event_id))
+                .set(&model)
+                .get_result::<WebhookEventModel>(connection)
+                .map_err(|e| match e {
+                    diesel::result::Error::NotFound => {
+                        Error::NotFound(format!("Webhook event {} not found", event_id))
+                    }
+                    other => Error::Database(other.to_string()),
+                })
+        })
+        .await?;
+
+        WebhookEvent::try_from(model)
+    }
+
+    async fn claim_due(
+        &self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<WebhookEvent>> {
+        let mut models = with_connection(&self.db, move |connection| {
+            connection
+                .build_transaction()
+                .run::<_, diesel::result::Error, _>(|conn| {
+                    let ids = webhook_events
+                        .select(schema::webhook_events::id)
+                        .filter(
+                            schema::webhook_events::status
+                                .eq(WebhookEventStatus::Pending.to_string()),
+                        )
+                        .filter(schema::webhook_events::next_attempt_at.le(now))
+                        .order(schema::webhook_events::received_at.asc())
+                        .limit(limit)
+                        .for_update()
+                        .skip_locked()
+                        .load::<String>(conn)?;
+
+                    diesel::update(webhook_events)
+                        .filter(schema::webhook_events::id.eq_any(&ids))
+                        .set(schema::webhook_events::next_attempt_at.eq(lease_until))
+                        .get_results::<WebhookEventModel>(conn)
+                })
+                .map_err(|e| Error::Database(e.to_string()))
+        })
+        .await?;
+
+        models.sort_by_key(|model| model.received_at);
+        models.into_iter().map(WebhookEvent::try_from).collect()
+    }
+}
@@ -0,0 +1,102 @@
+use crate::domain::unit_of_work::{Transaction, UnitOfWork};
+use crate::infra::postgres::connection::{get_connection, run_blocking, DbConnection, DbPool};
+use crate::infra::postgres::repositories::subscription::PostgresSubscriptionRepository;
+use crate::infra::postgres::repositories::user::PostgresUserRepository;
+use crate::prelude::*;
+use diesel::connection::{AnsiTransactionManager, TransactionManager};
+use diesel::PgConnection;
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone)]
+pub struct PostgresUnitOfWork {
+    pool: Arc<DbPool>,
+}
+impl PostgresUnitOfWork {
+    pub fn new(pool: Arc<DbPool>) -> Self {
+        Self { pool }
+    }
+}
+impl UnitOfWork for PostgresUnitOfWork {
+    type Transaction = PostgresTransaction;
+
+    async fn begin(&self) -> Result<PostgresTransaction> {
+        let pool = self.pool.clone();
+        let connection = run_blocking(move || {
+            let mut connection = get_connection(pool)?;
+            AnsiTransactionManager::begin_transaction(&mut *connection)
+                .map_err(|e| Error::Database(e.to_string()))?;
+            Ok(connection)
+        })
+        .await?;
+        Ok(PostgresTransaction::new(connection))
+    }
+}
+
+/// A Postgres transaction held on one pooled connection for its whole
+/// lifetime. The repositories it hands out share that connection.
+pub struct PostgresTransaction {
+    connection: Arc<Mutex<DbConnection>>,
+    users: PostgresUserRepository,
+    subscriptions: PostgresSubscriptionRepository,
+    finished: bool,
+}
+impl PostgresTransaction {
+    fn new(connection: DbConnection) -> Self {
+        let connection = Arc::new(Mutex::new(connection));
+        Self {
+            users: PostgresUserRepository::in_transaction(connection.clone()),
+            subscriptions: PostgresSubscriptionRepository::in_transaction(connection.clone()),
+            connection,
+            finished: false,
+        }
+    }
+}
+impl Transaction for PostgresTransaction {
+    type Users = PostgresUserRepository;
+    type Subscriptions = PostgresSubscriptionRepository;
+
+    fn users(&self) -> &PostgresUserRepository {
+        &self.users
+    }
+
+    fn subscriptions(&self) -> &PostgresSubscriptionRepository {
+        &self.subscriptions
+    }
+
+    async fn commit(mut self) -> Result<()> {
+        self.finished = true;
+        let connection = self.connection.clone();
+        run_blocking(move || {
+            let mut connection = connection
+                .lock()
+                .map_err(|e| Error::Connection(e.to_string()))?;
+            AnsiTransactionManager::commit_transaction(&mut **connection)
+                .map_err(|e| Error::Database(e.to_string()))
+        })
+        .await
+    }
+}
+impl Drop for PostgresTransaction {
+    /// Rolls back in the background, so the connection goes back to the pool
+    /// without the transaction or its locks.
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        let connection = self.connection.clone();
+        let rollback = move || {
+            if let Ok(mut connection) = connection.lock() {
+                let connection: &mut PgConnection = &mut connection;
+                if let Err(e) = AnsiTransactionManager::rollback_transaction(connection) {
+                    tracing::error!("Failed to roll back transaction: {}", e);
+                }
+            }
+        };
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                runtime.spawn_blocking(rollback);
+            }
+            Err(_) => rollback(),
+        }
+    }
+}
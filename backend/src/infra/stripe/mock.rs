@@ -0,0 +1,689 @@
+//! A local stand-in for the Stripe API, so `StripePaymentClient` and the
+//! webhook handling can be tested end to end without network access. It
+//! keeps its objects in memory, records every request it receives, and sends
+//! webhook events signed like Stripe does.
+
+use crate::infra::stripe::service::signature_header;
+use crate::prelude::*;
+use actix_web::dev::ServerHandle;
+use actix_web::http::StatusCode;
+use actix_web::web::{self, Bytes, Data};
+use actix_web::{App, HttpRequest, HttpResponse, HttpServer};
+use chrono::{Duration, Utc};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+/// A request received by the mock, form and query decoded.
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub form: Vec<(String, String)>,
+}
+impl RecordedRequest {
+    /// The value of `key` in the form, or else in the query string.
+    pub fn param(&self, key: &str) -> Option<&str> {
+        self.form
+            .iter()
+            .chain(self.query.iter())
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Price of a checkout, with the trial it was opened with. Stripe does not
+/// return either on the session object.
+#[derive(Debug, Clone)]
+struct CheckoutItem {
+    price: String,
+    trial_period_days: Option<i64>,
+}
+
+#[derive(Debug, Default)]
+struct Account {
+    requests: Vec<RecordedRequest>,
+    customers: Vec<Value>,
+    checkout_sessions: Vec<Value>,
+    checkout_items: HashMap<String, CheckoutItem>,
+    subscriptions: Vec<Value>,
+    invoices: Vec<Value>,
+    /// Product and unit amount of each price.
+    prices: HashMap<String, (String, i64)>,
+    events: usize,
+}
+
+/// The object to return, or the status and message of a Stripe error.
+type Response = std::result::Result<Value, (StatusCode, String)>;
+
+fn find(objects: &[Value], object: &str, id: &str) -> Response {
+    objects
+        .iter()
+        .find(|candidate| candidate["id"] == id)
+        .cloned()
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                format!("No such {}: '{}'", object, id),
+            )
+        })
+}
+
+/// A list endpoint: newest first, filtered by `customer`, paginated with
+/// `limit` and `starting_after`. Canceled subscriptions are only listed
+/// with `status=all`, as on Stripe.
+fn list(objects: &[Value], query: &[(String, String)]) -> Value {
+    let param = |key: &str| {
+        query
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    };
+    let mut items: Vec<&Value> = objects
+        .iter()
+        .rev()
+        .filter(|object| param("customer").is_none_or(|customer| object["customer"] == customer))
+        .filter(|object| match param("status") {
+            Some("all") => true,
+            Some(status) => object["status"] == status,
+            None => object["status"] != "canceled",
+        })
+        .collect();
+    if let Some(after) = param("starting_after") {
+        let position = items.iter().position(|object| object["id"] == after);
+        items = items.split_off(position.map_or(items.len(), |index| index + 1));
+    }
+    let limit = param("limit")
+        .and_then(|limit| limit.parse().ok())
+        .unwrap_or(10);
+    let has_more = items.len() > limit;
+    items.truncate(limit);
+    json!({ "object": "list", "data": items, "has_more": has_more })
+}
+
+impl Account {
+    fn next_event(&mut self, event_type: &str, object: Value) -> Value {
+        self.events += 1;
+        json!({
+            "id": format!("evt_{:04}", self.events),
+            "object": "event",
+            "type": event_type,
+            "created": Utc::now().timestamp(),
+            "data": { "object": object },
+        })
+    }
+
+    fn create_customer(&mut self, request: &RecordedRequest) -> Response {
+        let customer = json!({
+            "id": format!("cus_{:04}", self.customers.len() + 1),
+            "object": "customer",
+            "email": request.param("email"),
+            "name": request.param("name"),
+        });
+        self.customers.push(customer.clone());
+        Ok(customer)
+    }
+
+    /// Supports the `email:'...'` queries `StripePaymentClient` sends.
+    fn search_customers(&self, request: &RecordedRequest) -> Response {
+        let email = request
+            .param("query")
+            .and_then(|query| query.strip_prefix("email:'"))
+            .and_then(|query| query.strip_suffix('\''))
+            .ok_or_else(|| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    "Unsupported search query".to_string(),
+                )
+            })?;
+        let data: Vec<&Value> = self
+            .customers
+            .iter()
+            .filter(|customer| customer["email"] == email)
+            .collect();
+        Ok(json!({ "object": "search_result", "data": data, "has_more": false }))
+    }
+
+    fn create_checkout_session(&mut self, request: &RecordedRequest) -> Response {
+        let mode = request.param("mode").unwrap_or("payment").to_string();
+        let ui_mode = request.param("ui_mode").unwrap_or("hosted").to_string();
+        let price = request.param("line_items[0][price]");
+        if let Some(price) = price {
+            if !self.prices.contains_key(price) {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    format!("No such price: '{}'", price),
+                ));
+            }
+        }
+        if mode == "subscription" && price.is_none() {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Subscription checkouts need a line item".to_string(),
+            ));
+        }
+
+        let id = format!("cs_test_{:04}", self.checkout_sessions.len() + 1);
+        let customer = request.param("customer");
+        let customer_email = self
+            .customers
+            .iter()
+            .find(|candidate| customer.is_some_and(|customer| candidate["id"] == customer))
+            .map(|customer| customer["email"].clone());
+        let (url, client_secret) = if ui_mode == "embedded" {
+            (None, Some(format!("{}_secret_test", id)))
+        } else {
+            (
+                Some(format!("https://checkout.stripe.com/c/pay/{}", id)),
+                None,
+            )
+        };
+        let session = json!({
+            "id": id,
+            "object": "checkout.session",
+            "mode": mode,
+            "ui_mode": ui_mode,
+            "customer": customer,
+            "customer_email": customer_email,
+            "client_reference_id": request.param("client_reference_id"),
+            "subscription": null,
+            "payment_status": "unpaid",
+            "status": "open",
+            "url": url,
+            "client_secret": client_secret,
+        });
+        if let Some(price) = price {
+            let trial_period_days = request
+                .param("subscription_data[trial_period_days]")
+                .and_then(|days| days.parse().ok())
+                .filter(|days| *days > 0);
+            self.checkout_items.insert(
+                id,
+                CheckoutItem {
+                    price: price.to_string(),
+                    trial_period_days,
+                },
+            );
+        }
+        self.checkout_sessions.push(session.clone());
+        Ok(session)
+    }
+
+    fn create_portal_session(&self, request: &RecordedRequest) -> Response {
+        let customer = request.param("customer").ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                "Missing required param: customer".to_string(),
+            )
+        })?;
+        Ok(json!({
+            "id": "bps_test",
+            "object": "billing_portal.session",
+            "customer": customer,
+            "return_url": request.param("return_url"),
+            "url": format!("https://billing.stripe.com/p/session/{}", customer),
+        }))
+    }
+
+    /// Creates the subscription and the first invoice of a completed
+    /// subscription checkout.
+    fn subscribe(&mut self, session: &Value, item: &CheckoutItem) -> Result<(Value, Value)> {
+        let (product, unit_amount) = self
+            .prices
+            .get(&item.price)
+            .cloned()
+            .ok_or_else(|| Error::NotFound(format!("Price {} not found", item.price)))?;
+        let now = Utc::now();
+        let (status, period_end, amount_paid) = match item.trial_period_days {
+            Some(days) => ("trialing", now + Duration::days(days), 0),
+            None => ("active", now + Duration::days(30), unit_amount),
+        };
+        let price = json!({ "id": item.price, "product": product });
+        let subscription = json!({
+            "id": format!("sub_{:04}", self.subscriptions.len() + 1),
+            "object": "subscription",
+            "customer": session["customer"],
+            "status": status,
+            "plan": price,
+            "items": {
+                "data": [{ "price": price, "current_period_end": period_end.timestamp() }],
+                "has_more": false,
+            },
+            "current_period_end": period_end.timestamp(),
+            "cancel_at_period_end": false,
+            "canceled_at": null,
+        });
+        let invoice = json!({
+            "id": format!("in_{:04}", self.invoices.len() + 1),
+            "object": "invoice",
+            "customer": session["customer"],
+            "customer_email": session["customer_email"],
+            "subscription": subscription["id"],
+            "billing_reason": "subscription_create",
+            "amount_paid": amount_paid,
+            "status": "paid",
+            "lines": {
+                "data": [{
+                    "price": price,
+                    "period": { "start": now.timestamp(), "end": period_end.timestamp() },
+                }],
+                "has_more": false,
+            },
+        });
+        self.subscriptions.push(subscription.clone());
+        self.invoices.push(invoice.clone());
+        Ok((subscription, invoice))
+    }
+}
+
+async fn handle(account: Data<Mutex<Account>>, req: HttpRequest, body: Bytes) -> HttpResponse {
+    let request = RecordedRequest {
+        method: req.method().to_string(),
+        path: req.path().to_string(),
+        query: serde_urlencoded::from_str(req.query_string()).unwrap_or_default(),
+        form: serde_urlencoded::from_bytes(&body).unwrap_or_default(),
+    };
+    let mut account = account.lock().unwrap();
+    account.requests.push(request.clone());
+
+    let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+    let response = match (request.method.as_str(), segments.as_slice()) {
+        ("POST", ["v1", "customers"]) => account.create_customer(&request),
+        ("GET", ["v1", "customers", "search"]) => account.search_customers(&request),
+        ("POST", ["v1", "checkout", "sessions"]) => account.create_checkout_session(&request),
+        ("GET", ["v1", "checkout", "sessions", id]) => {
+            find(&account.checkout_sessions, "checkout.session", id)
+        }
+        ("POST", ["v1", "billing_portal", "sessions"]) => account.create_portal_session(&request),
+        ("GET", ["v1", "subscriptions"]) => Ok(list(&account.subscriptions, &request.query)),
+        ("GET", ["v1", "subscriptions", id]) => find(&account.subscriptions, "subscription", id),
+        ("GET", ["v1", "invoices"]) => Ok(list(&account.invoices, &request.query)),
+        ("GET", ["v1", "invoices", id]) => find(&account.invoices, "invoice", id),
+        _ => Err((
+            StatusCode::NOT_FOUND,
+            format!(
+                "Unrecognized request URL ({}: {})",
+                request.method, request.path
+            ),
+        )),
+    };
+    match response {
+        Ok(object) => HttpResponse::Ok().json(object),
+        Err((status, message)) => HttpResponse::build(status).json(json!({
+            "error": { "type": "invalid_request_error", "message": message }
+        })),
+    }
+}
+
+/// The mock server, listening on a random local port until dropped or
+/// stopped. Point `StripePaymentClient` at `base_url`.
+pub struct MockStripe {
+    account: Arc<Mutex<Account>>,
+    address: SocketAddr,
+    webhook_secret: String,
+    server: ServerHandle,
+    http: reqwest::Client,
+}
+impl MockStripe {
+    pub async fn start(webhook_secret: &str) -> std::io::Result<Self> {
+        let account = Arc::new(Mutex::new(Account::default()));
+        let data = Data::from(account.clone());
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(data.clone())
+                .default_service(web::to(handle))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))?;
+        let address = server.addrs()[0];
+        let server = server.run();
+        let handle = server.handle();
+        tokio::spawn(server);
+        Ok(Self {
+            account,
+            address,
+            webhook_secret: webhook_secret.to_string(),
+            server: handle,
+            http: reqwest::Client::new(),
+        })
+    }
+
+    pub fn base_url(&self) -> String {
+        format!("http://{}/v1", self.address)
+    }
+
+    /// Every request received so far, oldest first.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.account.lock().unwrap().requests.clone()
+    }
+
+    /// Makes `price_id` available to checkouts, billed `unit_amount` per
+    /// period.
+    pub fn add_price(&self, price_id: &str, product_id: &str, unit_amount: i64) {
+        let mut account = self.account.lock().unwrap();
+        account
+            .prices
+            .insert(price_id.to_string(), (product_id.to_string(), unit_amount));
+    }
+
+    /// Completes the checkout session as a paying customer would, and
+    /// returns the webhook events Stripe would send for it, in order.
+    pub fn complete_checkout(&self, session_id: &str) -> Result<Vec<Value>> {
+        let mut account = self.account.lock().unwrap();
+        let index = account
+            .checkout_sessions
+            .iter()
+            .position(|session| session["id"] == session_id)
+            .ok_or_else(|| Error::NotFound(format!("Checkout session {} not found", session_id)))?;
+        if account.checkout_sessions[index]["status"] != "open" {
+            return Err(Error::BadRequest(format!(
+                "Checkout session {} is not open",
+                session_id
+            )));
+        }
+
+        let mut session = account.checkout_sessions[index].clone();
+        let item = account.checkout_items.get(session_id).cloned();
+        let mut invoice = None;
+        match item {
+            Some(item) if session["mode"] == "subscription" => {
+                let (subscription, first_invoice) = account.subscribe(&session, &item)?;
+                session["subscription"] = subscription["id"].clone();
+                session["payment_status"] = if item.trial_period_days.is_some() {
+                    json!("no_payment_required")
+                } else {
+                    json!("paid")
+                };
+                invoice = Some(first_invoice);
+            }
+            _ => session["payment_status"] = json!("paid"),
+        }
+        session["status"] = json!("complete");
+        account.checkout_sessions[index] = session.clone();
+
+        let mut events = vec![account.next_event("checkout.session.completed", session)];
+        if let Some(invoice) = invoice {
+            events.push(account.next_event("invoice.paid", invoice));
+        }
+        Ok(events)
+    }
+
+    /// Cancels the subscription immediately and returns the
+    /// `customer.subscription.deleted` event.
+    pub fn cancel_subscription(&self, subscription_id: &str) -> Result<Value> {
+        let mut account = self.account.lock().unwrap();
+        let subscription = account
+            .subscriptions
+            .iter_mut()
+            .find(|subscription| subscription["id"] == subscription_id)
+            .ok_or_else(|| {
+                Error::NotFound(format!("Subscription {} not found", subscription_id))
+            })?;
+        subscription["status"] = json!("canceled");
+        subscription["canceled_at"] = json!(Utc::now().timestamp());
+        let subscription = subscription.clone();
+        Ok(account.next_event("customer.subscription.deleted", subscription))
+    }
+
+    /// Posts `event` to `url` with a valid `Stripe-Signature` header.
+    pub async fn send_webhook(&self, url: &str, event: &Value) -> Result<reqwest::Response> {
+        let payload = event.to_string();
+        let signature = signature_header(&self.webhook_secret, Utc::now().timestamp(), &payload)?;
+        let response = self
+            .http
+            .post(url)
+            .header("Content-Type", "application/json")
+            .header("Stripe-Signature", signature)
+            .body(payload)
+            .send()
+            .await?;
+        Ok(response)
+    }
+
+    pub async fn stop(self) {
+        self.server.stop(true).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::catalog::service::CatalogService;
+    use crate::application::payment::dto::NewCheckoutSessionDto;
+    use crate::application::payment::service::PaymentService;
+    use crate::application::payment::use_cases::{CheckoutSettings, CreateCheckoutSessionUseCase};
+    use crate::application::subscription::dispatcher::WebhookDispatcher;
+    use crate::application::subscription::receiver::WebhookReceiver;
+    use crate::application::subscription::service::{SignatureService, WebhookEventService};
+    use crate::application::subscription::worker::{RetryPolicy, WebhookWorker, WorkerSettings};
+    use crate::application::user::cache::UserCache;
+    use crate::application::user::dtos::UserDto;
+    use crate::application::user::service::UserService;
+    use crate::domain::catalog::entities::{Price, Product};
+    use crate::domain::catalog::repository::CatalogRepository;
+    use crate::domain::payment::entities::checkout::LineItem;
+    use crate::domain::payment::value_objects::checkout_mode::CheckoutMode;
+    use crate::domain::payment::value_objects::ui_mode::UiMode;
+    use crate::domain::subscription::repository::SubscriptionRepository;
+    use crate::domain::subscription::repository::WebhookEventRepository;
+    use crate::domain::subscription::value_objects::subscription_status::SubscriptionStatus;
+    use crate::domain::user::entities::{AuthProviderData, User, UserIdentity};
+    use crate::domain::user::repositories::UserRepository;
+    use crate::infra::memory::webhook_event::InMemoryWebhookEventRepository;
+    use crate::infra::memory::InMemoryDatabase;
+    use crate::infra::stripe::payment::StripePaymentClient;
+    use crate::infra::stripe::service::StripeSignatureVerificationService;
+    use crate::presentation::handlers::payment::payment_webhook;
+    use tokio::sync::Notify;
+
+    const WEBHOOK_SECRET: &str = "whsec_test_secret";
+
+    #[actix_web::test]
+    async fn test_checkout_to_subscription() {
+        let stripe = MockStripe::start(WEBHOOK_SECRET).await.unwrap();
+        stripe.add_price("price_pro", "prod_pro", 900);
+
+        let db = InMemoryDatabase::new();
+        let catalog = db.catalog();
+        catalog
+            .upsert_product(
+                &Product::new("prod_pro".to_string(), "Pro".to_string(), None, true),
+                Utc::now(),
+            )
+            .await
+            .unwrap();
+        catalog
+            .upsert_price(
+                &Price::new(
+                    "price_pro".to_string(),
+                    "prod_pro".to_string(),
+                    true,
+                    "usd".to_string(),
+                    Some(900),
+                    None,
+                    Some("month".to_string()),
+                    Some(1),
+                    1,
+                    Some(1),
+                ),
+                Utc::now(),
+            )
+            .await
+            .unwrap();
+        let auth = AuthProviderData::new(
+            "firebase".to_string(),
+            "uid".to_string(),
+            "john@example.com".to_string(),
+            true,
+            None,
+            None,
+            Utc::now(),
+        );
+        let user = User::new(auth.email.clone(), None);
+        let user = db
+            .users()
+            .save(&user, &UserIdentity::new(user.id(), &auth))
+            .await
+            .unwrap();
+
+        let payment_service = PaymentService::new(Arc::new(StripePaymentClient::new(
+            "sk_test",
+            Arc::new(reqwest::Client::new()),
+            &stripe.base_url(),
+        )));
+        let user_service = UserService::new(
+            Arc::new(db.users()),
+            UserCache::new(std::time::Duration::ZERO, 0),
+        );
+        let catalog_service = CatalogService::new(Arc::new(catalog));
+        let checkout = CreateCheckoutSessionUseCase::new(
+            payment_service.clone(),
+            catalog_service.clone(),
+            CheckoutSettings {
+                ui_mode: UiMode::Hosted,
+                mode: CheckoutMode::Subscription,
+                allowed_ui_modes: vec![UiMode::Hosted],
+                allowed_modes: vec![CheckoutMode::Subscription],
+                trial_period_days: 14,
+                allowed_products: vec![],
+            },
+        )
+        .execute(
+            UserDto::try_from(&user).unwrap(),
+            NewCheckoutSessionDto::new(
+                vec![LineItem {
+                    price: "price_pro".to_string(),
+                    quantity: 1,
+                }],
+                Some("https://app.example.com/success".to_string()),
+                Some("https://app.example.com/cancel".to_string()),
+                None,
+                None,
+                None,
+            ),
+        )
+        .await
+        .unwrap();
+        assert!(checkout.url.is_some());
+
+        let notifier = Arc::new(Notify::new());
+        let events = WebhookEventService::new(Arc::new(db.webhook_events()));
+        let receiver = Data::new(WebhookReceiver::new(
+            events.clone(),
+            SignatureService::new(Arc::new(StripeSignatureVerificationService::new(
+                &[WEBHOOK_SECRET],
+                std::time::Duration::from_secs(300),
+            ))),
+            notifier.clone(),
+            256 * 1024,
+        ));
+        let worker = WebhookWorker::new(
+            events,
+            WebhookDispatcher::new(
+                Arc::new(db.unit_of_work()),
+                user_service,
+                payment_service,
+                catalog_service,
+            ),
+            WorkerSettings {
+                poll_interval: std::time::Duration::from_secs(1),
+                batch_size: 10,
+                lease: std::time::Duration::from_secs(60),
+                retry: RetryPolicy {
+                    max_attempts: 1,
+                    base_delay: std::time::Duration::ZERO,
+                    max_delay: std::time::Duration::ZERO,
+                },
+            },
+            notifier,
+        );
+        let server = HttpServer::new(move || {
+            App::new().app_data(receiver.clone()).route(
+                "/webhook",
+                web::post().to(payment_webhook::<
+                    InMemoryWebhookEventRepository,
+                    StripeSignatureVerificationService,
+                >),
+            )
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let webhook_url = format!("http://{}/webhook", server.addrs()[0]);
+        let server = server.run();
+        let server_handle = server.handle();
+        tokio::spawn(server);
+
+        let events = stripe.complete_checkout(&checkout.id).unwrap();
+        assert_eq!(events.len(), 2);
+        for event in &events {
+            let response = stripe.send_webhook(&webhook_url, event).await.unwrap();
+            assert_eq!(response.status(), 200, "{}", response.text().await.unwrap());
+        }
+        worker.drain().await.unwrap();
+
+        let user = db.users().find(&user.id()).await.unwrap().unwrap();
+        assert_eq!(user.stripe_customer_id(), Some("cus_0001"));
+        let subscription = db
+            .subscriptions()
+            .find_by_user_id(&user.id())
+            .await
+            .unwrap();
+        assert_eq!(subscription.stripe_subscription_id(), "sub_0001");
+        assert_eq!(subscription.stripe_price_id(), "price_pro");
+        assert_eq!(subscription.status(), &SubscriptionStatus::Trialing);
+
+        let event = stripe.cancel_subscription("sub_0001").unwrap();
+        let response = stripe.send_webhook(&webhook_url, &event).await.unwrap();
+        assert_eq!(response.status(), 200);
+        worker.drain().await.unwrap();
+        let subscription = db
+            .subscriptions()
+            .find_by_user_id(&user.id())
+            .await
+            .unwrap();
+        assert_eq!(subscription.status(), &SubscriptionStatus::Canceled);
+
+        let forged = json!({ "id": "evt_forged", "type": "charge.succeeded", "created": 0, "data": { "object": {} } });
+        let response = reqwest::Client::new()
+            .post(&webhook_url)
+            .header(
+                "Stripe-Signature",
+                signature_header("whsec_other", Utc::now().timestamp(), &forged.to_string())
+                    .unwrap(),
+            )
+            .body(forged.to_string())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+        assert!(db.webhook_events().find("evt_forged").await.is_err());
+
+        let calls: Vec<(String, String)> = stripe
+            .requests()
+            .into_iter()
+            .map(|request| (request.method, request.path))
+            .collect();
+        let call = |method: &str, path: &str| (method.to_string(), path.to_string());
+        assert_eq!(
+            calls,
+            vec![
+                call("GET", "/v1/customers/search"),
+                call("POST", "/v1/customers"),
+                call("POST", "/v1/checkout/sessions"),
+                call("GET", "/v1/subscriptions/sub_0001"),
+            ]
+        );
+        let session = &stripe.requests()[2];
+        assert_eq!(session.param("customer"), Some("cus_0001"));
+        assert_eq!(
+            session.param("subscription_data[trial_period_days]"),
+            Some("14")
+        );
+
+        server_handle.stop(true).await;
+        stripe.stop().await;
+    }
+}
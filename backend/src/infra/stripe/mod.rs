@@ -1,3 +1,5 @@
-mod models;
+#[cfg(any(test, feature = "testing"))]
+pub mod mock;
+pub mod models;
 pub mod payment;
 pub mod service;
@@ -1,8 +1,16 @@
-use crate::domain::payment::entities::checkout::CheckoutSession;
+use crate::domain::catalog::entities::{Price, Product};
+use crate::domain::payment::entities::checkout::{CheckoutSession, CheckoutSessionDetails};
 use crate::domain::payment::entities::customer::Customer;
-use crate::infra::constants::{CHECKOUT_MODE, TRIAL_PERIOD_DAYS, UI_MODE};
+use crate::domain::payment::entities::subscription::SubscriptionDetails;
+use crate::domain::payment::value_objects::checkout_status::CheckoutStatus;
+use crate::domain::payment::value_objects::ui_mode::UiMode;
+use crate::domain::subscription::entities::WebhookEvent;
+use crate::domain::subscription::value_objects::subscription_status::SubscriptionStatus;
 use crate::prelude::*;
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GetCustomerResponse {
@@ -19,14 +27,30 @@ impl TryFrom<&CheckoutSession> for CheckoutSessionForm {
     fn try_from(checkout: &CheckoutSession) -> Result<Self> {
         let mut data = vec![];
         data.push(("customer".to_string(), checkout.customer().to_string()));
-        data.push(("mode".to_string(), CHECKOUT_MODE.to_string()));
-        data.push(("ui_mode".to_string(), UI_MODE.to_string()));
-
-        if let Some(success_url) = checkout.success_url() {
-            data.push(("success_url".to_string(), success_url.to_string()));
+        if let Some(client_reference_id) = checkout.client_reference_id() {
+            data.push((
+                "client_reference_id".to_string(),
+                client_reference_id.to_string(),
+            ));
         }
-        if let Some(cancel_url) = checkout.cancel_url() {
-            data.push(("cancel_url".to_string(), cancel_url.to_string()));
+        data.push(("mode".to_string(), checkout.mode().to_string()));
+        data.push(("ui_mode".to_string(), checkout.ui_mode().to_string()));
+
+        // Embedded sessions redirect to `return_url` and reject success/cancel urls.
+        match checkout.ui_mode() {
+            UiMode::Embedded => {
+                if let Some(return_url) = checkout.return_url() {
+                    data.push(("return_url".to_string(), return_url.to_string()));
+                }
+            }
+            UiMode::Hosted => {
+                if let Some(success_url) = checkout.success_url() {
+                    data.push(("success_url".to_string(), success_url.to_string()));
+                }
+                if let Some(cancel_url) = checkout.cancel_url() {
+                    data.push(("cancel_url".to_string(), cancel_url.to_string()));
+                }
+            }
         }
         for (index, item) in checkout.line_items().iter().enumerate() {
             let price_key = format!("line_items[{}][price]", index);
@@ -34,16 +58,396 @@ impl TryFrom<&CheckoutSession> for CheckoutSessionForm {
             data.push((price_key, item.price.to_string()));
             data.push((quantity_key, item.quantity.to_string()));
         }
-        if TRIAL_PERIOD_DAYS > 0 {
+        if let Some(trial_period_days) = checkout.trial_period_days() {
             data.push((
                 "subscription_data[trial_period_days]".to_string(),
-                TRIAL_PERIOD_DAYS.to_string(),
+                trial_period_days.to_string(),
             ));
         }
         Ok(CheckoutSessionForm { data })
     }
 }
 
+//*******************************************//
+//************** Webhook events **************//
+//*******************************************//
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum StripeEvent {
+    #[serde(rename = "customer.created")]
+    CustomerCreated(Event<StripeCustomer>),
+    #[serde(rename = "invoice.paid")]
+    InvoicePaid(Event<StripeInvoice>),
+    #[serde(rename = "invoice.payment_failed")]
+    InvoicePaymentFailed(Event<StripeInvoice>),
+    #[serde(rename = "customer.subscription.updated")]
+    SubscriptionUpdated(Event<StripeSubscription>),
+    #[serde(rename = "customer.subscription.deleted")]
+    SubscriptionDeleted(Event<StripeSubscription>),
+    #[serde(rename = "customer.subscription.paused")]
+    SubscriptionPaused(Event<StripeSubscription>),
+    #[serde(rename = "customer.subscription.resumed")]
+    SubscriptionResumed(Event<StripeSubscription>),
+    #[serde(rename = "checkout.session.completed")]
+    CheckoutSessionCompleted(Event<StripeCheckoutSession>),
+    #[serde(rename = "product.created")]
+    ProductCreated(Event<StripeProduct>),
+    #[serde(rename = "product.updated")]
+    ProductUpdated(Event<StripeProduct>),
+    #[serde(rename = "product.deleted")]
+    ProductDeleted(Event<StripeProduct>),
+    #[serde(rename = "price.created")]
+    PriceCreated(Event<StripePrice>),
+    #[serde(rename = "price.updated")]
+    PriceUpdated(Event<StripePrice>),
+    #[serde(rename = "price.deleted")]
+    PriceDeleted(Event<StripePrice>),
+    #[serde(untagged)]
+    Unhandled(UnhandledEvent),
+}
+impl StripeEvent {
+    /// Event types with a typed variant. A payload of one of these types that
+    /// does not match its struct is rejected instead of being treated as unhandled.
+    pub(crate) const HANDLED_TYPES: [&'static str; 14] = [
+        "customer.created",
+        "invoice.paid",
+        "invoice.payment_failed",
+        "customer.subscription.updated",
+        "customer.subscription.deleted",
+        "customer.subscription.paused",
+        "customer.subscription.resumed",
+        "checkout.session.completed",
+        "product.created",
+        "product.updated",
+        "product.deleted",
+        "price.created",
+        "price.updated",
+        "price.deleted",
+    ];
+
+    pub fn event_type(&self) -> &str {
+        match self {
+            Self::CustomerCreated(_) => "customer.created",
+            Self::InvoicePaid(_) => "invoice.paid",
+            Self::InvoicePaymentFailed(_) => "invoice.payment_failed",
+            Self::SubscriptionUpdated(_) => "customer.subscription.updated",
+            Self::SubscriptionDeleted(_) => "customer.subscription.deleted",
+            Self::SubscriptionPaused(_) => "customer.subscription.paused",
+            Self::SubscriptionResumed(_) => "customer.subscription.resumed",
+            Self::CheckoutSessionCompleted(_) => "checkout.session.completed",
+            Self::ProductCreated(_) => "product.created",
+            Self::ProductUpdated(_) => "product.updated",
+            Self::ProductDeleted(_) => "product.deleted",
+            Self::PriceCreated(_) => "price.created",
+            Self::PriceUpdated(_) => "price.updated",
+            Self::PriceDeleted(_) => "price.deleted",
+            Self::Unhandled(event) => &event.event_type,
+        }
+    }
+
+    pub fn created(&self) -> DateTime<Utc> {
+        match self {
+            Self::CustomerCreated(event) => event.created,
+            Self::InvoicePaid(event) | Self::InvoicePaymentFailed(event) => event.created,
+            Self::SubscriptionUpdated(event)
+            | Self::SubscriptionDeleted(event)
+            | Self::SubscriptionPaused(event)
+            | Self::SubscriptionResumed(event) => event.created,
+            Self::CheckoutSessionCompleted(event) => event.created,
+            Self::ProductCreated(event)
+            | Self::ProductUpdated(event)
+            | Self::ProductDeleted(event) => event.created,
+            Self::PriceCreated(event) | Self::PriceUpdated(event) | Self::PriceDeleted(event) => {
+                event.created
+            }
+            Self::Unhandled(event) => event.created,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event<T> {
+    pub id: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created: DateTime<Utc>,
+    pub data: EventData<T>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventData<T> {
+    pub object: T,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnhandledEvent {
+    pub id: String,
+    #[serde(rename = "type", deserialize_with = "unhandled_event_type")]
+    pub event_type: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created: DateTime<Utc>,
+}
+
+/// The fields every event has, whatever the shape of its `data`.
+#[derive(Debug, Clone, Deserialize)]
+struct StripeEventEnvelope {
+    id: String,
+    #[serde(rename = "type")]
+    event_type: String,
+}
+impl TryFrom<Value> for WebhookEvent {
+    type Error = Error;
+
+    /// Keeps the verified body as received, less the object's client secret.
+    /// It is only parsed into a [`StripeEvent`] when dispatched, so that an
+    /// event our structs do not fit is still stored, retried and dead-lettered.
+    fn try_from(mut payload: Value) -> Result<Self> {
+        let envelope = StripeEventEnvelope::deserialize(&payload)
+            .map_err(|e| Error::BadRequest(format!("Invalid webhook event: {}", e)))?;
+        if let Some(object) = payload
+            .pointer_mut("/data/object")
+            .and_then(Value::as_object_mut)
+        {
+            object.remove("client_secret");
+        }
+        Ok(WebhookEvent::new(envelope.id, envelope.event_type, payload))
+    }
+}
+
+fn unhandled_event_type<'de, D>(deserializer: D) -> std::result::Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let event_type = String::deserialize(deserializer)?;
+    if StripeEvent::HANDLED_TYPES.contains(&event_type.as_str()) {
+        return Err(serde::de::Error::custom(format!(
+            "malformed `{}` event payload",
+            event_type
+        )));
+    }
+    Ok(event_type)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StripeList<T> {
+    pub data: Vec<T>,
+    #[serde(default)]
+    pub has_more: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StripeCustomer {
+    pub id: String,
+    pub email: Option<String>,
+    pub name: Option<String>,
+}
+impl TryFrom<StripeCustomer> for Customer {
+    type Error = Error;
+
+    fn try_from(customer: StripeCustomer) -> Result<Self> {
+        let email = customer
+            .email
+            .ok_or_else(|| Error::BadRequest(format!("Customer {} has no email", customer.id)))?;
+        Ok(Customer::construct(customer.id, email, customer.name))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StripePriceRef {
+    pub id: String,
+    pub product: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StripePeriod {
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub start: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub end: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StripeInvoiceLine {
+    pub price: Option<StripePriceRef>,
+    pub period: StripePeriod,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StripeInvoice {
+    pub id: String,
+    pub customer: String,
+    pub customer_email: Option<String>,
+    pub subscription: Option<String>,
+    pub billing_reason: Option<String>,
+    pub amount_paid: i64,
+    pub lines: StripeList<StripeInvoiceLine>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StripeSubscriptionItem {
+    pub price: StripePriceRef,
+    #[serde(default, with = "chrono::serde::ts_seconds_option")]
+    pub current_period_end: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StripeSubscription {
+    pub id: String,
+    pub customer: String,
+    pub status: SubscriptionStatus,
+    pub plan: Option<StripePriceRef>,
+    #[serde(default)]
+    pub items: Option<StripeList<StripeSubscriptionItem>>,
+    #[serde(default, with = "chrono::serde::ts_seconds_option")]
+    pub current_period_end: Option<DateTime<Utc>>,
+    pub cancel_at_period_end: bool,
+    #[serde(default, with = "chrono::serde::ts_seconds_option")]
+    pub canceled_at: Option<DateTime<Utc>>,
+}
+impl StripeSubscription {
+    /// Newer API versions drop the legacy `plan` field in favour of `items`.
+    pub fn price(&self) -> Option<&StripePriceRef> {
+        self.plan.as_ref().or_else(|| {
+            self.items
+                .as_ref()
+                .and_then(|items| items.data.first())
+                .map(|item| &item.price)
+        })
+    }
+
+    pub fn period_end(&self) -> Option<DateTime<Utc>> {
+        self.current_period_end.or_else(|| {
+            self.items
+                .as_ref()
+                .and_then(|items| items.data.first())
+                .and_then(|item| item.current_period_end)
+        })
+    }
+}
+impl TryFrom<StripeSubscription> for SubscriptionDetails {
+    type Error = Error;
+
+    fn try_from(subscription: StripeSubscription) -> Result<Self> {
+        let price = subscription.price().cloned().ok_or_else(|| {
+            Error::BadRequest(format!("Subscription {} has no price", subscription.id))
+        })?;
+        let current_period_end = subscription.period_end();
+        Ok(SubscriptionDetails::construct(
+            subscription.id,
+            subscription.customer,
+            subscription.status,
+            price.id,
+            price.product,
+            current_period_end,
+            subscription.cancel_at_period_end,
+            subscription.canceled_at,
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StripeCheckoutSession {
+    pub id: String,
+    pub mode: String,
+    pub customer: Option<String>,
+    pub customer_email: Option<String>,
+    pub subscription: Option<String>,
+    pub client_reference_id: Option<String>,
+    pub payment_status: String,
+    #[serde(default)]
+    pub status: Option<CheckoutStatus>,
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Never persisted with stored webhook payloads.
+    #[serde(default, skip_serializing)]
+    pub client_secret: Option<String>,
+}
+impl From<StripeCheckoutSession> for CheckoutSessionDetails {
+    fn from(session: StripeCheckoutSession) -> Self {
+        CheckoutSessionDetails::construct(
+            session.id,
+            session.status,
+            session.payment_status,
+            session.customer,
+            session.customer_email,
+            session.client_reference_id,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StripeProduct {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub active: bool,
+}
+impl From<StripeProduct> for Product {
+    fn from(product: StripeProduct) -> Self {
+        Product::new(
+            product.id,
+            product.name,
+            product.description,
+            product.active,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StripeRecurring {
+    pub interval: String,
+    pub interval_count: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StripePrice {
+    pub id: String,
+    pub product: String,
+    pub active: bool,
+    pub currency: String,
+    pub unit_amount: Option<i64>,
+    pub nickname: Option<String>,
+    pub recurring: Option<StripeRecurring>,
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+impl StripePrice {
+    /// Reads an integer from the price metadata, ignoring malformed values.
+    fn metadata_i32(&self, key: &str) -> Option<i32> {
+        let value = self.metadata.get(key)?;
+        match value.trim().parse::<i32>() {
+            Ok(value) => Some(value),
+            Err(_) => {
+                tracing::warn!("Ignoring invalid {} {:?} on price {}", key, value, self.id);
+                None
+            }
+        }
+    }
+}
+impl From<StripePrice> for Price {
+    /// Quantity limits come from the `min_quantity` and `max_quantity`
+    /// metadata keys set on the price in the Stripe dashboard.
+    fn from(price: StripePrice) -> Self {
+        let min_quantity = price.metadata_i32("min_quantity").unwrap_or(1);
+        let max_quantity = price.metadata_i32("max_quantity");
+        let (interval, interval_count) = match price.recurring {
+            Some(recurring) => (Some(recurring.interval), Some(recurring.interval_count)),
+            None => (None, None),
+        };
+        Price::new(
+            price.id,
+            price.product,
+            price.active,
+            price.currency,
+            price.unit_amount,
+            price.nickname,
+            interval,
+            interval_count,
+            min_quantity,
+            max_quantity,
+        )
+    }
+}
+
 //
 // #[derive(Debug, Serialize, Deserialize)]
 // pub struct LineItemForm {
@@ -94,3 +498,190 @@ impl TryFrom<&CheckoutSession> for CheckoutSessionForm {
 //         })
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_deserialize_stripe_event() {
+        let payload = json!({
+            "id": "evt_invoice_paid",
+            "type": "invoice.paid",
+            "created": 1700000000,
+            "data": {
+                "object": {
+                    "id": "in_test",
+                    "customer": "cus_test",
+                    "customer_email": "john@example.com",
+                    "subscription": "sub_test",
+                    "billing_reason": "subscription_create",
+                    "amount_paid": 0,
+                    "lines": {
+                        "data": [{
+                            "price": { "id": "price_test", "product": "prod_test" },
+                            "period": { "start": 1700000000, "end": 1702592000 }
+                        }]
+                    }
+                }
+            }
+        });
+        let event: StripeEvent = serde_json::from_value(payload).unwrap();
+        assert_eq!(event.event_type(), "invoice.paid");
+        match event {
+            StripeEvent::InvoicePaid(event) => {
+                assert_eq!(event.id, "evt_invoice_paid");
+                assert_eq!(event.data.object.customer, "cus_test");
+                assert_eq!(event.data.object.lines.data.len(), 1);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+
+        let payload = json!({
+            "id": "evt_unhandled",
+            "type": "charge.succeeded",
+            "created": 1700000000,
+            "data": { "object": { "id": "ch_test" } }
+        });
+        let event: StripeEvent = serde_json::from_value(payload).unwrap();
+        assert!(matches!(event, StripeEvent::Unhandled(_)));
+        assert_eq!(event.event_type(), "charge.succeeded");
+
+        let payload = json!({
+            "id": "evt_malformed",
+            "type": "invoice.paid",
+            "created": 1700000000,
+            "data": { "object": { "id": "in_test" } }
+        });
+        assert!(serde_json::from_value::<StripeEvent>(payload).is_err());
+    }
+
+    #[test]
+    fn test_webhook_event_keeps_payload() {
+        let payload = json!({
+            "id": "evt_unhandled",
+            "type": "charge.succeeded",
+            "created": 1700000000,
+            "data": { "object": { "id": "ch_test" } }
+        });
+        let event = WebhookEvent::try_from(payload.clone()).unwrap();
+        assert_eq!(event.id(), "evt_unhandled");
+        assert_eq!(event.event_type(), "charge.succeeded");
+        assert_eq!(event.payload(), &payload);
+
+        // Stored even though it does not fit `StripeInvoice`
+        let payload = json!({
+            "id": "evt_malformed",
+            "type": "invoice.paid",
+            "created": 1700000000,
+            "data": { "object": { "id": "in_test" } }
+        });
+        let event = WebhookEvent::try_from(payload.clone()).unwrap();
+        assert_eq!(event.payload(), &payload);
+
+        assert!(WebhookEvent::try_from(json!({ "type": "invoice.paid" })).is_err());
+
+        let payload = json!({
+            "id": "evt_checkout",
+            "type": "checkout.session.completed",
+            "created": 1700000000,
+            "data": { "object": { "id": "cs_test", "client_secret": "cs_test_secret" } }
+        });
+        let event = WebhookEvent::try_from(payload).unwrap();
+        assert_eq!(
+            event.payload()["data"]["object"],
+            json!({ "id": "cs_test" })
+        );
+    }
+
+    #[test]
+    fn test_deserialize_price_event() {
+        let payload = json!({
+            "id": "evt_price_updated",
+            "type": "price.updated",
+            "created": 1700000000,
+            "data": {
+                "object": {
+                    "id": "price_test",
+                    "object": "price",
+                    "product": "prod_test",
+                    "active": false,
+                    "currency": "eur",
+                    "unit_amount": 990,
+                    "nickname": null,
+                    "recurring": { "interval": "month", "interval_count": 1 },
+                    "metadata": { "max_quantity": "10" }
+                }
+            }
+        });
+        let event: StripeEvent = serde_json::from_value(payload).unwrap();
+        let price = match event {
+            StripeEvent::PriceUpdated(event) => Price::from(event.data.object),
+            other => panic!("unexpected event {:?}", other),
+        };
+        assert_eq!(price.product_id(), "prod_test");
+        assert!(!price.is_active());
+        assert_eq!(price.unit_amount(), Some(990));
+        assert_eq!(price.recurring_interval(), Some("month"));
+        assert_eq!(price.recurring_interval_count(), Some(1));
+        assert_eq!(price.min_quantity(), 1);
+        assert_eq!(price.max_quantity(), Some(10));
+    }
+
+    #[test]
+    fn test_checkout_session_form() {
+        use crate::domain::payment::entities::checkout::LineItem;
+        use crate::domain::payment::value_objects::checkout_mode::CheckoutMode;
+
+        let field = |form: &CheckoutSessionForm, key: &str| {
+            form.data
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.clone())
+        };
+
+        let checkout = CheckoutSession::new(
+            "cus_test".to_string(),
+            None,
+            CheckoutMode::Subscription,
+            UiMode::Hosted,
+            vec![LineItem {
+                price: "price_test".to_string(),
+                quantity: 1,
+            }],
+            None,
+            None,
+            None,
+            Some(7),
+        );
+        let form = CheckoutSessionForm::try_from(&checkout).unwrap();
+        assert_eq!(field(&form, "mode").as_deref(), Some("subscription"));
+        assert_eq!(field(&form, "ui_mode").as_deref(), Some("hosted"));
+        assert_eq!(
+            field(&form, "subscription_data[trial_period_days]").as_deref(),
+            Some("7")
+        );
+
+        let checkout = CheckoutSession::new(
+            "cus_test".to_string(),
+            None,
+            CheckoutMode::Payment,
+            UiMode::Embedded,
+            vec![],
+            Some("https://example.com/success".to_string()),
+            None,
+            Some("https://example.com/return".to_string()),
+            Some(7),
+        );
+        let form = CheckoutSessionForm::try_from(&checkout).unwrap();
+        assert_eq!(field(&form, "mode").as_deref(), Some("payment"));
+        assert_eq!(field(&form, "ui_mode").as_deref(), Some("embedded"));
+        assert_eq!(
+            field(&form, "return_url").as_deref(),
+            Some("https://example.com/return")
+        );
+        assert_eq!(field(&form, "success_url"), None);
+        assert_eq!(field(&form, "subscription_data[trial_period_days]"), None);
+    }
+}
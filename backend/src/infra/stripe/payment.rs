@@ -1,9 +1,18 @@
+use crate::domain::catalog::entities::{Price, Product};
 use crate::domain::payment::client::PaymentClient;
-use crate::domain::payment::entities::checkout::CheckoutSession;
+use crate::domain::payment::entities::checkout::{
+    CheckoutSession, CheckoutSessionDetails, CheckoutSessionResult,
+};
 use crate::domain::payment::entities::customer::Customer;
 use crate::domain::payment::entities::portal::CustomerPortalSession;
-use crate::infra::stripe::models::{CheckoutSessionForm, GetCustomerResponse};
+use crate::domain::payment::entities::subscription::SubscriptionDetails;
+use crate::domain::payment::value_objects::ui_mode::UiMode;
+use crate::infra::stripe::models::{
+    CheckoutSessionForm, GetCustomerResponse, StripeCheckoutSession, StripeList, StripePrice,
+    StripeProduct, StripeSubscription,
+};
 use crate::prelude::*;
+use serde::de::DeserializeOwned;
 use serde_json::Value;
 use std::sync::Arc;
 
@@ -29,6 +38,53 @@ impl StripePaymentClient {
             headers,
         }
     }
+
+    /// Walks every page of a Stripe list endpoint. `id_of` returns the cursor
+    /// passed as `starting_after` for the next page.
+    async fn list_all<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        params: &[(&str, &str)],
+        id_of: fn(&T) -> &str,
+    ) -> Result<Vec<T>> {
+        let url = format!("{}/{}", self.base_url, path);
+        let mut items: Vec<T> = vec![];
+        loop {
+            let mut query = vec![("limit", "100".to_string())];
+            query.extend(params.iter().map(|(key, value)| (*key, value.to_string())));
+            if let Some(last) = items.last() {
+                query.push(("starting_after", id_of(last).to_string()));
+            }
+            let response = self
+                .http
+                .get(&url)
+                .basic_auth(&self.secret_key, Some(""))
+                .query(&query)
+                .send()
+                .await?;
+
+            let status = response.status();
+            if !status.is_success() {
+                let error_body = response
+                    .text()
+                    .await
+                    .unwrap_or_else(|_| "Failed to read error body".to_string());
+                tracing::error!("Failed to list {} (HTTP {}): {}", path, status, error_body);
+                let code = status.as_u16();
+                return Err(Error::ApiError(code, error_body));
+            }
+
+            let page = response.json::<StripeList<T>>().await.map_err(|e| {
+                tracing::error!("Failed to deserialize {} page: {:?}", path, e);
+                Error::DeserializationError(format!("Failed to list {}", path))
+            })?;
+            let has_more = page.has_more && !page.data.is_empty();
+            items.extend(page.data);
+            if !has_more {
+                return Ok(items);
+            }
+        }
+    }
 }
 
 impl PaymentClient for StripePaymentClient {
@@ -100,7 +156,10 @@ impl PaymentClient for StripePaymentClient {
             Err(Error::ApiError(code, error_body))
         }
     }
-    async fn create_checkout_session(&self, checkout: &CheckoutSession) -> Result<String> {
+    async fn create_checkout_session(
+        &self,
+        checkout: &CheckoutSession,
+    ) -> Result<CheckoutSessionResult> {
         let url = format!("{}/checkout/sessions", self.base_url);
         let form_data = CheckoutSessionForm::try_from(checkout)?;
 
@@ -116,15 +175,34 @@ impl PaymentClient for StripePaymentClient {
         let status = response.status();
 
         if status.is_success() {
-            let response = response.json::<Value>().await?;
-            let session = response["url"]
-                .as_str()
-                .ok_or_else(|| {
-                    tracing::error!("Failed to create checkout session: URL not found");
+            let session = response
+                .json::<StripeCheckoutSession>()
+                .await
+                .map_err(|e| {
+                    tracing::error!("Failed to create checkout session: {:?}", e);
                     Error::DeserializationError("Failed to create checkout session".to_string())
-                })?
-                .to_string();
-            Ok(session)
+                })?;
+            // Hosted sessions are opened by url, embedded ones by client secret.
+            let missing = match checkout.ui_mode() {
+                UiMode::Hosted => session.url.is_none(),
+                UiMode::Embedded => session.client_secret.is_none(),
+            };
+            if missing {
+                tracing::error!(
+                    "Checkout session {} has no {} entry point",
+                    session.id,
+                    checkout.ui_mode()
+                );
+                return Err(Error::DeserializationError(
+                    "Failed to create checkout session".to_string(),
+                ));
+            }
+            Ok(CheckoutSessionResult {
+                id: session.id,
+                url: session.url,
+                client_secret: session.client_secret,
+                ui_mode: checkout.ui_mode(),
+            })
         } else {
             let error_body = response
                 .text()
@@ -139,6 +217,49 @@ impl PaymentClient for StripePaymentClient {
             Err(Error::ApiError(code, error_body))
         }
     }
+    async fn get_checkout_session(&self, session_id: &str) -> Result<CheckoutSessionDetails> {
+        let url = format!(
+            "{}/checkout/sessions/{}",
+            self.base_url,
+            path_id(session_id)?
+        );
+        let response = self
+            .http
+            .get(&url)
+            .basic_auth(&self.secret_key, Some(""))
+            .send()
+            .await?;
+
+        let status = response.status();
+
+        if status.is_success() {
+            let session = response
+                .json::<StripeCheckoutSession>()
+                .await
+                .map_err(|e| {
+                    tracing::error!("Failed to deserialize checkout session: {:?}", e);
+                    Error::DeserializationError("Failed to get checkout session".to_string())
+                })?;
+            Ok(CheckoutSessionDetails::from(session))
+        } else if status == reqwest::StatusCode::NOT_FOUND {
+            Err(Error::NotFound(format!(
+                "Checkout session {} not found",
+                session_id
+            )))
+        } else {
+            let error_body = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Failed to read error body".to_string());
+            tracing::error!(
+                "Failed to get checkout session (HTTP {}): {}",
+                status,
+                error_body
+            );
+            let code = status.as_u16();
+            Err(Error::ApiError(code, error_body))
+        }
+    }
     async fn create_portal_session(&self, portal: &CustomerPortalSession) -> Result<String> {
         let url = format!("{}/billing_portal/sessions", self.base_url);
         let response = self
@@ -180,4 +301,92 @@ impl PaymentClient for StripePaymentClient {
             Err(Error::ApiError(code, error_body))
         }
     }
+    async fn get_subscription(&self, subscription_id: &str) -> Result<SubscriptionDetails> {
+        let url = format!(
+            "{}/subscriptions/{}",
+            self.base_url,
+            path_id(subscription_id)?
+        );
+        let response = self
+            .http
+            .get(&url)
+            .basic_auth(&self.secret_key, Some(""))
+            .send()
+            .await?;
+
+        let status = response.status();
+
+        if status.is_success() {
+            let subscription = response.json::<StripeSubscription>().await.map_err(|e| {
+                tracing::error!("Failed to deserialize subscription: {:?}", e);
+                Error::DeserializationError("Failed to get subscription".to_string())
+            })?;
+            SubscriptionDetails::try_from(subscription)
+        } else {
+            let error_body = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Failed to read error body".to_string());
+            tracing::error!(
+                "Failed to get subscription (HTTP {}): {}",
+                status,
+                error_body
+            );
+            let code = status.as_u16();
+            Err(Error::ApiError(code, error_body))
+        }
+    }
+    async fn list_subscriptions(&self, customer_id: &str) -> Result<Vec<SubscriptionDetails>> {
+        let subscriptions = self
+            .list_all::<StripeSubscription>(
+                "subscriptions",
+                &[("customer", customer_id), ("status", "all")],
+                |subscription| &subscription.id,
+            )
+            .await?;
+        subscriptions
+            .into_iter()
+            .map(SubscriptionDetails::try_from)
+            .collect()
+    }
+    async fn list_products(&self) -> Result<Vec<Product>> {
+        let products = self
+            .list_all::<StripeProduct>("products", &[], |product| &product.id)
+            .await?;
+        Ok(products.into_iter().map(Product::from).collect())
+    }
+    async fn list_prices(&self) -> Result<Vec<Price>> {
+        let prices = self
+            .list_all::<StripePrice>("prices", &[], |price| &price.id)
+            .await?;
+        Ok(prices.into_iter().map(Price::from).collect())
+    }
+}
+
+/// Stripe ids only contain `[A-Za-z0-9_]`. Anything else is rejected before
+/// it is put into a request path, where it could point at another endpoint.
+fn path_id(id: &str) -> Result<&str> {
+    if !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        Ok(id)
+    } else {
+        Err(Error::BadRequest(format!("Invalid Stripe id {:?}", id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_id() {
+        assert_eq!(path_id("cs_test_a1B2").unwrap(), "cs_test_a1B2");
+        for id in [
+            "",
+            "cs_1/../../customers",
+            "cs_1?expand=customer",
+            "cs_1%2F",
+        ] {
+            assert!(matches!(path_id(id), Err(Error::BadRequest(_))), "{}", id);
+        }
+    }
 }
@@ -2,28 +2,53 @@ use crate::domain::subscription::service::SignatureVerificationService;
 use crate::prelude::{Error, Result};
 use hmac::{Hmac, Mac};
 use sha2::Sha256;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 type HmacSha256 = Hmac<Sha256>;
 
 #[derive(Clone)]
 pub struct StripeSignatureVerificationService {
-    secret: String,
-    tolerance: u64,
+    /// Every secret currently accepted. While a secret is rolled, Stripe signs
+    /// each event with both the old and the new one.
+    secrets: Vec<String>,
+    tolerance: Duration,
 }
 impl StripeSignatureVerificationService {
-    pub fn new(secret: &str) -> Self {
+    pub fn new<S: AsRef<str>>(secrets: &[S], tolerance: Duration) -> Self {
         Self {
-            secret: secret.to_string(),
-            tolerance: 300,
+            secrets: secrets.iter().map(|s| s.as_ref().to_string()).collect(),
+            tolerance,
         }
     }
 }
 
+/// HMAC-SHA256 of `"{timestamp}.{payload}"` keyed with the endpoint secret.
+fn signed_payload_mac(secret: &str, timestamp: &str, payload: &[u8]) -> Result<HmacSha256> {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).map_err(|e| {
+        tracing::error!("HMAC initialization error: {}", e);
+        Error::InternalError
+    })?;
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(payload);
+    Ok(mac)
+}
+
+/// The `Stripe-Signature` header Stripe would send with `payload` at
+/// `timestamp`, for replaying events and for tests.
+pub fn signature_header(secret: &str, timestamp: i64, payload: &str) -> Result<String> {
+    let mac = signed_payload_mac(secret, &timestamp.to_string(), payload.as_bytes())?;
+    Ok(format!(
+        "t={},v1={}",
+        timestamp,
+        hex::encode(mac.finalize().into_bytes())
+    ))
+}
+
 impl SignatureVerificationService for StripeSignatureVerificationService {
-    fn verify(&self, payload: &str, signature_header: &str) -> Result<()> {
+    fn verify(&self, payload: &[u8], signature_header: &str) -> Result<()> {
         let mut timestamp = None;
-        let mut signature = None;
+        let mut signatures = Vec::new();
 
         for part in signature_header.split(',') {
             let kv: Vec<&str> = part.splitn(2, '=').collect();
@@ -36,7 +61,12 @@ impl SignatureVerificationService for StripeSignatureVerificationService {
 
             match kv[0].trim() {
                 "t" => timestamp = Some(kv[1].trim()),
-                "v1" => signature = Some(kv[1].trim()),
+                "v1" => {
+                    // Malformed entries can never match, so they are not kept
+                    if let Ok(signature) = hex::decode(kv[1].trim()) {
+                        signatures.push(signature);
+                    }
+                }
                 _ => continue,
             }
         }
@@ -44,9 +74,11 @@ impl SignatureVerificationService for StripeSignatureVerificationService {
         let timestamp = timestamp.ok_or(Error::InvalidSignature(
             "Missing timestamp in signature header".to_string(),
         ))?;
-        let signature = signature.ok_or(Error::InvalidSignature(
-            "Missing v1 signature in signature header".to_string(),
-        ))?;
+        if signatures.is_empty() {
+            return Err(Error::InvalidSignature(
+                "Missing v1 signature in signature header".to_string(),
+            ));
+        }
 
         // Convert timestamp to u64 and verify it's not too old
         let timestamp_secs: u64 = timestamp
@@ -58,77 +90,99 @@ impl SignatureVerificationService for StripeSignatureVerificationService {
             .map_err(|_| Error::InvalidSignature("System time error".to_string()))?
             .as_secs();
 
-        if current_time < timestamp_secs || current_time - timestamp_secs > self.tolerance {
+        if current_time < timestamp_secs || current_time - timestamp_secs > self.tolerance.as_secs()
+        {
             return Err(Error::InvalidSignature(format!(
                 "Timestamp {} outside tolerance window (current: {})",
                 timestamp_secs, current_time
             )));
         }
 
-        // Construct the signed payload per Stripe's specification
-        let signed_payload = format!("{}.{}", timestamp, payload);
-
-        // Compute HMAC-SHA256 signature
-        let mut mac = HmacSha256::new_from_slice(self.secret.as_bytes()).map_err(|e| {
-            tracing::error!("HMAC initialization error: {}", e);
-            Error::InternalError
-        })?;
-        mac.update(signed_payload.as_bytes());
-        let computed_signature = hex::encode(mac.finalize().into_bytes());
-
-        // Compare signatures (Stripe uses hex-encoded strings)
-        if computed_signature != signature {
-            return Err(Error::InvalidSignature(format!(
-                "Signature mismatch (expected: {}, got: {})",
-                computed_signature, signature
-            )));
+        // `verify_slice` compares in constant time
+        for secret in &self.secrets {
+            let mac = signed_payload_mac(secret, timestamp, payload)?;
+            if signatures
+                .iter()
+                .any(|signature| mac.clone().verify_slice(signature).is_ok())
+            {
+                return Ok(());
+            }
         }
 
-        Ok(())
+        Err(Error::InvalidSignature(
+            "No v1 signature matches an active webhook secret".to_string(),
+        ))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    
+
+    const TOLERANCE: Duration = Duration::from_secs(300);
+
+    fn now() -> i64 {
+        chrono::Utc::now().timestamp()
+    }
 
     #[test]
     fn test_verify_signature() {
-        let service = StripeSignatureVerificationService::new("whsec_test_secret");
+        let service = StripeSignatureVerificationService::new(&["whsec_test_secret"], TOLERANCE);
+
+        let payload = r#"{"id":"evt_test_event"}"#;
+        let timestamp = now();
+        let header = signature_header("whsec_test_secret", timestamp, payload).unwrap();
+        assert!(service.verify(payload.as_bytes(), &header).is_ok());
+
+        // Tampered payload
+        let tampered = r#"{"id":"evt_other_event"}"#;
+        let error = service.verify(tampered.as_bytes(), &header).unwrap_err();
+        assert!(matches!(error, Error::InvalidSignature(_)));
+        // The expected signature is not disclosed
+        let expected = signature_header("whsec_test_secret", timestamp, tampered).unwrap();
+        let expected = expected.split("v1=").nth(1).unwrap();
+        assert!(!error.to_string().contains(expected));
+
+        // Too old
+        let header = signature_header("whsec_test_secret", now() - 301, payload).unwrap();
+        assert!(service.verify(payload.as_bytes(), &header).is_err());
+    }
 
+    #[test]
+    fn test_verify_signature_during_secret_roll() {
         let payload = r#"{"id":"evt_test_event"}"#;
-        let timestamp = "1612210817";
-
-        let signed_payload = format!("{}.{}", timestamp, payload);
-
-        let mut mac = HmacSha256::new_from_slice("whsec_test_secret".as_bytes()).unwrap();
-        mac.update(signed_payload.as_bytes());
-        let signature = hex::encode(mac.finalize().into_bytes());
-
-        let signature_header = format!("t={},v1={}", timestamp, signature);
-
-        assert!(service.verify(payload, &signature_header).is_ok());
-
-        // Test with invalid payload
-
-        // let payload = r#"{"id":"evt_test_event"}"#;
-        //
-        // let signed_payload = format!("{}.{}", timestamp, payload);
-        //
-        // let mut mac = HmacSha256::new_from_slice("whsec_test
-        //
-        //     _secret".as_bytes()).unwrap();
-        //
-        // mac.update(signed_payload.as_bytes());
-        //
-        // let signature = hex::encode(mac.finalize().into_bytes());
-        //
-        // let signature_header = format!("t={},v1={}", timestamp, signature);
-        //
-        // assert_eq!(
-        //     service.verify(payload, &signature_header),
-        //     Err(Error::InvalidSignature("Signature mismatch".to_string()))
-        // );
+        let timestamp = now();
+        let old = signature_header("whsec_old", timestamp, payload).unwrap();
+        let new = signature_header("whsec_new", timestamp, payload).unwrap();
+        let new_signature = new.split("v1=").nth(1).unwrap();
+        // Stripe lists every signature of the event in one header
+        let header = format!("{},v1={},v0=ignored", old, new_signature);
+
+        let rolled = StripeSignatureVerificationService::new(&["whsec_new"], TOLERANCE);
+        assert!(rolled.verify(payload.as_bytes(), &header).is_ok());
+        let rolling =
+            StripeSignatureVerificationService::new(&["whsec_other", "whsec_old"], TOLERANCE);
+        assert!(rolling.verify(payload.as_bytes(), &old).is_ok());
+        let other = StripeSignatureVerificationService::new(&["whsec_other"], TOLERANCE);
+        assert!(other.verify(payload.as_bytes(), &header).is_err());
+
+        let header = format!("t={},v1=not-hex,v1={}", timestamp, new_signature);
+        assert!(rolled.verify(payload.as_bytes(), &header).is_ok());
+    }
+
+    #[test]
+    fn test_verify_signature_on_raw_bytes() {
+        let service = StripeSignatureVerificationService::new(&["whsec_test_secret"], TOLERANCE);
+
+        // Not UTF-8, and verified as sent all the same
+        let payload = b"{\"name\":\"\xff\"}";
+        let timestamp = now().to_string();
+        let mac = signed_payload_mac("whsec_test_secret", &timestamp, payload).unwrap();
+        let header = format!(
+            "t={},v1={}",
+            timestamp,
+            hex::encode(mac.finalize().into_bytes())
+        );
+        assert!(service.verify(payload, &header).is_ok());
     }
 }
@@ -1,6 +1,9 @@
-use crate::infra::cli::Args;
+use crate::application::catalog::use_cases::SyncCatalogUseCase;
+use crate::application::user::middleware::authenticate;
+use crate::infra::cli::{Args, Command, WebhookCommand};
 use crate::infra::config::Config;
 use crate::infra::dependencies::AppState;
+use crate::infra::replay;
 use crate::presentation::routers;
 use actix_cors::Cors;
 use actix_web::web::{scope, Data};
@@ -18,7 +21,27 @@ pub async fn run() -> std::io::Result<()> {
     )
     .init();
 
+    if let Some(Command::Webhook {
+        command: WebhookCommand::Replay(replay_args),
+    }) = args.command
+    {
+        return replay::run(config, replay_args)
+            .await
+            .map_err(std::io::Error::other);
+    }
+
     let app_state = AppState::new(config.clone());
+    tokio::spawn(app_state.webhook_worker().run());
+
+    if args.sync_catalog {
+        let use_case = SyncCatalogUseCase::new(
+            app_state.catalog_service.clone(),
+            app_state.payment_service.clone(),
+        );
+        if let Err(e) = use_case.execute().await {
+            tracing::error!("Catalog sync failed: {}", e);
+        }
+    }
 
     let cors_origin = config.app().cors_origin.clone();
 
@@ -30,14 +53,18 @@ pub async fn run() -> std::io::Result<()> {
             .max_age(3600);
 
         App::new()
+            .wrap(middleware::from_fn(authenticate))
             .wrap(middleware::Logger::default())
             .wrap(middleware::Compress::default())
             .wrap(cors)
             .app_data(Data::new(app_state.clone()))
+            .app_data(Data::new(app_state.webhook_receiver.clone()))
             .service(scope("/v1/payment").configure(routers::payment::routes))
+            .service(scope("/v1/admin").configure(routers::admin::routes))
             .service(
                 scope("/v1")
                     .configure(routers::probes::routes)
+                    .configure(routers::catalog::routes)
                     .configure(routers::users::routes),
             )
     })
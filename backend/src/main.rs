@@ -6,7 +6,6 @@ mod infra;
 mod prelude;
 mod presentation;
 mod schema;
-mod shared;
 
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
@@ -1,5 +1,8 @@
+use crate::domain::user::value_objects::user_status::UserStatus;
+use actix_web::http::header::ContentType;
 use actix_web::http::StatusCode;
-use actix_web::ResponseError;
+use actix_web::{HttpResponse, ResponseError};
+use serde::Serialize;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -13,6 +16,10 @@ pub enum Error {
     InvalidToken(String),
     #[error("Unauthorized")]
     Unauthorized,
+    #[error("Forbidden. Cause: {0}")]
+    Forbidden(String),
+    #[error("Account is {0}")]
+    AccountDisabled(UserStatus, Option<String>),
     #[error("Conversion Error. Cause: {0}")]
     ConversionError(String),
     #[error("Parsing Error. Cause: {0}")]
@@ -51,6 +58,8 @@ pub enum Error {
     BadRequest(String),
     #[error("Record already exists")]
     RecordAlreadyExists,
+    #[error("Identity conflict. Cause: {0}")]
+    IdentityConflict(String),
 
     #[error("Invalid currency. Cause: {0}")]
     InvalidCurrency(String),
@@ -61,6 +70,12 @@ pub enum Error {
     #[error("Invalid subscription status. Cause: {0}")]
     InvalidSubscriptionStatus(String),
 
+    #[error("Stale event. Cause: {0}")]
+    StaleEvent(String),
+
+    #[error("Validation failed: {}", .0.iter().map(|e| e.to_string()).collect::<Vec<_>>().join(", "))]
+    Validation(Vec<ValidationError>),
+
     #[error("Internal error")]
     InternalError,
 
@@ -72,6 +87,12 @@ pub enum Error {
 
     #[error("Failed to serialize data. Cause: {0}")]
     Serialization(String),
+
+    #[error("I/O error. Cause: {0}")]
+    Io(String),
+
+    #[error("Payload too large. Limit: {0} bytes")]
+    PayloadTooLarge(usize),
 }
 
 impl ResponseError for Error {
@@ -79,9 +100,70 @@ impl ResponseError for Error {
         match self {
             Self::NotFound(_) => StatusCode::NOT_FOUND,
             Self::InvalidToken(_) => StatusCode::UNAUTHORIZED,
+            Self::InvalidSignature(_) => StatusCode::UNAUTHORIZED,
             Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::Forbidden(_) => StatusCode::FORBIDDEN,
+            Self::AccountDisabled(..) => StatusCode::FORBIDDEN,
             Self::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Self::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::Validation(_) => StatusCode::BAD_REQUEST,
+            Self::StaleEvent(_) => StatusCode::CONFLICT,
+            Self::IdentityConflict(_) => StatusCode::CONFLICT,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
+
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            Self::Validation(errors) => {
+                HttpResponse::build(self.status_code()).json(ValidationErrorBody {
+                    error: "validation_failed",
+                    details: errors,
+                })
+            }
+            Self::AccountDisabled(status, reason) => {
+                HttpResponse::build(self.status_code()).json(AccountDisabledBody {
+                    error: format!("account_{}", status),
+                    reason: reason.as_deref(),
+                })
+            }
+            _ => HttpResponse::build(self.status_code())
+                .insert_header(ContentType::plaintext())
+                .body(self.to_string()),
+        }
+    }
+}
+
+/// A single rejected input field, reported back to the client as-is.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationError {
+    pub field: String,
+    pub message: String,
+}
+impl ValidationError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+#[derive(Serialize)]
+struct ValidationErrorBody<'a> {
+    error: &'static str,
+    details: &'a [ValidationError],
+}
+
+/// Lets clients tell a locked account (`account_banned`, `account_pending`,
+/// ...) apart from a bad token.
+#[derive(Serialize)]
+struct AccountDisabledBody<'a> {
+    error: String,
+    reason: Option<&'a str>,
 }
@@ -0,0 +1,104 @@
+use crate::application::admin::dtos::{ChangeUserRoleDto, SubscriptionListQuery, UserListQuery};
+use crate::application::admin::use_cases::{
+    ChangeUserRoleUseCase, GetAdminUserUseCase, ListSubscriptionsUseCase, ListUsersUseCase,
+    ResyncUserUseCase,
+};
+use crate::application::user::dtos::ChangeUserStatusDto;
+use crate::application::user::extractor::permissions::{ManageRoles, ManageUsers, ReadUsers};
+use crate::application::user::extractor::RequirePermission;
+use crate::application::user::use_cases::{ChangeUserStatusUseCase, GetUserStatusChangesUseCase};
+use crate::infra::dependencies::AppState;
+use crate::prelude::*;
+use actix_web::{get, post, put, web, HttpResponse, Responder};
+use uuid::Uuid;
+
+#[get("/users")]
+pub async fn list_users(
+    _admin: RequirePermission<ReadUsers>,
+    state: web::Data<AppState>,
+    query: web::Query<UserListQuery>,
+) -> Result<impl Responder> {
+    let use_case = ListUsersUseCase::new(state.user_service.clone());
+    let page = use_case.execute(query.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(page))
+}
+
+#[get("/users/{id}")]
+pub async fn get_user(
+    _admin: RequirePermission<ReadUsers>,
+    state: web::Data<AppState>,
+    user_id: web::Path<Uuid>,
+) -> Result<impl Responder> {
+    let use_case = GetAdminUserUseCase::new(
+        state.user_service.clone(),
+        state.subscription_service.clone(),
+    );
+    let user = use_case.execute(&user_id).await?;
+    Ok(HttpResponse::Ok().json(user))
+}
+
+#[put("/users/{id}/role")]
+pub async fn change_user_role(
+    admin: RequirePermission<ManageRoles>,
+    state: web::Data<AppState>,
+    user_id: web::Path<Uuid>,
+    change: web::Json<ChangeUserRoleDto>,
+) -> Result<impl Responder> {
+    let use_case = ChangeUserRoleUseCase::new(state.user_service.clone());
+    let user = use_case.execute(&admin.0, &user_id, change.role).await?;
+    Ok(HttpResponse::Ok().json(user))
+}
+
+#[post("/users/{id}/resync")]
+pub async fn resync_user(
+    _admin: RequirePermission<ManageUsers>,
+    state: web::Data<AppState>,
+    user_id: web::Path<Uuid>,
+) -> Result<impl Responder> {
+    let use_case = ResyncUserUseCase::new(
+        state.unit_of_work.clone(),
+        state.user_service.clone(),
+        state.subscription_service.clone(),
+        state.payment_service.clone(),
+    );
+    let user = use_case.execute(&user_id).await?;
+    Ok(HttpResponse::Ok().json(user))
+}
+
+#[put("/users/{id}/status")]
+pub async fn change_user_status(
+    admin: RequirePermission<ManageUsers>,
+    state: web::Data<AppState>,
+    user_id: web::Path<Uuid>,
+    change: web::Json<ChangeUserStatusDto>,
+) -> Result<impl Responder> {
+    let service = state.user_service.clone();
+    let use_case = ChangeUserStatusUseCase::new(service);
+    let user = use_case
+        .execute(&admin.0, &user_id, change.into_inner())
+        .await?;
+    Ok(HttpResponse::Ok().json(user))
+}
+
+#[get("/users/{id}/status-changes")]
+pub async fn get_user_status_changes(
+    _admin: RequirePermission<ReadUsers>,
+    state: web::Data<AppState>,
+    user_id: web::Path<Uuid>,
+) -> Result<impl Responder> {
+    let service = state.user_service.clone();
+    let use_case = GetUserStatusChangesUseCase::new(service);
+    let changes = use_case.execute(&user_id).await?;
+    Ok(HttpResponse::Ok().json(changes))
+}
+
+#[get("/subscriptions")]
+pub async fn list_subscriptions(
+    _admin: RequirePermission<ReadUsers>,
+    state: web::Data<AppState>,
+    query: web::Query<SubscriptionListQuery>,
+) -> Result<impl Responder> {
+    let use_case = ListSubscriptionsUseCase::new(state.subscription_service.clone());
+    let page = use_case.execute(query.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(page))
+}
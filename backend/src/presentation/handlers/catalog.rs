@@ -0,0 +1,10 @@
+use crate::infra::dependencies::AppState;
+use crate::prelude::*;
+use actix_web::{get, web, HttpResponse, Responder};
+
+#[get("/plans")]
+pub async fn list_plans(state: web::Data<AppState>) -> Result<impl Responder> {
+    let service = state.catalog_service.clone();
+    let plans = service.list_plans().await?;
+    Ok(HttpResponse::Ok().json(plans))
+}
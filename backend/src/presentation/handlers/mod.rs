@@ -1,3 +1,5 @@
-pub(super) mod payment;
+pub(super) mod admin;
+pub(super) mod catalog;
+pub(crate) mod payment;
 pub(super) mod probes;
 pub(super) mod users;
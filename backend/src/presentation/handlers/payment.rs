@@ -1,18 +1,16 @@
 use crate::application::payment::dto::{NewCheckoutSessionDto, NewPortalDto};
-use crate::application::payment::event_use_cases::UpdateUserEvent;
 use crate::application::payment::use_cases::{
-    CreateCheckoutSessionUseCase, CreatePortalSessionUseCase,
+    CreateCheckoutSessionUseCase, CreatePortalSessionUseCase, GetCheckoutSessionUseCase,
 };
-use crate::application::subscription::extractors::SignatureVerifier;
-use crate::application::subscription::use_cases::{
-    InvoicePaidUseCase, InvoicePaymentFailedUseCase, SubscriptionCanceledUseCase,
-    SubscriptionUpdatedUseCase,
-};
-use crate::application::user::extractor::UserExtractor;
-use crate::domain::payment::entities::customer::Customer;
+use crate::application::subscription::extractors::{SignatureVerifier, Stripe};
+use crate::application::subscription::receiver::WebhookReceiver;
+use crate::application::user::extractor::permissions::ManageOwnBilling;
+use crate::application::user::extractor::RequirePermission;
+use crate::domain::subscription::repository::WebhookEventRepository;
+use crate::domain::subscription::service::SignatureVerificationService;
 use crate::infra::dependencies::AppState;
 use crate::prelude::*;
-use actix_web::{post, web, HttpResponse, Responder};
+use actix_web::{get, post, web, HttpResponse, Responder};
 use serde_json::Value;
 
 //
@@ -44,13 +42,17 @@ use serde_json::Value;
 
 #[post("/checkout/sessions")]
 pub async fn create_checkout_session(
-    user: UserExtractor,
+    user: RequirePermission<ManageOwnBilling>,
     state: web::Data<AppState>,
     new_checkout: web::Json<NewCheckoutSessionDto>,
 ) -> Result<impl Responder> {
     let user = user.0;
     let service = state.payment_service.clone();
-    let use_case = CreateCheckoutSessionUseCase::new(service);
+    let use_case = CreateCheckoutSessionUseCase::new(
+        service,
+        state.catalog_service.clone(),
+        state.checkout_settings.clone(),
+    );
     let new_checkout = new_checkout.into_inner();
     match use_case.execute(user, new_checkout).await {
         Ok(checkout) => Ok(HttpResponse::Created().json(checkout)),
@@ -58,9 +60,22 @@ pub async fn create_checkout_session(
     }
 }
 
+#[get("/checkout/sessions/{id}")]
+pub async fn get_checkout_session(
+    user: RequirePermission<ManageOwnBilling>,
+    state: web::Data<AppState>,
+    session_id: web::Path<String>,
+) -> Result<impl Responder> {
+    let user = user.0;
+    let service = state.payment_service.clone();
+    let use_case = GetCheckoutSessionUseCase::new(service);
+    let session = use_case.execute(user, &session_id).await?;
+    Ok(HttpResponse::Ok().json(session))
+}
+
 #[post("/portal/sessions")]
 pub async fn create_portal_session(
-    user: UserExtractor,
+    user: RequirePermission<ManageOwnBilling>,
     state: web::Data<AppState>,
     new_portal: web::Json<NewPortalDto>,
 ) -> Result<impl Responder> {
@@ -74,60 +89,17 @@ pub async fn create_portal_session(
     }
 }
 
-#[post("/webhook")]
-pub async fn payment_webhook(
-    state: web::Data<AppState>,
-    verified: SignatureVerifier<Value>,
-) -> Result<impl Responder> {
-    let body = &verified.0;
-    let event_type = body["type"]
-        .as_str()
-        .ok_or(Error::BadRequest("Invalid event type".to_string()))?;
-    match event_type {
-        "customer.created" => {
-            tracing::info!("customer.created event received");
-            let customer: Customer =
-                serde_json::from_value(body["data"]["object"].clone()).unwrap();
-            let use_case = UpdateUserEvent::new(state.user_service.clone());
-            use_case.execute(customer).await?;
-        }
-        "invoice.paid" => {
-            tracing::info!("invoice.paid event received");
-            let data = body["data"]["object"].clone();
-            let use_case = InvoicePaidUseCase::new(
-                state.subscription_service.clone(),
-                state.user_service.clone(),
-            );
-            use_case.execute(data).await?;
-        }
-        "invoice.payment_failed" => {
-            tracing::info!("invoice.payment_failed event received");
-            let data = body["data"]["object"].clone();
-            let use_case = InvoicePaymentFailedUseCase::new(
-                state.subscription_service.clone(),
-                state.user_service.clone(),
-            );
-            use_case.execute(data).await?;
-        }
-        "customer.subscription.updated" => {
-            tracing::info!("customer.subscription.updated event received");
-            let data = body["data"]["object"].clone();
-            let use_case = SubscriptionUpdatedUseCase::new(
-                state.subscription_service.clone(),
-                state.user_service.clone(),
-            );
-            use_case.execute(data).await?;
-        }
-        "customer.subscription.deleted" => {
-            tracing::info!("customer.subscription.deleted event received");
-            let data = body["data"]["object"].clone();
-            let use_case = SubscriptionCanceledUseCase::new(
-                state.subscription_service.clone(),
-                state.user_service.clone(),
-            );
-            use_case.execute(data).await?;
-        }
-        _ => {}
-    }
+/// Generic over the event store, so tests can mount it over in-memory fakes.
+pub async fn payment_webhook<R, S>(
+    receiver: web::Data<WebhookReceiver<R, S>>,
+    verified: SignatureVerifier<Value, Stripe<R, S>>,
+) -> Result<impl Responder>
+where
+    R: WebhookEventRepository + 'static,
+    S: SignatureVerificationService + 'static,
+{
+    // Processing happens in the background worker; we only need the event
+    // durably stored before acknowledging it to Stripe.
+    receiver.record(verified.0).await?;
     Ok(HttpResponse::Ok().finish())
 }
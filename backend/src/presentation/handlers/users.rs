@@ -1,5 +1,8 @@
 use crate::application::user::dtos::UpdateUserDto;
-use crate::application::user::extractor::{Authenticate, UserExtractor};
+use crate::application::user::extractor::permissions::{
+    ManageOwnAccount, ManageOwnBilling, ReadOwnAccount,
+};
+use crate::application::user::extractor::{Authenticate, RequirePermission};
 use crate::application::user::use_cases::{DeleteUserUseCase, LoginUseCase, UpdateUserUseCase};
 use crate::infra::dependencies::AppState;
 use crate::prelude::*;
@@ -20,7 +23,7 @@ pub async fn login(auth: Authenticate, state: web::Data<AppState>) -> Result<imp
 
 #[patch("/users")]
 pub async fn update_user(
-    user: UserExtractor,
+    user: RequirePermission<ManageOwnAccount>,
     updates: web::Json<UpdateUserDto>,
     state: web::Data<AppState>,
 ) -> Result<impl Responder> {
@@ -38,7 +41,7 @@ pub async fn update_user(
 
 #[delete("/users")]
 pub async fn delete_user(
-    user: UserExtractor,
+    user: RequirePermission<ManageOwnAccount>,
     state: web::Data<AppState>,
 ) -> Result<impl Responder> {
     let service = state.user_service.clone();
@@ -48,14 +51,14 @@ pub async fn delete_user(
 }
 
 #[get("/users")]
-pub async fn get_user(user: UserExtractor) -> Result<impl Responder> {
+pub async fn get_user(user: RequirePermission<ReadOwnAccount>) -> Result<impl Responder> {
     // This will retrieve the user from the extractor by using auth provider id
     Ok(HttpResponse::Ok().json(user.0))
 }
 
 #[get("/users/me/subscription")]
 pub async fn get_user_subscription(
-    user: UserExtractor,
+    user: RequirePermission<ManageOwnBilling>,
     state: web::Data<AppState>,
 ) -> Result<impl Responder> {
     let user = user.0;
@@ -0,0 +1,11 @@
+use crate::presentation::handlers::admin;
+
+pub fn routes(cfg: &mut actix_web::web::ServiceConfig) {
+    cfg.service(admin::list_users)
+        .service(admin::get_user)
+        .service(admin::change_user_role)
+        .service(admin::change_user_status)
+        .service(admin::get_user_status_changes)
+        .service(admin::resync_user)
+        .service(admin::list_subscriptions);
+}
@@ -0,0 +1,5 @@
+use crate::presentation::handlers::catalog;
+
+pub fn routes(cfg: &mut actix_web::web::ServiceConfig) {
+    cfg.service(catalog::list_plans);
+}
@@ -1,3 +1,5 @@
+pub mod admin;
+pub mod catalog;
 pub mod payment;
 pub mod probes;
 pub mod users;
@@ -1,10 +1,16 @@
+use crate::infra::dependencies::{WebhookEvents, WebhookSignatures};
 use crate::presentation::handlers::payment;
+use actix_web::web;
 
 pub fn routes(cfg: &mut actix_web::web::ServiceConfig) {
     cfg
         // .service(payment::create_customer)
         // .service(payment::get_customer)
         .service(payment::create_checkout_session)
+        .service(payment::get_checkout_session)
         .service(payment::create_portal_session)
-        .service(payment::payment_webhook);
+        .route(
+            "/webhook",
+            web::post().to(payment::payment_webhook::<WebhookEvents, WebhookSignatures>),
+        );
 }
@@ -4,12 +4,12 @@ table! {
     users (id) {
         id -> Uuid,
         email -> Varchar,
-        firebase_id -> Varchar,
         stripe_customer_id -> Nullable<Varchar>,
         status -> Varchar,
         role -> Varchar,
         created_at -> Timestamptz,
         updated_at -> Nullable<Timestamptz>,
+        status_reason -> Nullable<Varchar>,
     }
 }
 
@@ -41,14 +41,91 @@ table! {
         canceled_at -> Nullable<Timestamptz>,
         created_at -> Timestamptz,
         updated_at -> Nullable<Timestamptz>,
+        last_event_at -> Nullable<Timestamptz>,
     }
 }
 
+table! {
+    webhook_events (id) {
+        id -> Varchar,
+        event_type -> Varchar,
+        payload -> Jsonb,
+        received_at -> Timestamptz,
+        processed_at -> Nullable<Timestamptz>,
+        attempts -> Int4,
+        last_error -> Nullable<Varchar>,
+        status -> Varchar,
+        next_attempt_at -> Timestamptz,
+    }
+}
+
+table! {
+    prices (id) {
+        id -> Varchar,
+        product_id -> Varchar,
+        active -> Bool,
+        currency -> Varchar,
+        unit_amount -> Nullable<Int8>,
+        nickname -> Nullable<Varchar>,
+        recurring_interval -> Nullable<Varchar>,
+        recurring_interval_count -> Nullable<Int4>,
+        created_at -> Timestamptz,
+        updated_at -> Nullable<Timestamptz>,
+        min_quantity -> Int4,
+        max_quantity -> Nullable<Int4>,
+        last_event_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    products (id) {
+        id -> Varchar,
+        name -> Varchar,
+        description -> Nullable<Varchar>,
+        active -> Bool,
+        created_at -> Timestamptz,
+        updated_at -> Nullable<Timestamptz>,
+        last_event_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    user_identities (id) {
+        id -> Int4,
+        user_id -> Uuid,
+        provider -> Varchar,
+        subject -> Varchar,
+        email -> Varchar,
+        email_verified -> Bool,
+        created_at -> Timestamptz,
+        updated_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    user_status_changes (id) {
+        id -> Int4,
+        user_id -> Uuid,
+        actor_id -> Nullable<Uuid>,
+        old_status -> Varchar,
+        new_status -> Varchar,
+        reason -> Nullable<Varchar>,
+        created_at -> Timestamptz,
+    }
+}
+
+joinable!(prices -> products (product_id));
 joinable!(profiles -> users (user_id));
 joinable!(subscriptions -> users (user_id));
+joinable!(user_identities -> users (user_id));
 
 allow_tables_to_appear_in_same_query!(
     users,
     profiles,
     subscriptions,
+    webhook_events,
+    products,
+    prices,
+    user_identities,
+    user_status_changes,
 );
\ No newline at end of file
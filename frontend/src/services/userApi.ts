@@ -10,9 +10,9 @@ export type Profile = {
 export type User  = {
     id: number;
     email: string;
-    firebaseId: string;
     stripeCustomerId: string;
     status: string;
+    statusReason?: string;
     role: string;
     profile: Profile;
 }